@@ -1,13 +1,18 @@
-use std::process::Command;
+use std::{env, path::PathBuf, process::Command};
 use tonic_build_extend::BuilderExt;
 
 fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
     tonic_build::configure()
         .out_dir("src/pb")
+        // served by the reflection service
+        .file_descriptor_set_path(out_dir.join("reservation_descriptor.bin"))
         .types_attributes(
             &[
                 "reservation.ReservationQuery",
                 "reservation.ReservationFilter",
+                "reservation.ReservationSearch",
             ],
             &[
                 "#[derive(derive_builder::Builder)]",
@@ -15,12 +20,18 @@ fn main() {
             ],
         )
         .fields_attributes(
-            &["reservation.ReservationQuery"],
+            &[
+                "reservation.ReservationQuery",
+                "reservation.ReservationSearch",
+            ],
             &["start", "end"],
             &["#[builder(setter(strip_option))]"],
         )
         .fields_attributes(
-            &["reservation.ReservationQuery"],
+            &[
+                "reservation.ReservationQuery",
+                "reservation.ReservationSearch",
+            ],
             &["page"],
             &[r#"#[builder(default = "1")]"#],
         )
@@ -28,6 +39,7 @@ fn main() {
             &[
                 "reservation.ReservationQuery",
                 "reservation.ReservationFilter",
+                "reservation.ReservationSearch",
             ],
             &["page_size"],
             &[r#"#[builder(default = "10")]"#],
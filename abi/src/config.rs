@@ -1,4 +1,4 @@
-use std::fs;
+use std::{collections::HashMap, fs};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +8,16 @@ use crate::Error;
 pub struct Config {
     pub db: DbConfig,
     pub server: ServerConfig,
+
+    /// authentication is disabled if not set, the user id of the requests is trusted as is
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+
+    #[serde(default)]
+    pub reminder: ReminderConfig,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -46,6 +56,223 @@ fn default_max_connects() -> u32 {
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+
+    /// port of the REST/JSON gateway on the same host, not served if not set.
+    /// It is served with TLS when the gRPC server is, with the same certificate
+    #[serde(default)]
+    pub http_port: Option<u16>,
+
+    /// serve gRPC-Web for browser clients besides gRPC on the same port, accepting HTTP/1.1
+    #[serde(default)]
+    pub grpc_web: bool,
+
+    /// origins browsers may call gRPC-Web and the REST gateway from, e.g.
+    /// https://app.example.com, "*" for any. Cross-origin calls are refused if empty
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// server certificate file (pem)
+    pub cert: String,
+    /// server private key file (pem)
+    pub key: String,
+    /// ca certificate file (pem) to verify client certificates with, enables mTLS
+    #[serde(default)]
+    pub client_ca: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// accept bearer JWTs
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+
+    /// accept client certificates, the common name of the subject is the user id
+    #[serde(default)]
+    pub mtls: bool,
+
+    /// who may do what, callers only manage their own reservations by default
+    #[serde(default)]
+    pub policy: PolicyConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PolicyConfig {
+    /// users allowed to do anything
+    #[serde(default)]
+    pub admins: Vec<String>,
+
+    /// resource id -> users who may see, confirm and reject its reservations
+    #[serde(default)]
+    pub resource_managers: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JwtAlgorithm {
+    HS256,
+    RS256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JwtConfig {
+    pub algorithm: JwtAlgorithm,
+    /// shared secret for HS256
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// public key file (pem) for RS256
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// expected `iss` claim
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// expected `aud` claim
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+/// how the webhook worker delivers the changes
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// how often the outbox is read when idle
+    #[serde(default = "default_webhook_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// timeout of a single POST
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+    /// a delivery is moved to the dead letters after this many failed attempts
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: i32,
+    /// wait before the first retry, doubled for every other one
+    #[serde(default = "default_webhook_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_webhook_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// deliver to loopback, link-local and private addresses too. Refused by default so a
+    /// webhook can't reach the internal network, only allow it for local testing
+    #[serde(default)]
+    pub allow_private_urls: bool,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: default_webhook_poll_interval_ms(),
+            timeout_ms: default_webhook_timeout_ms(),
+            max_attempts: default_webhook_max_attempts(),
+            initial_backoff_ms: default_webhook_initial_backoff_ms(),
+            max_backoff_ms: default_webhook_max_backoff_ms(),
+            allow_private_urls: false,
+        }
+    }
+}
+
+fn default_webhook_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_webhook_max_attempts() -> i32 {
+    8
+}
+
+fn default_webhook_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_webhook_max_backoff_ms() -> u64 {
+    3_600_000
+}
+
+/// when the reservations are reminded and how the reminders are sent
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReminderConfig {
+    /// how often the reminders are scheduled and the due ones sent
+    #[serde(default = "default_reminder_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// minutes before the start the reservations are reminded at, unless their resource type
+    /// has its own
+    #[serde(default = "default_reminder_offsets_min")]
+    pub offsets_min: Vec<u32>,
+    /// resource type -> minutes before the start its reservations are reminded at
+    #[serde(default)]
+    pub resource_types: HashMap<String, Vec<u32>>,
+    /// a reminder is given up on after this many failed attempts
+    #[serde(default = "default_reminder_max_attempts")]
+    pub max_attempts: i32,
+    /// wait before trying a failed reminder again
+    #[serde(default = "default_reminder_retry_interval_ms")]
+    pub retry_interval_ms: u64,
+    #[serde(default)]
+    pub notifier: NotifierConfig,
+}
+
+impl ReminderConfig {
+    /// minutes before the start the reservations of the resource type are reminded at
+    pub fn offsets(&self, resource_type: &str) -> &[u32] {
+        self.resource_types
+            .get(resource_type)
+            .unwrap_or(&self.offsets_min)
+    }
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: default_reminder_poll_interval_ms(),
+            offsets_min: default_reminder_offsets_min(),
+            resource_types: HashMap::new(),
+            max_attempts: default_reminder_max_attempts(),
+            retry_interval_ms: default_reminder_retry_interval_ms(),
+            notifier: NotifierConfig::default(),
+        }
+    }
+}
+
+/// where the reminders are sent
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    /// printed to stdout
+    #[default]
+    Log,
+    /// POSTed as JSON, signed like the webhook deliveries
+    Webhook { url: String, secret: String },
+    /// appended to the file as JSON lines
+    File { path: String },
+    /// mailed to the user, user ids without a domain are mailed at `domain`. The connection is
+    /// upgraded with STARTTLS unless `insecure`, meant for a local stand-in only
+    Smtp {
+        host: String,
+        port: u16,
+        from: String,
+        domain: String,
+        #[serde(default)]
+        insecure: bool,
+    },
+}
+
+fn default_reminder_poll_interval_ms() -> u64 {
+    10_000
+}
+
+fn default_reminder_offsets_min() -> Vec<u32> {
+    vec![24 * 60, 15]
+}
+
+fn default_reminder_max_attempts() -> i32 {
+    5
+}
+
+fn default_reminder_retry_interval_ms() -> u64 {
+    60_000
 }
 
 impl Config {
@@ -81,5 +308,52 @@ mod tests {
         assert_eq!(config.db.dbname, "reservation");
         assert_eq!(config.server.host, "0.0.0.0");
         assert_eq!(config.server.port, 3333);
+        assert_eq!(config.server.http_port, None);
+        assert!(!config.server.grpc_web);
+        assert!(config.server.cors_origins.is_empty());
+        assert_eq!(config.server.tls, None);
+        assert_eq!(config.auth, None);
+        assert_eq!(config.webhook, WebhookConfig::default());
+        assert_eq!(config.reminder, ReminderConfig::default());
+    }
+
+    #[test]
+    fn load_auth_config_should_work() {
+        let config = Config::load("../service/fixtures/config_auth.yaml").unwrap();
+        let auth = config.auth.unwrap();
+        let jwt = auth.jwt.unwrap();
+
+        assert!(auth.mtls);
+        assert_eq!(auth.policy.admins, vec!["alice".to_string()]);
+        assert_eq!(
+            auth.policy.resource_managers.get("room_01"),
+            Some(&vec!["bob".to_string()])
+        );
+        assert_eq!(jwt.algorithm, JwtAlgorithm::HS256);
+        assert_eq!(jwt.secret, Some("reservation-secret".to_string()));
+        assert_eq!(config.server.http_port, Some(8080));
+        assert!(config.server.grpc_web);
+        assert_eq!(
+            config.server.cors_origins,
+            vec!["https://app.example.com".to_string()]
+        );
+        assert_eq!(
+            config.server.tls.unwrap().client_ca,
+            Some("fixtures/tls/ca.pem".to_string())
+        );
+
+        let reminder = config.reminder;
+        assert_eq!(reminder.offsets("meeting_room"), &[60]);
+        assert_eq!(reminder.offsets("desk"), &[15]);
+        assert_eq!(
+            reminder.notifier,
+            NotifierConfig::Smtp {
+                host: "localhost".to_string(),
+                port: 2525,
+                from: "rsvp@example.com".to_string(),
+                domain: "example.com".to_string(),
+                insecure: true,
+            }
+        );
     }
 }
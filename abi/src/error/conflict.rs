@@ -1,14 +1,22 @@
-use std::{convert::Infallible, str::FromStr};
-
 use chrono::{DateTime, Utc};
-use prost_types::Timestamp;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{NativeRange, ReservationId, ReservationStatus, RsvpStatus};
 
-use crate::convert_to_utc_time;
+/// reservations overlapping the requested window on the same resource
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReservationConflictInfo {
+    pub conflicts: Vec<ReservationConflict>,
+}
 
+/// a reservation blocking the requested one
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ReservationConflictInfo {
-    Parsed(ReservationWindow),
-    Unparsed(String),
+pub struct ReservationConflict {
+    pub id: ReservationId,
+    /// None if the caller is not allowed to see who holds the reservation
+    pub user_id: Option<String>,
+    pub status: ReservationStatus,
+    pub window: ReservationWindow,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -24,57 +32,32 @@ impl ReservationWindow {
     }
 }
 
-impl FromStr for ReservationWindow {
-    type Err = String;
-
-    /// "Key (resource_id, timespan)=(resource_id, [\"2024-01-02 07:00:00+00\",
-    /// \"2024-01-04 07:00:00+00\")) conflicts with existing key (resource_id_a, timespan)
-    /// =(resource_id_b, [\"2024-01-01 07:00:00+00\",\"2024-01-03 07:00:00+00\"))
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = regex::Regex::new(r"(?:=\((?<old>[^\(\(]*)\){2})\.?$").unwrap();
-        if let Some(caps) = re.captures(s) {
-            let split = caps["old"].splitn(2, ',').collect::<Vec<_>>();
-
-            let rid = split[0].trim().to_string();
-            let timespan = split[1].trim().to_string().replace(['[', '"'], "");
-
-            let timespan = timespan.splitn(2, ',').collect::<Vec<_>>();
-            let start = timespan[0].trim().to_string();
-            let end = timespan[1].trim().to_string();
-
-            Ok(ReservationWindow::new(
-                rid,
-                convert_to_utc_time(&Timestamp::from_str(&start).unwrap()),
-                convert_to_utc_time(&Timestamp::from_str(&end).unwrap()),
-            ))
-        } else {
-            Err(s.to_string())
-        }
+impl ReservationConflictInfo {
+    pub fn new(conflicts: Vec<ReservationConflict>) -> Self {
+        Self { conflicts }
     }
-}
 
-impl FromStr for ReservationConflictInfo {
-    type Err = Infallible;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let window = ReservationWindow::from_str(s);
-        if let Ok(window) = window {
-            return Ok(ReservationConflictInfo::Parsed(window));
-        }
-
-        Ok(ReservationConflictInfo::Unparsed(s.to_string()))
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    fn test_parse() {
-        let s = r#"Key (resource_id, timespan)=(resource_id, ["2024-01-02 07:00:00+00", "2024-01-04 07:00:00+00")) conflicts with existing key (resource_id, timespan)=(resource_id, ["2024-01-01 07:00:00+00","2024-01-03 07:00:00+00"))"#;
-        let info: ReservationConflictInfo = s.parse::<ReservationConflictInfo>().unwrap();
-        assert!(matches!(info, ReservationConflictInfo::Parsed(_)));
+impl FromRow<'_, PgRow> for ReservationConflict {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let range: NativeRange<DateTime<Utc>> = row
+            .get::<sqlx::postgres::types::PgRange<DateTime<Utc>>, _>("timespan")
+            .into();
+        let status: RsvpStatus = row.get("status");
+
+        Ok(Self {
+            id: row.get("id"),
+            user_id: Some(row.get("user_id")),
+            status: ReservationStatus::from(status),
+            window: ReservationWindow::new(
+                row.get("resource_id"),
+                range.start.unwrap_or_default(),
+                range.end.unwrap_or_default(),
+            ),
+        })
     }
 }
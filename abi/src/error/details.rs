@@ -0,0 +1,295 @@
+use prost::Message;
+use prost_types::Any;
+use tonic_types::pb::{bad_request::FieldViolation, BadRequest, ErrorInfo};
+
+use crate::{convert_to_timestamp, ConflictDetail, Error};
+
+/// domain of the `ErrorInfo` details sent by the reservation service
+pub const ERROR_DOMAIN: &str = "reservation";
+
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const BAD_REQUEST_TYPE_URL: &str = "type.googleapis.com/google.rpc.BadRequest";
+const CONFLICT_DETAIL_TYPE_URL: &str = "type.googleapis.com/reservation.ConflictDetail";
+
+/// rich error details (google.rpc error model) carried by the status of a failed rpc.
+/// the server builds them from `Error`, clients decode them with `StatusDetails::from_status`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusDetails {
+    pub error_info: Option<ErrorInfo>,
+    pub field_violations: Vec<FieldViolation>,
+    pub conflicts: Vec<ConflictDetail>,
+}
+
+impl StatusDetails {
+    /// decode the details of a status, unknown or malformed details are skipped
+    pub fn from_status(status: &tonic::Status) -> Self {
+        let mut details = Self::default();
+
+        let Ok(rpc_status) = tonic_types::pb::Status::decode(status.details()) else {
+            return details;
+        };
+
+        for any in rpc_status.details {
+            match any.type_url.as_str() {
+                ERROR_INFO_TYPE_URL => {
+                    details.error_info = ErrorInfo::decode(any.value.as_slice()).ok();
+                }
+                BAD_REQUEST_TYPE_URL => {
+                    if let Ok(bad_request) = BadRequest::decode(any.value.as_slice()) {
+                        details
+                            .field_violations
+                            .extend(bad_request.field_violations);
+                    }
+                }
+                CONFLICT_DETAIL_TYPE_URL => {
+                    if let Ok(conflict) = ConflictDetail::decode(any.value.as_slice()) {
+                        details.conflicts.push(conflict);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        details
+    }
+
+    /// the reason of the error info, e.g. `RESERVATION_CONFLICT`
+    pub fn reason(&self) -> Option<&str> {
+        self.error_info.as_ref().map(|info| info.reason.as_str())
+    }
+
+    /// attach the details to a status, keeping its code and message
+    pub fn attach(self, status: tonic::Status) -> tonic::Status {
+        let mut details = vec![];
+
+        if let Some(info) = self.error_info {
+            details.push(to_any(ERROR_INFO_TYPE_URL, &info));
+        }
+
+        if !self.field_violations.is_empty() {
+            let bad_request = BadRequest {
+                field_violations: self.field_violations,
+            };
+            details.push(to_any(BAD_REQUEST_TYPE_URL, &bad_request));
+        }
+
+        for conflict in &self.conflicts {
+            details.push(to_any(CONFLICT_DETAIL_TYPE_URL, conflict));
+        }
+
+        let rpc_status = tonic_types::pb::Status {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            details,
+        };
+
+        tonic::Status::with_details(
+            status.code(),
+            status.message(),
+            rpc_status.encode_to_vec().into(),
+        )
+    }
+}
+
+impl From<&Error> for StatusDetails {
+    fn from(e: &Error) -> Self {
+        let mut info = ErrorInfo {
+            reason: e.reason().to_string(),
+            domain: ERROR_DOMAIN.to_string(),
+            metadata: Default::default(),
+        };
+
+        let mut conflicts = vec![];
+
+        if let Error::ConflictReservation(conflict) = e {
+            if let Some(first) = conflict.conflicts.first() {
+                info.metadata
+                    .insert("resource_id".to_string(), first.window.rid.clone());
+            }
+
+            conflicts = conflict
+                .conflicts
+                .iter()
+                .map(|c| ConflictDetail {
+                    id: c.id,
+                    resource_id: c.window.rid.clone(),
+                    start: Some(convert_to_timestamp(c.window.start)),
+                    end: Some(convert_to_timestamp(c.window.end)),
+                    user_id: c.user_id.clone().unwrap_or_default(),
+                    status: c.status as i32,
+                })
+                .collect();
+        }
+
+        let field_violations = e
+            .invalid_fields()
+            .iter()
+            .map(|field| FieldViolation {
+                field: field.to_string(),
+                description: e.to_string(),
+            })
+            .collect();
+
+        Self {
+            error_info: Some(info),
+            field_violations,
+            conflicts,
+        }
+    }
+}
+
+impl Error {
+    /// stable, machine readable reason of the error
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Error::Unknown => "UNKNOWN",
+            Error::ReadConfigError => "READ_CONFIG_ERROR",
+            Error::ParseConfigError => "PARSE_CONFIG_ERROR",
+            Error::DbError(_) => "DATABASE_ERROR",
+            Error::ConflictReservation(_) => "RESERVATION_CONFLICT",
+            Error::NotFound => "NOT_FOUND",
+            Error::InvalidTime => "INVALID_TIME",
+            Error::InvalidUserId(_) => "INVALID_USER_ID",
+            Error::InvalidReservationId(_) => "INVALID_RESERVATION_ID",
+            Error::InvalidResourceId(_) => "INVALID_RESOURCE_ID",
+            Error::InvalidStatus(_) => "INVALID_STATUS",
+            Error::InvalidOrderBy(_) => "INVALID_ORDER_BY",
+            Error::InvalidCursor(_) => "INVALID_CURSOR",
+            Error::InvalidSearchQuery(_) => "INVALID_SEARCH_QUERY",
+            Error::InvalidLabel(_) => "INVALID_LABEL",
+            Error::InvalidLabelSelector(_) => "INVALID_LABEL_SELECTOR",
+            Error::MissingField(_) => "MISSING_FIELD",
+            Error::Unauthenticated(_) => "UNAUTHENTICATED",
+            Error::PermissionDenied(_) => "PERMISSION_DENIED",
+            Error::InvalidTenant(_) => "INVALID_TENANT",
+            Error::InvalidResource(_) => "INVALID_RESOURCE",
+            Error::ApprovalRequired(_) => "APPROVAL_REQUIRED",
+            Error::NotAwaitingApproval(_) => "NOT_AWAITING_APPROVAL",
+            Error::InvalidChangedField(_) => "INVALID_CHANGED_FIELD",
+            Error::InvalidWebhook(_) => "INVALID_WEBHOOK",
+            Error::InvalidIcal(_) => "INVALID_ICAL",
+            Error::InvalidRecord(_) => "INVALID_RECORD",
+            Error::InvalidJson(_) => "INVALID_JSON",
+        }
+    }
+
+    /// request fields a validation error is about
+    fn invalid_fields(&self) -> Vec<&str> {
+        match self {
+            Error::InvalidTime => vec!["start", "end"],
+            Error::InvalidUserId(_) => vec!["user_id"],
+            Error::InvalidReservationId(_) => vec!["id"],
+            Error::InvalidResourceId(_) => vec!["resource_id"],
+            Error::InvalidStatus(_) => vec!["status"],
+            Error::InvalidOrderBy(_) => vec!["order_by"],
+            Error::InvalidCursor(_) => vec!["cursor_token"],
+            Error::InvalidSearchQuery(_) => vec!["query"],
+            Error::InvalidLabel(_) => vec!["labels"],
+            Error::InvalidLabelSelector(_) => vec!["label_selector"],
+            Error::MissingField(field) => vec![field.as_str()],
+            Error::InvalidResource(_) => vec!["resource"],
+            Error::InvalidChangedField(_) => vec!["changed_fields"],
+            Error::InvalidWebhook(_) => vec!["webhook"],
+            Error::InvalidIcal(_) => vec!["ical"],
+            Error::InvalidRecord(_) => vec!["data"],
+            Error::InvalidJson(_) => vec!["body"],
+            _ => vec![],
+        }
+    }
+}
+
+fn to_any(type_url: &str, msg: &impl Message) -> Any {
+    Any {
+        type_url: type_url.to_string(),
+        value: msg.encode_to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ReservationConflict, ReservationConflictInfo, ReservationStatus, ReservationWindow,
+    };
+
+    #[test]
+    fn conflict_status_should_carry_conflict_detail() {
+        let window = ReservationWindow::new(
+            "room_01".to_string(),
+            "2024-01-01T07:00:00+00:00".parse().unwrap(),
+            "2024-01-03T07:00:00+00:00".parse().unwrap(),
+        );
+        let conflicts = vec![
+            ReservationConflict {
+                id: 1,
+                user_id: Some("john".to_string()),
+                status: ReservationStatus::Confirmed,
+                window: window.clone(),
+            },
+            ReservationConflict {
+                id: 2,
+                user_id: None,
+                status: ReservationStatus::Pending,
+                window: window.clone(),
+            },
+        ];
+        let status: tonic::Status =
+            Error::ConflictReservation(ReservationConflictInfo::new(conflicts)).into();
+
+        assert_eq!(status.code(), tonic::Code::AlreadyExists);
+        assert_eq!(
+            status.message(),
+            "reservation conflicts with 2 existing reservations"
+        );
+
+        let details = StatusDetails::from_status(&status);
+
+        assert_eq!(details.reason(), Some("RESERVATION_CONFLICT"));
+        assert_eq!(
+            details.error_info.unwrap().metadata.get("resource_id"),
+            Some(&"room_01".to_string())
+        );
+        assert_eq!(
+            details.conflicts,
+            vec![
+                ConflictDetail {
+                    id: 1,
+                    resource_id: "room_01".to_string(),
+                    start: Some(convert_to_timestamp(window.start)),
+                    end: Some(convert_to_timestamp(window.end)),
+                    user_id: "john".to_string(),
+                    status: ReservationStatus::Confirmed as i32,
+                },
+                ConflictDetail {
+                    id: 2,
+                    resource_id: "room_01".to_string(),
+                    start: Some(convert_to_timestamp(window.start)),
+                    end: Some(convert_to_timestamp(window.end)),
+                    user_id: String::new(),
+                    status: ReservationStatus::Pending as i32,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validation_status_should_carry_field_violations() {
+        let status: tonic::Status = Error::InvalidUserId("".to_string()).into();
+        let details = StatusDetails::from_status(&status);
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(details.reason(), Some("INVALID_USER_ID"));
+        assert_eq!(details.field_violations.len(), 1);
+        assert_eq!(details.field_violations[0].field, "user_id");
+    }
+
+    #[test]
+    fn status_without_details_should_decode_to_empty() {
+        let status = tonic::Status::internal("boom");
+
+        assert_eq!(
+            StatusDetails::from_status(&status),
+            StatusDetails::default()
+        );
+    }
+}
@@ -1,9 +1,11 @@
 mod conflict;
+mod details;
 
 use sqlx::postgres::PgDatabaseError;
 use thiserror::Error;
 
-pub use conflict::{ReservationConflictInfo, ReservationWindow};
+pub use conflict::{ReservationConflict, ReservationConflictInfo, ReservationWindow};
+pub use details::{StatusDetails, ERROR_DOMAIN};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -39,6 +41,57 @@ pub enum Error {
 
     #[error("Invalid status: {0}")]
     InvalidStatus(i32),
+
+    #[error("Invalid order by: {0}")]
+    InvalidOrderBy(i32),
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Invalid search query: {0}")]
+    InvalidSearchQuery(String),
+
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Invalid label selector: {0}")]
+    InvalidLabelSelector(String),
+
+    #[error("Missing required field: {0}")]
+    MissingField(String),
+
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Invalid tenant: {0}")]
+    InvalidTenant(String),
+
+    #[error("Invalid resource: {0}")]
+    InvalidResource(String),
+
+    #[error("Reservation {0} requires approval")]
+    ApprovalRequired(i64),
+
+    #[error("Reservation {0} is not waiting for approval")]
+    NotAwaitingApproval(i64),
+
+    #[error("Invalid changed field: {0}")]
+    InvalidChangedField(String),
+
+    #[error("Invalid webhook: {0}")]
+    InvalidWebhook(String),
+
+    #[error("Invalid iCalendar: {0}")]
+    InvalidIcal(String),
+
+    #[error("Invalid record: {0}")]
+    InvalidRecord(String),
+
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
 }
 
 impl PartialEq for Error {
@@ -55,6 +108,23 @@ impl PartialEq for Error {
             (Self::InvalidReservationId(v1), Self::InvalidReservationId(v2)) => v1 == v2,
             (Self::InvalidResourceId(v1), Self::InvalidResourceId(v2)) => v1 == v2,
             (Self::InvalidStatus(v1), Self::InvalidStatus(v2)) => v1 == v2,
+            (Self::InvalidOrderBy(v1), Self::InvalidOrderBy(v2)) => v1 == v2,
+            (Self::InvalidCursor(v1), Self::InvalidCursor(v2)) => v1 == v2,
+            (Self::InvalidSearchQuery(v1), Self::InvalidSearchQuery(v2)) => v1 == v2,
+            (Self::InvalidLabel(v1), Self::InvalidLabel(v2)) => v1 == v2,
+            (Self::InvalidLabelSelector(v1), Self::InvalidLabelSelector(v2)) => v1 == v2,
+            (Self::MissingField(v1), Self::MissingField(v2)) => v1 == v2,
+            (Self::Unauthenticated(v1), Self::Unauthenticated(v2)) => v1 == v2,
+            (Self::PermissionDenied(v1), Self::PermissionDenied(v2)) => v1 == v2,
+            (Self::InvalidTenant(v1), Self::InvalidTenant(v2)) => v1 == v2,
+            (Self::InvalidResource(v1), Self::InvalidResource(v2)) => v1 == v2,
+            (Self::ApprovalRequired(v1), Self::ApprovalRequired(v2)) => v1 == v2,
+            (Self::NotAwaitingApproval(v1), Self::NotAwaitingApproval(v2)) => v1 == v2,
+            (Self::InvalidChangedField(v1), Self::InvalidChangedField(v2)) => v1 == v2,
+            (Self::InvalidWebhook(v1), Self::InvalidWebhook(v2)) => v1 == v2,
+            (Self::InvalidIcal(v1), Self::InvalidIcal(v2)) => v1 == v2,
+            (Self::InvalidRecord(v1), Self::InvalidRecord(v2)) => v1 == v2,
+            (Self::InvalidJson(v1), Self::InvalidJson(v2)) => v1 == v2,
             _ => false,
         }
     }
@@ -66,8 +136,9 @@ impl From<sqlx::Error> for Error {
             sqlx::Error::Database(e) => {
                 let err: &PgDatabaseError = e.downcast_ref();
                 match (err.code(), err.schema(), err.table()) {
+                    // the conflicting reservations are looked up by the caller, see `Rsvp::reserve`
                     ("23P01", Some("rsvp"), Some("reservations")) => {
-                        Error::ConflictReservation(err.detail().unwrap().parse().unwrap())
+                        Error::ConflictReservation(ReservationConflictInfo::default())
                     }
                     _ => Error::DbError(sqlx::Error::Database(e)),
                 }
@@ -80,13 +151,19 @@ impl From<sqlx::Error> for Error {
 
 impl From<crate::Error> for tonic::Status {
     fn from(e: crate::Error) -> Self {
-        match e {
+        let details = StatusDetails::from(&e);
+
+        let status = match e {
             crate::Error::Unknown => tonic::Status::internal("Unknown error"),
             crate::Error::DbError(_) => tonic::Status::internal("Database error"),
             crate::Error::ReadConfigError => tonic::Status::internal("Read config error"),
             crate::Error::ParseConfigError => tonic::Status::internal("Parse config error"),
             crate::Error::ConflictReservation(info) => {
-                let msg = format!("Conflict reservation: {:?}", info);
+                // the conflicting reservations are in the details
+                let msg = format!(
+                    "reservation conflicts with {} existing reservations",
+                    info.conflicts.len()
+                );
                 tonic::Status::already_exists(msg)
             }
             crate::Error::NotFound => {
@@ -103,6 +180,48 @@ impl From<crate::Error> for tonic::Status {
                 tonic::Status::invalid_argument("Invalid resource id")
             }
             crate::Error::InvalidStatus(_) => tonic::Status::invalid_argument("Invalid status"),
-        }
+            crate::Error::InvalidOrderBy(_) => tonic::Status::invalid_argument("Invalid order by"),
+            crate::Error::InvalidCursor(_) => tonic::Status::invalid_argument("Invalid cursor"),
+            crate::Error::InvalidSearchQuery(_) => {
+                tonic::Status::invalid_argument("Invalid search query")
+            }
+            crate::Error::InvalidLabel(_) => tonic::Status::invalid_argument("Invalid label"),
+            crate::Error::InvalidLabelSelector(_) => {
+                tonic::Status::invalid_argument("Invalid label selector")
+            }
+            crate::Error::MissingField(field) => {
+                tonic::Status::invalid_argument(format!("{} is required", field))
+            }
+            crate::Error::Unauthenticated(msg) => tonic::Status::unauthenticated(msg),
+            crate::Error::PermissionDenied(msg) => tonic::Status::permission_denied(msg),
+            crate::Error::InvalidTenant(_) => tonic::Status::invalid_argument("Invalid tenant"),
+            crate::Error::InvalidResource(msg) => {
+                tonic::Status::invalid_argument(format!("Invalid resource: {}", msg))
+            }
+            crate::Error::ApprovalRequired(id) => {
+                tonic::Status::failed_precondition(format!("Reservation {} requires approval", id))
+            }
+            crate::Error::NotAwaitingApproval(id) => tonic::Status::failed_precondition(format!(
+                "Reservation {} is not waiting for approval",
+                id
+            )),
+            crate::Error::InvalidChangedField(field) => {
+                tonic::Status::invalid_argument(format!("Invalid changed field: {}", field))
+            }
+            crate::Error::InvalidWebhook(msg) => {
+                tonic::Status::invalid_argument(format!("Invalid webhook: {}", msg))
+            }
+            crate::Error::InvalidIcal(msg) => {
+                tonic::Status::invalid_argument(format!("Invalid iCalendar: {}", msg))
+            }
+            crate::Error::InvalidRecord(msg) => {
+                tonic::Status::invalid_argument(format!("Invalid record: {}", msg))
+            }
+            crate::Error::InvalidJson(msg) => {
+                tonic::Status::invalid_argument(format!("Invalid JSON: {}", msg))
+            }
+        };
+
+        details.attach(status)
     }
 }
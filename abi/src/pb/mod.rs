@@ -2,3 +2,7 @@
 mod reservation;
 
 pub use self::reservation::*;
+
+/// encoded FileDescriptorSet of the reservation service, described by the reflection service
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/reservation_descriptor.bin"));
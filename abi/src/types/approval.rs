@@ -0,0 +1,48 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{convert_to_timestamp, Approval, ApprovalDecision};
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "approval_decision", rename_all = "lowercase")]
+pub enum RsvpApprovalDecision {
+    Approved,
+    Rejected,
+}
+
+impl From<RsvpApprovalDecision> for ApprovalDecision {
+    fn from(decision: RsvpApprovalDecision) -> Self {
+        match decision {
+            RsvpApprovalDecision::Approved => ApprovalDecision::Approved,
+            RsvpApprovalDecision::Rejected => ApprovalDecision::Rejected,
+        }
+    }
+}
+
+impl FromRow<'_, PgRow> for Approval {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let decision: RsvpApprovalDecision = row.get("decision");
+        let created_at: DateTime<Utc> = row.get("created_at");
+
+        Ok(Self {
+            id: row.get("id"),
+            reservation_id: row.get("reservation_id"),
+            approver: row.get("approver"),
+            decision: ApprovalDecision::from(decision) as i32,
+            comment: row.get("comment"),
+            created_at: Some(convert_to_timestamp(created_at)),
+        })
+    }
+}
+
+impl fmt::Display for ApprovalDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalDecision::Unknown => write!(f, "unknown"),
+            ApprovalDecision::Approved => write!(f, "approved"),
+            ApprovalDecision::Rejected => write!(f, "rejected"),
+        }
+    }
+}
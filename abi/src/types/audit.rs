@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{
+    convert_to_struct, convert_to_timestamp, AuditEntry, AuditQuery, Error, ReservationType,
+    Validator,
+};
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "reservation_update_type", rename_all = "lowercase")]
+pub enum RsvpUpdateType {
+    Unknown,
+    Create,
+    Update,
+    Delete,
+}
+
+impl From<RsvpUpdateType> for ReservationType {
+    fn from(op: RsvpUpdateType) -> Self {
+        match op {
+            RsvpUpdateType::Unknown => ReservationType::Unknown,
+            RsvpUpdateType::Create => ReservationType::Create,
+            RsvpUpdateType::Update => ReservationType::Update,
+            RsvpUpdateType::Delete => ReservationType::Delete,
+        }
+    }
+}
+
+impl Validator for AuditQuery {
+    fn validate(&self) -> Result<(), Error> {
+        if self.reservation_id < 0 {
+            return Err(Error::InvalidReservationId(self.reservation_id));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for AuditEntry {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let op: RsvpUpdateType = row.get("op");
+        let created_at: DateTime<Utc> = row.get("created_at");
+        let before: Option<serde_json::Value> = row.get("before");
+        let after: Option<serde_json::Value> = row.get("after");
+        let text =
+            |name: &str| -> String { row.get::<Option<String>, _>(name).unwrap_or_default() };
+
+        Ok(Self {
+            id: row.get("id"),
+            reservation_id: row
+                .get::<Option<i64>, _>("reservation_id")
+                .unwrap_or_default(),
+            resource_id: row.get("resource_id"),
+            user_id: text("user_id"),
+            actor: text("actor"),
+            tenant_id: row.get("tenant_id"),
+            request_id: text("request_id"),
+            rpc: text("rpc"),
+            reason: text("reason"),
+            op: ReservationType::from(op) as i32,
+            created_at: Some(convert_to_timestamp(created_at)),
+            before: before.and_then(convert_to_struct),
+            after: after.and_then(convert_to_struct),
+        })
+    }
+}
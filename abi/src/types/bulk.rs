@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use prost_types::Timestamp;
+use serde_json::{json, Value};
+
+use crate::{
+    convert_to_json, convert_to_struct, convert_to_timestamp, convert_to_utc_time, validate_range,
+    BulkFormat, ConflictPolicy, Error, ExportRequest, ImportError, ImportRequest, LabelSelector,
+    Reservation, ReservationStatus, Validator,
+};
+
+/// columns of a CSV export, an import needs resource_id, user_id, start and end
+pub const CSV_COLUMNS: [&str; 10] = [
+    "id",
+    "tenant_id",
+    "resource_id",
+    "user_id",
+    "status",
+    "start",
+    "end",
+    "note",
+    "labels",
+    "attributes",
+];
+
+/// a row of an import: the line it starts on and the reservation it holds
+pub type ImportRow = (u64, Result<Reservation, Error>);
+
+impl Reservation {
+    /// the reservation as a JSON object, times in RFC 3339
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "resource_id": self.resource_id,
+            "user_id": self.user_id,
+            "status": status_name(self.status),
+            "start": self.start.as_ref().map(format_time),
+            "end": self.end.as_ref().map(format_time),
+            "note": self.note,
+            "labels": self.labels,
+            "attributes": self.attributes.as_ref().map(convert_to_json),
+            "tenant_id": self.tenant_id,
+        })
+    }
+
+    /// the reservation of a JSON object like the one of to_json, ids and tenants are left out
+    pub fn from_json(value: Value) -> Result<Self, Error> {
+        let Value::Object(mut fields) = value else {
+            return Err(Error::InvalidRecord("not a JSON object".to_string()));
+        };
+        let mut text = |name: &str| match fields.remove(name) {
+            None | Some(Value::Null) => Ok(String::new()),
+            Some(Value::String(s)) => Ok(s),
+            Some(_) => Err(Error::InvalidRecord(format!("{} is not a string", name))),
+        };
+
+        let resource_id = text("resource_id")?;
+        let user_id = text("user_id")?;
+        let status = parse_status(&text("status")?)?;
+        let start = parse_time(&text("start")?)?;
+        let end = parse_time(&text("end")?)?;
+        let note = text("note")?;
+        let labels = match fields.remove("labels") {
+            None | Some(Value::Null) => HashMap::new(),
+            Some(labels) => serde_json::from_value(labels).map_err(|_| {
+                Error::InvalidRecord("labels is not an object of strings".to_string())
+            })?,
+        };
+        let attributes =
+            match fields.remove("attributes") {
+                None | Some(Value::Null) => None,
+                Some(attributes) => Some(convert_to_struct(attributes).ok_or_else(|| {
+                    Error::InvalidRecord("attributes is not an object".to_string())
+                })?),
+            };
+
+        Ok(Self {
+            resource_id,
+            user_id,
+            status: status as i32,
+            start,
+            end,
+            note,
+            labels,
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    /// the reservation as the fields of a CSV row, in the order of CSV_COLUMNS
+    fn to_csv_record(&self) -> Vec<String> {
+        let time = |ts: Option<&Timestamp>| ts.map(format_time).unwrap_or_default();
+        let labels = match self.labels.is_empty() {
+            true => String::new(),
+            false => json!(self.labels).to_string(),
+        };
+        let attributes = self
+            .attributes
+            .as_ref()
+            .map(|attributes| convert_to_json(attributes).to_string())
+            .unwrap_or_default();
+
+        vec![
+            self.id.to_string(),
+            self.tenant_id.clone(),
+            self.resource_id.clone(),
+            self.user_id.clone(),
+            status_name(self.status),
+            time(self.start.as_ref()),
+            time(self.end.as_ref()),
+            self.note.clone(),
+            labels,
+            attributes,
+        ]
+    }
+}
+
+impl BulkFormat {
+    /// the header of an export, empty for formats without one
+    pub fn header(&self) -> String {
+        match self {
+            BulkFormat::Csv => write_csv([CSV_COLUMNS.map(String::from).to_vec()]),
+            BulkFormat::Ndjson => String::new(),
+        }
+    }
+
+    /// the reservations as rows of an export
+    pub fn encode(&self, rsvps: &[Reservation]) -> String {
+        match self {
+            BulkFormat::Csv => write_csv(rsvps.iter().map(Reservation::to_csv_record)),
+            BulkFormat::Ndjson => rsvps
+                .iter()
+                .map(|rsvp| format!("{}\n", rsvp.to_json()))
+                .collect(),
+        }
+    }
+
+    /// the rows of an import, each one validated on its own. Only data without the columns
+    /// a reservation needs fails as a whole
+    pub fn decode(&self, data: &str) -> Result<Vec<ImportRow>, Error> {
+        let rows = match self {
+            BulkFormat::Csv => decode_csv(data)?,
+            BulkFormat::Ndjson => data
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    let rsvp = serde_json::from_str(line)
+                        .map_err(|e| Error::InvalidRecord(e.to_string()))
+                        .and_then(Reservation::from_json);
+                    (i as u64 + 1, rsvp)
+                })
+                .collect(),
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|(line, rsvp)| (line, rsvp.and_then(|rsvp| rsvp.validate().map(|_| rsvp))))
+            .collect())
+    }
+}
+
+impl Validator for ExportRequest {
+    fn validate(&self) -> Result<(), Error> {
+        BulkFormat::try_from(self.format)
+            .map_err(|_| Error::InvalidRecord(format!("unknown format {}", self.format)))?;
+
+        if let Some(query) = &self.query {
+            ReservationStatus::try_from(query.status)
+                .map_err(|_| Error::InvalidStatus(query.status))?;
+            // the window may be open on either side
+            if query.start.is_some() && query.end.is_some() {
+                validate_range(query.start.as_ref(), query.end.as_ref())?;
+            }
+            query.label_selector.parse::<LabelSelector>()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validator for ImportRequest {
+    fn validate(&self) -> Result<(), Error> {
+        BulkFormat::try_from(self.format)
+            .map_err(|_| Error::InvalidRecord(format!("unknown format {}", self.format)))?;
+        ConflictPolicy::try_from(self.on_conflict).map_err(|_| {
+            Error::InvalidRecord(format!("unknown conflict policy {}", self.on_conflict))
+        })?;
+
+        Ok(())
+    }
+}
+
+impl ImportError {
+    pub fn new(line: u64, e: &Error) -> Self {
+        Self {
+            line: line as i64,
+            reason: e.reason().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+fn decode_csv(data: &str) -> Result<Vec<ImportRow>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| Error::InvalidRecord(e.to_string()))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    for name in ["resource_id", "user_id", "start", "end"] {
+        if column(name).is_none() {
+            return Err(Error::InvalidRecord(format!("no {} column", name)));
+        }
+    }
+    let columns: HashMap<&str, usize> = CSV_COLUMNS
+        .iter()
+        .filter_map(|&name| Some((name, column(name)?)))
+        .collect();
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            let position = match &record {
+                Ok(record) => record.position(),
+                Err(e) => e.position(),
+            };
+            let line = position.map(|p| p.line()).unwrap_or_default();
+            let rsvp = record
+                .map_err(|e| Error::InvalidRecord(e.to_string()))
+                .and_then(|record| {
+                    let field = |name: &str| {
+                        columns
+                            .get(name)
+                            .and_then(|&i| record.get(i))
+                            .unwrap_or_default()
+                    };
+                    let json = |name: &str| match field(name) {
+                        "" => Ok(Value::Null),
+                        value => serde_json::from_str(value)
+                            .map_err(|_| Error::InvalidRecord(format!("{} is not JSON", name))),
+                    };
+
+                    Reservation::from_json(json!({
+                        "resource_id": field("resource_id"),
+                        "user_id": field("user_id"),
+                        "status": field("status"),
+                        "start": field("start"),
+                        "end": field("end"),
+                        "note": field("note"),
+                        "labels": json("labels")?,
+                        "attributes": json("attributes")?,
+                    }))
+                });
+
+            (line, rsvp)
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+fn write_csv(records: impl IntoIterator<Item = Vec<String>>) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        // writing to memory doesn't fail
+        let _ = writer.write_record(&record);
+    }
+    let data = writer.into_inner().unwrap_or_default();
+
+    String::from_utf8(data).unwrap_or_default()
+}
+
+fn status_name(status: i32) -> String {
+    ReservationStatus::try_from(status)
+        .unwrap_or(ReservationStatus::Unknown)
+        .to_string()
+}
+
+/// the status of an imported row, pending if it has none
+fn parse_status(s: &str) -> Result<ReservationStatus, Error> {
+    match s.trim().to_lowercase().as_str() {
+        "" | "pending" => Ok(ReservationStatus::Pending),
+        "confirmed" => Ok(ReservationStatus::Confirmed),
+        "blocked" => Ok(ReservationStatus::Blocked),
+        _ => Err(Error::InvalidRecord(format!("unknown status {}", s))),
+    }
+}
+
+fn format_time(ts: &Timestamp) -> String {
+    convert_to_utc_time(ts).to_rfc3339()
+}
+
+fn parse_time(s: &str) -> Result<Option<Timestamp>, Error> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    DateTime::parse_from_rfc3339(s.trim())
+        .map(|dt| Some(convert_to_timestamp(dt.with_timezone(&Utc))))
+        .map_err(|_| Error::InvalidRecord(format!("invalid time {}", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservations() -> Vec<Reservation> {
+        let mut rsvp = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "late arrival, \"quiet\" room\nplease",
+        );
+        rsvp.id = 42;
+        rsvp.tenant_id = "acme".to_string();
+        rsvp.labels = [("team".to_string(), "payments".to_string())].into();
+        rsvp.attributes = convert_to_struct(json!({"guests": 2.0}));
+
+        let mut blocked = Reservation::new_pending(
+            "lei",
+            "ocean-view-room-714",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-27T12:00:00-0700".parse().unwrap(),
+            "",
+        );
+        blocked.status = ReservationStatus::Blocked as i32;
+
+        vec![rsvp, blocked]
+    }
+
+    /// what an import gets back of an export
+    fn imported(rsvp: &Reservation) -> Reservation {
+        Reservation {
+            id: 0,
+            tenant_id: String::new(),
+            ..rsvp.clone()
+        }
+    }
+
+    #[test]
+    fn csv_should_round_trip() {
+        let rsvps = reservations();
+        let data = BulkFormat::Csv.header() + &BulkFormat::Csv.encode(&rsvps);
+        assert!(data.starts_with(
+            "id,tenant_id,resource_id,user_id,status,start,end,note,labels,attributes\n\
+            42,acme,ocean-view-room-713,john,pending,2022-12-26T22:00:00+00:00,"
+        ));
+
+        let rows = BulkFormat::Csv.decode(&data).unwrap();
+        assert_eq!(rows.len(), 2);
+        // the note of the first row spans two lines
+        assert_eq!(rows[0], (2, Ok(imported(&rsvps[0]))));
+        assert_eq!(rows[1], (4, Ok(imported(&rsvps[1]))));
+    }
+
+    #[test]
+    fn ndjson_should_round_trip() {
+        let rsvps = reservations();
+        let data = BulkFormat::Ndjson.encode(&rsvps);
+        assert_eq!(data.lines().count(), 2);
+        assert!(BulkFormat::Ndjson.header().is_empty());
+
+        let rows = BulkFormat::Ndjson.decode(&format!("\n{}", data)).unwrap();
+        assert_eq!(
+            rows,
+            vec![(2, Ok(imported(&rsvps[0]))), (3, Ok(imported(&rsvps[1])))]
+        );
+    }
+
+    #[test]
+    fn invalid_rows_should_be_reported_on_their_own() {
+        let data = "user_id,resource_id,start,end,status\n\
+            john,room-1,2024-01-01T00:00:00Z,2024-01-02T00:00:00Z,\n\
+            john,room-1,yesterday,2024-01-02T00:00:00Z,\n\
+            ,room-1,2024-01-01T00:00:00Z,2024-01-02T00:00:00Z,\n\
+            john,room-1,2024-01-02T00:00:00Z,2024-01-01T00:00:00Z,\n\
+            john,room-1,2024-01-01T00:00:00Z,2024-01-02T00:00:00Z,gone\n";
+
+        let rows = BulkFormat::Csv.decode(data).unwrap();
+        let errors: Vec<_> = rows
+            .iter()
+            .map(|(line, rsvp)| (*line, rsvp.as_ref().err().map(Error::reason)))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (2, None),
+                (3, Some("INVALID_RECORD")),
+                (4, Some("INVALID_USER_ID")),
+                (5, Some("INVALID_TIME")),
+                (6, Some("INVALID_RECORD")),
+            ]
+        );
+        assert_eq!(
+            rows[0].1.as_ref().unwrap().status,
+            ReservationStatus::Pending as i32
+        );
+
+        assert_eq!(
+            BulkFormat::Csv.decode("user_id,start,end\n").unwrap_err(),
+            Error::InvalidRecord("no resource_id column".to_string())
+        );
+
+        let rows = BulkFormat::Ndjson
+            .decode("[1]\n{\"labels\": [1]}\n")
+            .unwrap();
+        assert!(rows
+            .iter()
+            .all(|(_, rsvp)| matches!(rsvp, Err(Error::InvalidRecord(_)))));
+    }
+}
@@ -0,0 +1,97 @@
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{
+    Error, ListenRequest, ListenResponse, Reservation, ReservationType, RsvpUpdateType, Validator,
+};
+
+/// the reservation fields an update can change, as listed in the change feed
+pub const RESERVATION_FIELDS: &[&str] = &[
+    "resource_id",
+    "user_id",
+    "status",
+    "start",
+    "end",
+    "note",
+    "labels",
+    "attributes",
+    "tenant_id",
+];
+
+impl ListenRequest {
+    /// true if the change should be sent to the listener
+    pub fn matches(&self, change: &ListenResponse) -> bool {
+        change.op != ReservationType::Update as i32
+            || self.changed_fields.is_empty()
+            || change
+                .changed_fields
+                .iter()
+                .any(|f| self.changed_fields.contains(f))
+    }
+}
+
+impl Validator for ListenRequest {
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(seq) = self.after_sequence.filter(|seq| *seq < 0) {
+            return Err(Error::InvalidCursor(seq.to_string()));
+        }
+
+        match self
+            .changed_fields
+            .iter()
+            .find(|f| !RESERVATION_FIELDS.contains(&f.as_str()))
+        {
+            Some(field) => Err(Error::InvalidChangedField(field.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromRow<'_, PgRow> for ListenResponse {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let op: RsvpUpdateType = row.get("op");
+
+        Ok(Self {
+            op: ReservationType::from(op) as i32,
+            reservation: Some(Reservation::from_row(row)?),
+            changed_fields: row.get("changed_fields"),
+            sequence: row.get("seq"),
+            txid: row.get("txid"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(op: ReservationType, fields: &[&str]) -> ListenResponse {
+        ListenResponse {
+            op: op as i32,
+            changed_fields: fields.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn listen_request_should_filter_updates_by_changed_field() {
+        let request = ListenRequest {
+            changed_fields: vec!["note".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        assert!(request.matches(&change(ReservationType::Update, &["note", "status"])));
+        assert!(!request.matches(&change(ReservationType::Update, &["status"])));
+        assert!(request.matches(&change(ReservationType::Create, &[])));
+        assert!(ListenRequest::default().matches(&change(ReservationType::Update, &["status"])));
+
+        let request = ListenRequest {
+            changed_fields: vec!["timespan".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            request.validate(),
+            Err(Error::InvalidChangedField("timespan".to_string()))
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use crate::{DryRunResult, Error, StatusDetails};
+
+impl DryRunResult {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&Error> for DryRunResult {
+    fn from(e: &Error) -> Self {
+        Self {
+            ok: false,
+            reason: e.reason().to_string(),
+            message: e.to_string(),
+            conflicts: StatusDetails::from(e).conflicts,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{
+    convert_to_timestamp, Reservation, ReservationType, ReservationVersion, RsvpUpdateType,
+};
+
+impl FromRow<'_, PgRow> for ReservationVersion {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let op: RsvpUpdateType = row.get("op");
+        let changed_at: DateTime<Utc> = row.get("changed_at");
+
+        Ok(Self {
+            version: row.get("version"),
+            op: ReservationType::from(op) as i32,
+            reservation: Some(Reservation::from_row(row)?),
+            changed_at: Some(convert_to_timestamp(changed_at)),
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use prost_types::Timestamp;
+
+use crate::{convert_to_utc_time, Reservation, ReservationStatus};
+
+/// product identifier of the calendars we generate
+pub const ICAL_PRODID: &str = "-//rsvp//Reservation Service//EN";
+/// domain of the UIDs of the events
+const ICAL_UID_DOMAIN: &str = "rsvp";
+/// content lines longer than this (in octets) are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+impl Reservation {
+    /// UID of the VEVENT of the reservation, stable across feeds and exports
+    pub fn ical_uid(&self) -> String {
+        format!("reservation-{}@{}", self.id, ICAL_UID_DOMAIN)
+    }
+
+    /// the reservation as an iCalendar (RFC 5545) VEVENT, stamped with the time the calendar
+    /// is generated at
+    pub fn to_vevent(&self, stamp: DateTime<Utc>) -> String {
+        let status = match ReservationStatus::try_from(self.status) {
+            Ok(ReservationStatus::Confirmed) => "CONFIRMED",
+            // blocked reservations don't take place
+            Ok(ReservationStatus::Blocked) => "CANCELLED",
+            _ => "TENTATIVE",
+        };
+
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", self.ical_uid()),
+            format!("DTSTAMP:{}", format_utc(stamp)),
+        ];
+        if let Some(start) = &self.start {
+            lines.push(format!("DTSTART:{}", format_timestamp(start)));
+        }
+        if let Some(end) = &self.end {
+            lines.push(format!("DTEND:{}", format_timestamp(end)));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&self.resource_id)));
+        lines.push(format!("LOCATION:{}", escape_text(&self.resource_id)));
+        if !self.note.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&self.note)));
+        }
+        lines.push(format!("CONTACT:{}", escape_text(&self.user_id)));
+        lines.push(format!("STATUS:{}", status));
+        lines.push("END:VEVENT".to_string());
+
+        lines.iter().map(|line| fold(line)).collect()
+    }
+}
+
+/// the reservations as an iCalendar (RFC 5545) VCALENDAR
+pub fn to_ical(reservations: &[Reservation], stamp: DateTime<Utc>) -> String {
+    let mut ical = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        &format!("PRODID:{}", ICAL_PRODID),
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+    ]
+    .iter()
+    .map(|line| fold(line))
+    .collect::<String>();
+
+    for rsvp in reservations {
+        ical.push_str(&rsvp.to_vevent(stamp));
+    }
+    ical.push_str("END:VCALENDAR\r\n");
+
+    ical
+}
+
+fn format_timestamp(ts: &Timestamp) -> String {
+    format_utc(convert_to_utc_time(ts))
+}
+
+fn format_utc(dt: DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// escape a TEXT value
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+/// a content line terminated by CRLF, folded into lines of at most 75 octets without splitting
+/// characters
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut octets = 0;
+
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // the leading space counts
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp() -> DateTime<Utc> {
+        "2022-12-01T08:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn reservation_should_convert_to_vevent() {
+        let mut rsvp = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting; bring slides, please",
+        );
+        rsvp.id = 42;
+
+        assert_eq!(
+            rsvp.to_vevent(stamp()),
+            "BEGIN:VEVENT\r\n\
+            UID:reservation-42@rsvp\r\n\
+            DTSTAMP:20221201T080000Z\r\n\
+            DTSTART:20221226T220000Z\r\n\
+            DTEND:20221230T190000Z\r\n\
+            SUMMARY:ocean-view-room-713\r\n\
+            LOCATION:ocean-view-room-713\r\n\
+            DESCRIPTION:I need this room for a meeting\\; bring slides\\, please\r\n\
+            CONTACT:john\r\n\
+            STATUS:TENTATIVE\r\n\
+            END:VEVENT\r\n"
+        );
+
+        rsvp.status = ReservationStatus::Confirmed as i32;
+        assert!(rsvp.to_vevent(stamp()).contains("STATUS:CONFIRMED\r\n"));
+        rsvp.status = ReservationStatus::Blocked as i32;
+        assert!(rsvp.to_vevent(stamp()).contains("STATUS:CANCELLED\r\n"));
+
+        // a note can't add its own properties, whatever its line breaks
+        rsvp.note = "a\rSTATUS:CONFIRMED\r\nb\nc".to_string();
+        assert!(rsvp
+            .to_vevent(stamp())
+            .contains("DESCRIPTION:a\\nSTATUS:CONFIRMED\\nb\\nc\r\n"));
+    }
+
+    #[test]
+    fn long_lines_should_be_folded() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|l| l.len() <= MAX_LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn reservations_should_convert_to_vcalendar() {
+        let ical = to_ical(&[Reservation::default(), Reservation::default()], stamp());
+
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:"));
+        assert!(ical.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert_eq!(ical.matches("BEGIN:VEVENT").count(), 2);
+    }
+}
@@ -0,0 +1,1002 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    convert_to_timestamp, convert_to_utc_time, Error, IcalImportOutcome, IcalImportRequest,
+    IcalImportResponse, IcalImportResult, Reservation, ReservationStatus,
+};
+
+/// a recurring event is expanded to this many occurrences at most
+const MAX_OCCURRENCES: usize = 1000;
+/// a calendar is imported only if its events have this many occurrences at most
+const MAX_IMPORT_OCCURRENCES: usize = 10_000;
+/// periods of a recurrence rule looked at before giving up on finding more occurrences
+const MAX_PERIODS: u32 = 10_000;
+/// how far recurring events are expanded unless the request says otherwise
+const DEFAULT_HORIZON_DAYS: i64 = 365;
+
+/// start and end of an occurrence
+pub type Occurrence = (DateTime<Utc>, DateTime<Utc>);
+
+/// a property of a calendar component, `NAME;PARAM=VALUE:value`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IcalProperty {
+    pub name: String,
+    pub params: HashMap<String, String>,
+    pub value: String,
+}
+
+/// a VEVENT of a calendar, its recurrences not expanded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IcalEvent {
+    pub properties: Vec<IcalProperty>,
+}
+
+/// a DATE or DATE-TIME value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IcalTime {
+    Utc(DateTime<Utc>),
+    /// in the time zone of its TZID, floating if there is none
+    Local(NaiveDateTime, Option<Tz>),
+    Date(NaiveDate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// the RRULE of an event, the parts calendar apps commonly use are supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RecurrenceRule {
+    freq: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<IcalTime>,
+    /// (ordinal, weekday), the ordinal is 0 for every such weekday of the period
+    by_day: Vec<(i32, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+/// the VEVENTs of an iCalendar (RFC 5545) VCALENDAR
+pub fn parse_ical(ical: &str) -> Result<Vec<IcalEvent>, Error> {
+    let unfolded = ical
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut components: Vec<String> = Vec::new();
+    let mut event: Option<IcalEvent> = None;
+    let mut calendars = 0;
+
+    for (i, line) in unfolded.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let property = parse_line(line)
+            .ok_or_else(|| Error::InvalidIcal(format!("invalid content line {}", i + 1)))?;
+
+        match property.name.as_str() {
+            "BEGIN" => {
+                let name = property.value.to_uppercase();
+                let in_calendar = components.last().is_some_and(|c| c == "VCALENDAR");
+                match name.as_str() {
+                    "VCALENDAR" => calendars += 1,
+                    "VEVENT" if in_calendar => event = Some(IcalEvent::default()),
+                    _ => {}
+                }
+                components.push(name);
+            }
+            "END" => {
+                let name = property.value.to_uppercase();
+                if components.pop().as_ref() != Some(&name) {
+                    return Err(Error::InvalidIcal(format!(
+                        "unexpected END:{} on line {}",
+                        property.value,
+                        i + 1
+                    )));
+                }
+                if name == "VEVENT" {
+                    events.extend(event.take());
+                }
+            }
+            // the properties of the components of the event (e.g. VALARM) are not its own
+            _ if components.last().is_some_and(|c| c == "VEVENT") => {
+                if let Some(event) = event.as_mut() {
+                    event.properties.push(property);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(name) = components.pop() {
+        return Err(Error::InvalidIcal(format!("BEGIN:{} is not ended", name)));
+    }
+    if calendars == 0 {
+        return Err(Error::InvalidIcal("no VCALENDAR".to_string()));
+    }
+
+    Ok(events)
+}
+
+impl IcalEvent {
+    pub fn property(&self, name: &str) -> Option<&IcalProperty> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn uid(&self) -> &str {
+        self.property("UID")
+            .map(|p| p.value.as_str())
+            .unwrap_or_default()
+    }
+
+    /// the value of a TEXT property, empty if the event doesn't have it
+    pub fn text(&self, name: &str) -> String {
+        self.property(name)
+            .map(|p| unescape_text(&p.value))
+            .unwrap_or_default()
+    }
+
+    /// start and end of every occurrence of the event, recurrences up to the horizon. Times
+    /// without a time zone are in the given one
+    pub fn occurrences(&self, tz: Tz, horizon: DateTime<Utc>) -> Result<Vec<Occurrence>, String> {
+        let start = self.time("DTSTART")?.ok_or("no DTSTART")?;
+        let start_at = start.resolve(tz)?;
+        let duration = match (self.time("DTEND")?, self.property("DURATION")) {
+            (Some(end), _) => end.resolve(tz)? - start_at,
+            (None, Some(duration)) => parse_duration(&duration.value)?,
+            // all day events last the day
+            (None, None) if matches!(start, IcalTime::Date(_)) => Duration::days(1),
+            (None, None) => Duration::zero(),
+        };
+        if duration <= Duration::zero() {
+            return Err("the event has no duration".to_string());
+        }
+
+        let Some(rule) = self.property("RRULE") else {
+            return Ok(vec![(start_at, start_at + duration)]);
+        };
+        let rule: RecurrenceRule = rule.value.parse()?;
+        let until = rule.until.map(|until| until.resolve_end(tz)).transpose()?;
+        let exdates = self.exdates(tz)?;
+
+        // the start is the first occurrence even if the rule doesn't pick it
+        let mut instances = vec![start_at];
+        let (wall, zone) = start.local(tz);
+        'periods: for period in 0..MAX_PERIODS {
+            for date in rule.dates(wall.date(), period) {
+                let local = date.and_time(wall.time());
+                if local <= wall {
+                    continue;
+                }
+                // the times DST skips don't occur
+                let Ok(at) = localize(local, zone) else {
+                    continue;
+                };
+                if until.is_some_and(|until| at > until)
+                    || rule.count.is_some_and(|count| instances.len() >= count)
+                    || at > horizon
+                    || instances.len() >= MAX_OCCURRENCES
+                {
+                    break 'periods;
+                }
+                instances.push(at);
+            }
+        }
+
+        Ok(instances
+            .into_iter()
+            .filter(|at| *at <= horizon && !exdates.contains(at))
+            .map(|at| (at, at + duration))
+            .collect())
+    }
+
+    fn time(&self, name: &str) -> Result<Option<IcalTime>, String> {
+        self.property(name)
+            .map(|p| IcalTime::parse(&p.value, &p.params))
+            .transpose()
+    }
+
+    /// the occurrences excluded by the EXDATE properties
+    fn exdates(&self, tz: Tz) -> Result<HashSet<DateTime<Utc>>, String> {
+        self.properties
+            .iter()
+            .filter(|p| p.name == "EXDATE")
+            .flat_map(|p| {
+                p.value
+                    .split(',')
+                    .map(|value| IcalTime::parse(value, &p.params)?.resolve(tz))
+            })
+            .collect()
+    }
+}
+
+impl IcalImportRequest {
+    /// the reservations of the events of the calendar, one per occurrence. The results still
+    /// UNKNOWN carry the reservation to make, the others are events skipped and why. A calendar
+    /// with too many occurrences in all is refused
+    pub fn plan(&self, now: DateTime<Utc>) -> Result<Vec<IcalImportResult>, Error> {
+        let tz = match self.timezone.as_str() {
+            "" => Tz::UTC,
+            tz => tz
+                .parse()
+                .map_err(|_| Error::InvalidIcal(format!("unknown time zone {}", tz)))?,
+        };
+        let horizon = self
+            .until
+            .as_ref()
+            .map(convert_to_utc_time)
+            .unwrap_or_else(|| now + Duration::days(DEFAULT_HORIZON_DAYS));
+        let events = parse_ical(&self.ical)?;
+
+        // occurrences another VEVENT with a RECURRENCE-ID moves or changes
+        let overridden: HashSet<(&str, DateTime<Utc>)> = events
+            .iter()
+            .filter_map(|event| {
+                let at = event.time("RECURRENCE-ID").ok()??.resolve(tz).ok()?;
+                Some((event.uid(), at))
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        let mut planned = 0;
+        for event in &events {
+            let uid = event.uid().to_string();
+            match self.reservations(event, tz, horizon, &overridden) {
+                Ok(rsvps) => {
+                    planned += rsvps.len();
+                    if planned > MAX_IMPORT_OCCURRENCES {
+                        return Err(Error::InvalidIcal(format!(
+                            "the events have more than {} occurrences",
+                            MAX_IMPORT_OCCURRENCES
+                        )));
+                    }
+                    results.extend(rsvps.into_iter().map(|rsvp| IcalImportResult {
+                        uid: uid.clone(),
+                        reservation: Some(rsvp),
+                        ..Default::default()
+                    }))
+                }
+                Err(reason) => results.push(IcalImportResult {
+                    uid,
+                    outcome: IcalImportOutcome::Skipped as i32,
+                    reason,
+                    ..Default::default()
+                }),
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn reservations(
+        &self,
+        event: &IcalEvent,
+        tz: Tz,
+        horizon: DateTime<Utc>,
+        overridden: &HashSet<(&str, DateTime<Utc>)>,
+    ) -> Result<Vec<Reservation>, String> {
+        let status = event.text("STATUS").to_uppercase();
+        if status == "CANCELLED" {
+            return Err("the event is cancelled".to_string());
+        }
+
+        let location = event.text("LOCATION");
+        let resource_id = self
+            .resource_map
+            .get(&location)
+            .or(Some(&self.resource_id).filter(|r| !r.is_empty()))
+            .ok_or_else(|| format!("no resource for location {:?}", location))?;
+
+        let organizer = event.property("ORGANIZER");
+        let address = organizer
+            .map(|o| strip_mailto(&o.value))
+            .unwrap_or_default();
+        let name = organizer.and_then(|o| o.params.get("CN"));
+        let user_id = self
+            .user_map
+            .get(address)
+            .or_else(|| name.and_then(|name| self.user_map.get(name)))
+            .or(Some(&self.user_id).filter(|u| !u.is_empty()))
+            .ok_or_else(|| format!("no user for organizer {:?}", address))?;
+
+        let description = event.text("DESCRIPTION");
+        let note = match description.is_empty() {
+            true => event.text("SUMMARY"),
+            false => description,
+        };
+        let status = match status.as_str() {
+            "CONFIRMED" => ReservationStatus::Confirmed,
+            _ => ReservationStatus::Pending,
+        };
+        let master = event.property("RECURRENCE-ID").is_none();
+
+        Ok(event
+            .occurrences(tz, horizon)?
+            .into_iter()
+            .filter(|(start, _)| !(master && overridden.contains(&(event.uid(), *start))))
+            .map(|(start, end)| Reservation {
+                user_id: user_id.clone(),
+                resource_id: resource_id.clone(),
+                start: Some(convert_to_timestamp(start)),
+                end: Some(convert_to_timestamp(end)),
+                note: note.clone(),
+                status: status as i32,
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+impl IcalImportResponse {
+    /// the response reporting the results, counted by outcome
+    pub fn new(results: Vec<IcalImportResult>) -> Self {
+        let count = |outcome: IcalImportOutcome| {
+            results
+                .iter()
+                .filter(|r| r.outcome == outcome as i32)
+                .count() as i32
+        };
+
+        Self {
+            created: count(IcalImportOutcome::Created),
+            skipped: count(IcalImportOutcome::Skipped),
+            conflicts: count(IcalImportOutcome::Conflict),
+            results,
+        }
+    }
+}
+
+impl IcalTime {
+    fn parse(value: &str, params: &HashMap<String, String>) -> Result<Self, String> {
+        let invalid = || format!("invalid date-time {}", value);
+
+        if params.get("VALUE").is_some_and(|v| v == "DATE") || value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .map(IcalTime::Date)
+                .map_err(|_| invalid());
+        }
+
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        if utc {
+            return Ok(IcalTime::Utc(Utc.from_utc_datetime(&naive)));
+        }
+
+        let zone = params
+            .get("TZID")
+            .map(|tzid| {
+                tzid.parse::<Tz>()
+                    .map_err(|_| format!("unknown time zone {}", tzid))
+            })
+            .transpose()?;
+
+        Ok(IcalTime::Local(naive, zone))
+    }
+
+    /// the wall time and the zone it's in, floating times and dates are in the given one
+    fn local(&self, tz: Tz) -> (NaiveDateTime, Tz) {
+        match *self {
+            IcalTime::Utc(at) => (at.naive_utc(), Tz::UTC),
+            IcalTime::Local(naive, zone) => (naive, zone.unwrap_or(tz)),
+            IcalTime::Date(date) => (date.and_hms_opt(0, 0, 0).unwrap(), tz),
+        }
+    }
+
+    fn resolve(&self, tz: Tz) -> Result<DateTime<Utc>, String> {
+        let (naive, zone) = self.local(tz);
+        localize(naive, zone)
+    }
+
+    /// like resolve, a date lasts until the end of the day
+    fn resolve_end(&self, tz: Tz) -> Result<DateTime<Utc>, String> {
+        match self {
+            IcalTime::Date(date) => {
+                let next = date
+                    .succ_opt()
+                    .ok_or_else(|| format!("invalid date {}", date))?;
+                Ok(IcalTime::Date(next).resolve(tz)? - Duration::seconds(1))
+            }
+            _ => self.resolve(tz),
+        }
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |part: &str| format!("invalid RRULE part {}", part);
+        let mut rule = RecurrenceRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        let mut freq = None;
+
+        for part in s.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| invalid(part))?;
+            let numbers = || -> Result<Vec<i32>, String> {
+                value
+                    .split(',')
+                    .map(|n| n.parse().map_err(|_| invalid(part)))
+                    .collect()
+            };
+
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported RRULE frequency {}", value)),
+                    })
+                }
+                "INTERVAL" => rule.interval = value.parse().map_err(|_| invalid(part))?,
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid(part))?),
+                "UNTIL" => rule.until = Some(IcalTime::parse(value, &HashMap::new())?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|day| parse_weekday(day).ok_or_else(|| invalid(part)))
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => rule.by_month_day = numbers()?,
+                "BYMONTH" => rule.by_month = numbers()?.into_iter().map(|m| m as u32).collect(),
+                // weeks start on monday
+                "WKST" => {}
+                _ => return Err(format!("unsupported RRULE part {}", key)),
+            }
+        }
+
+        rule.freq = freq.ok_or("RRULE without FREQ")?;
+        if rule.interval == 0 {
+            return Err(invalid("INTERVAL=0"));
+        }
+        if rule
+            .by_month_day
+            .iter()
+            .any(|&d| d == 0 || !(-31..=31).contains(&d))
+            || rule.by_month.iter().any(|&m| !(1..=12).contains(&m))
+        {
+            return Err(format!("invalid RRULE {}", s));
+        }
+        let ordinals = rule.by_day.iter().any(|&(ordinal, _)| ordinal != 0);
+        match rule.freq {
+            Frequency::Daily | Frequency::Weekly if ordinals => {
+                return Err(format!("invalid RRULE {}", s));
+            }
+            Frequency::Yearly if !rule.by_day.is_empty() && rule.by_month.is_empty() => {
+                return Err("unsupported RRULE: yearly BYDAY without BYMONTH".to_string());
+            }
+            _ => {}
+        }
+
+        Ok(rule)
+    }
+}
+
+impl RecurrenceRule {
+    /// the dates of the period `n` intervals after the one of the start, in order
+    fn dates(&self, start: NaiveDate, n: u32) -> Vec<NaiveDate> {
+        let step = (n * self.interval) as i64;
+
+        let mut dates = match self.freq {
+            Frequency::Daily => {
+                let date = start + Duration::days(step);
+                let weekday_ok = self.by_day.is_empty()
+                    || self.by_day.iter().any(|&(_, wd)| wd == date.weekday());
+                let day_ok = self.by_month_day.is_empty()
+                    || self.month_days(date.year(), date.month()).contains(&date);
+                match weekday_ok && day_ok {
+                    true => vec![date],
+                    false => vec![],
+                }
+            }
+            Frequency::Weekly => {
+                let monday = start - Duration::days(start.weekday().num_days_from_monday() as i64)
+                    + Duration::weeks(step);
+                let weekdays = match self.by_day.is_empty() {
+                    true => vec![start.weekday()],
+                    false => self.by_day.iter().map(|&(_, wd)| wd).collect(),
+                };
+                (0..7)
+                    .map(|d| monday + Duration::days(d))
+                    .filter(|date| weekdays.contains(&date.weekday()))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let (year, month) = add_months(start.year(), start.month(), step);
+                self.month_dates(year, month, start.day())
+            }
+            Frequency::Yearly => {
+                let year = start.year() + step as i32;
+                let months = match self.by_month.is_empty() {
+                    true => vec![start.month()],
+                    false => self.by_month.clone(),
+                };
+                months
+                    .into_iter()
+                    .flat_map(|month| self.month_dates(year, month, start.day()))
+                    .collect()
+            }
+        };
+
+        if self.freq != Frequency::Yearly && !self.by_month.is_empty() {
+            dates.retain(|date| self.by_month.contains(&date.month()));
+        }
+        dates.sort();
+        dates.dedup();
+
+        dates
+    }
+
+    /// the days of the month BYMONTHDAY or BYDAY pick, the day of the start otherwise
+    fn month_dates(&self, year: i32, month: u32, day: u32) -> Vec<NaiveDate> {
+        if !self.by_month_day.is_empty() {
+            let mut dates = self.month_days(year, month);
+            if !self.by_day.is_empty() {
+                dates.retain(|date| self.by_day.iter().any(|&(_, wd)| wd == date.weekday()));
+            }
+            return dates;
+        }
+
+        if self.by_day.is_empty() {
+            return NaiveDate::from_ymd_opt(year, month, day)
+                .into_iter()
+                .collect();
+        }
+
+        let days = days_of_month(year, month);
+        self.by_day
+            .iter()
+            .flat_map(|&(ordinal, weekday)| {
+                let all: Vec<_> = days.iter().filter(|d| d.weekday() == weekday).collect();
+                let picked = match ordinal {
+                    0 => all,
+                    n if n > 0 => all.get(n as usize - 1).copied().into_iter().collect(),
+                    n => all
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .and_then(|i| all.get(i).copied())
+                        .into_iter()
+                        .collect(),
+                };
+                picked.into_iter().copied()
+            })
+            .collect()
+    }
+
+    /// the days of the month BYMONTHDAY picks, negative days count from the end
+    fn month_days(&self, year: i32, month: u32) -> Vec<NaiveDate> {
+        let days = days_of_month(year, month);
+        self.by_month_day
+            .iter()
+            .filter_map(|&d| match d > 0 {
+                true => days.get(d as usize - 1),
+                false => days
+                    .len()
+                    .checked_sub(d.unsigned_abs() as usize)
+                    .and_then(|i| days.get(i)),
+            })
+            .copied()
+            .collect()
+    }
+}
+
+fn days_of_month(year: i32, month: u32) -> Vec<NaiveDate> {
+    (1..=31)
+        .map_while(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .collect()
+}
+
+fn add_months(year: i32, month: u32, months: i64) -> (i32, u32) {
+    let total = year as i64 * 12 + month as i64 - 1 + months;
+    ((total / 12) as i32, (total % 12) as u32 + 1)
+}
+
+/// `MO`, `2TU`, `-1FR`
+fn parse_weekday(s: &str) -> Option<(i32, Weekday)> {
+    let split = s.len().checked_sub(2)?;
+    let (ordinal, day) = (s.get(..split)?, s.get(split..)?);
+    let ordinal = match ordinal {
+        "" => 0,
+        ordinal => ordinal.parse().ok().filter(|o: &i32| *o != 0)?,
+    };
+    let weekday = match day.to_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+
+    Some((ordinal, weekday))
+}
+
+/// the instant of the wall time in the zone, the first one when DST makes it ambiguous
+fn localize(naive: NaiveDateTime, zone: Tz) -> Result<DateTime<Utc>, String> {
+    zone.from_local_datetime(&naive)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} doesn't exist in {}", naive, zone))
+}
+
+/// `[+-]P[nW]` or `[+-]P[nD][T[nH][nM][nS]]`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {}", value);
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                duration += match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return Err(invalid()),
+                };
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(duration * sign)
+}
+
+/// NAME *(";" param "=" value) ":" value, the colons and semicolons of quoted parameter values
+/// don't count
+fn parse_line(line: &str) -> Option<IcalProperty> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        (c == ':' && !quoted).then_some(i)
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    let mut parts = split_unquoted(head, ';').into_iter();
+    let name = parts.next()?.trim().to_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.to_uppercase(), value.trim_matches('"').to_string()))
+        })
+        .collect();
+
+    Some(IcalProperty {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+fn unescape_text(s: &str) -> String {
+    let mut text = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(c) => text.push(c),
+            None => text.push('\\'),
+        }
+    }
+
+    text
+}
+
+fn strip_mailto(address: &str) -> &str {
+    match address.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &address[7..],
+        _ => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &[&str]) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//test//EN\r\n{}END:VCALENDAR\r\n",
+            events.concat()
+        )
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn starts(event: &IcalEvent) -> Vec<DateTime<Utc>> {
+        event
+            .occurrences(Tz::UTC, utc("2030-01-01T00:00:00Z"))
+            .unwrap()
+            .into_iter()
+            .map(|(start, _)| start)
+            .collect()
+    }
+
+    fn event(lines: &str) -> IcalEvent {
+        let ical = calendar(&[&format!("BEGIN:VEVENT\r\n{}END:VEVENT\r\n", lines)]);
+        parse_ical(&ical).unwrap().remove(0)
+    }
+
+    #[test]
+    fn parse_ical_should_unfold_and_unescape() {
+        let ical = calendar(&["BEGIN:VEVENT\r\n\
+            UID:1@example.com\r\n\
+            DESCRIPTION:bring slides\\, the projector\\;\\n and coffee for the\r\n  whole team\r\n\
+            ORGANIZER;CN=\"Doe, John\":mailto:john@example.com\r\n\
+            BEGIN:VALARM\r\n\
+            DESCRIPTION:alarm\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n"]);
+
+        let events = parse_ical(&ical).unwrap();
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.uid(), "1@example.com");
+        assert_eq!(
+            event.text("DESCRIPTION"),
+            "bring slides, the projector;\n and coffee for the whole team"
+        );
+        let organizer = event.property("ORGANIZER").unwrap();
+        assert_eq!(organizer.params["CN"], "Doe, John");
+        assert_eq!(organizer.value, "mailto:john@example.com");
+
+        assert!(matches!(
+            parse_ical("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR\r\n"),
+            Err(Error::InvalidIcal(_))
+        ));
+        assert!(matches!(parse_ical(""), Err(Error::InvalidIcal(_))));
+    }
+
+    #[test]
+    fn events_should_have_a_duration() {
+        let e = event("DTSTART:20240102T090000Z\r\nDURATION:PT1H30M\r\n");
+        let occurrences = e.occurrences(Tz::UTC, Utc::now()).unwrap();
+        assert_eq!(
+            occurrences,
+            vec![(utc("2024-01-02T09:00:00Z"), utc("2024-01-02T10:30:00Z"))]
+        );
+
+        // all day
+        let e = event("DTSTART;VALUE=DATE:20240102\r\n");
+        let occurrences = e.occurrences(Tz::UTC, Utc::now()).unwrap();
+        assert_eq!(
+            occurrences,
+            vec![(utc("2024-01-02T00:00:00Z"), utc("2024-01-03T00:00:00Z"))]
+        );
+
+        let e = event("DTSTART:20240102T090000Z\r\n");
+        assert!(e.occurrences(Tz::UTC, Utc::now()).is_err());
+    }
+
+    #[test]
+    fn weekly_rules_should_follow_the_time_zone() {
+        // DST starts on 2024-03-31 in Berlin, the meeting stays at 9:00 local time
+        let e = event(
+            "DTSTART;TZID=Europe/Berlin:20240325T090000\r\n\
+            DTEND;TZID=Europe/Berlin:20240325T100000\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4\r\n\
+            EXDATE;TZID=Europe/Berlin:20240327T090000\r\n",
+        );
+
+        assert_eq!(
+            starts(&e),
+            vec![
+                utc("2024-03-25T08:00:00Z"),
+                utc("2024-04-01T07:00:00Z"),
+                utc("2024-04-03T07:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_and_yearly_rules_should_work() {
+        let e = event(
+            "DTSTART:20240126T150000Z\r\nDURATION:PT1H\r\n\
+            RRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20240430T000000Z\r\n",
+        );
+        assert_eq!(
+            starts(&e),
+            vec![
+                utc("2024-01-26T15:00:00Z"),
+                utc("2024-02-23T15:00:00Z"),
+                utc("2024-03-29T15:00:00Z"),
+                utc("2024-04-26T15:00:00Z"),
+            ]
+        );
+
+        // months without the 31st are skipped
+        let e =
+            event("DTSTART:20240131T150000Z\r\nDURATION:PT1H\r\nRRULE:FREQ=MONTHLY;COUNT=3\r\n");
+        assert_eq!(
+            starts(&e),
+            vec![
+                utc("2024-01-31T15:00:00Z"),
+                utc("2024-03-31T15:00:00Z"),
+                utc("2024-05-31T15:00:00Z"),
+            ]
+        );
+
+        let e =
+            event("DTSTART;VALUE=DATE:20240229\r\nRRULE:FREQ=YEARLY;INTERVAL=2;UNTIL=20290101\r\n");
+        assert_eq!(
+            starts(&e),
+            vec![utc("2024-02-29T00:00:00Z"), utc("2028-02-29T00:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn rules_without_an_end_should_stop_at_the_horizon() {
+        let e =
+            event("DTSTART:20240101T090000Z\r\nDURATION:PT1H\r\nRRULE:FREQ=DAILY;INTERVAL=2\r\n");
+        let occurrences = e.occurrences(Tz::UTC, utc("2024-01-07T09:00:00Z")).unwrap();
+        assert_eq!(occurrences.len(), 4);
+
+        let e = event("DTSTART:20240101T090000Z\r\nDURATION:PT1H\r\nRRULE:FREQ=HOURLY\r\n");
+        assert_eq!(
+            e.occurrences(Tz::UTC, Utc::now()).unwrap_err(),
+            "unsupported RRULE frequency HOURLY"
+        );
+    }
+
+    #[test]
+    fn import_should_plan_the_reservations_of_the_events() {
+        let ical = calendar(&[
+            // weekly standup, the second one moved to the afternoon
+            "BEGIN:VEVENT\r\nUID:standup\r\nSUMMARY:Standup\r\nLOCATION:Room 1\r\n\
+            ORGANIZER;CN=John:mailto:john@example.com\r\n\
+            DTSTART:20240101T090000\r\nDTEND:20240101T093000\r\n\
+            RRULE:FREQ=WEEKLY;COUNT=3\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:standup\r\nRECURRENCE-ID:20240108T090000\r\nSUMMARY:Standup\r\n\
+            LOCATION:Room 1\r\nORGANIZER;CN=John:mailto:john@example.com\r\n\
+            DTSTART:20240108T140000\r\nDTEND:20240108T143000\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:offsite\r\nSTATUS:CONFIRMED\r\nLOCATION:Somewhere else\r\n\
+            DTSTART:20240105T090000Z\r\nDTEND:20240105T170000Z\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nUID:cancelled\r\nSTATUS:CANCELLED\r\n\
+            DTSTART:20240105T090000Z\r\nDTEND:20240105T170000Z\r\nEND:VEVENT\r\n",
+        ]);
+        let request = IcalImportRequest {
+            ical,
+            resource_map: [("Room 1".to_string(), "room-1".to_string())].into(),
+            user_map: [("john@example.com".to_string(), "john".to_string())].into(),
+            timezone: "America/Los_Angeles".to_string(),
+            ..Default::default()
+        };
+
+        let results = request.plan(utc("2024-01-01T00:00:00Z")).unwrap();
+        let planned: Vec<_> = results
+            .iter()
+            .filter(|r| r.outcome == IcalImportOutcome::Unknown as i32)
+            .map(|r| r.reservation.clone().unwrap())
+            .collect();
+        assert_eq!(planned.len(), 3);
+        assert!(planned.iter().all(|r| r.user_id == "john"
+            && r.resource_id == "room-1"
+            && r.note == "Standup"
+            && r.status == ReservationStatus::Pending as i32));
+        let starts: Vec<_> = planned
+            .iter()
+            .map(|r| convert_to_utc_time(r.start.as_ref().unwrap()))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                utc("2024-01-01T17:00:00Z"),
+                utc("2024-01-15T17:00:00Z"),
+                utc("2024-01-08T22:00:00Z"),
+            ]
+        );
+
+        let skipped: Vec<_> = results
+            .iter()
+            .filter(|r| r.outcome == IcalImportOutcome::Skipped as i32)
+            .map(|r| (r.uid.as_str(), r.reason.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("offsite", "no resource for location \"Somewhere else\""),
+                ("cancelled", "the event is cancelled"),
+            ]
+        );
+
+        // each event is capped, so is the calendar
+        let daily: Vec<_> = (0..11)
+            .map(|i| {
+                format!(
+                    "BEGIN:VEVENT\r\nUID:daily-{}\r\nLOCATION:Room 1\r\n\
+                    DTSTART:20240101T090000Z\r\nDTEND:20240101T093000Z\r\n\
+                    RRULE:FREQ=DAILY\r\nEND:VEVENT\r\n",
+                    i
+                )
+            })
+            .collect();
+        let daily = IcalImportRequest {
+            ical: calendar(&daily.iter().map(String::as_str).collect::<Vec<_>>()),
+            user_id: "john".to_string(),
+            until: Some(convert_to_timestamp(utc("2030-01-01T00:00:00Z"))),
+            ..request.clone()
+        };
+        assert!(matches!(
+            daily.plan(utc("2024-01-01T00:00:00Z")),
+            Err(Error::InvalidIcal(_))
+        ));
+
+        let request = IcalImportRequest {
+            timezone: "Mars/Olympus_Mons".to_string(),
+            ..request
+        };
+        assert!(matches!(
+            request.plan(Utc::now()),
+            Err(Error::InvalidIcal(_))
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde::Serialize;
+
+use crate::Error;
+
+/// a single requirement of a label selector
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum LabelRequirement {
+    /// `key=value` or `key==value`
+    Eq { key: String, value: String },
+    /// `key!=value`, also matches reservations without the key
+    Ne { key: String, value: String },
+    /// `key`
+    Exists { key: String },
+    /// `!key`
+    NotExists { key: String },
+}
+
+/// label selector such as `team=payments,priority!=low`, all requirements must match
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct LabelSelector {
+    pub requirements: Vec<LabelRequirement>,
+}
+
+impl LabelSelector {
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// json form of the selector consumed by `rsvp.label_condition`, None if empty
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        if self.is_empty() {
+            None
+        } else {
+            serde_json::to_value(self).ok()
+        }
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|r| match r {
+            LabelRequirement::Eq { key, value } => labels.get(key) == Some(value),
+            LabelRequirement::Ne { key, value } => labels.get(key) != Some(value),
+            LabelRequirement::Exists { key } => labels.contains_key(key),
+            LabelRequirement::NotExists { key } => !labels.contains_key(key),
+        })
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidLabelSelector(s.to_string());
+
+        let mut requirements = vec![];
+
+        for req in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let requirement = if let Some((key, value)) = req.split_once("!=") {
+                LabelRequirement::Ne {
+                    key: parse_key(key).ok_or_else(invalid)?,
+                    value: parse_value(value).ok_or_else(invalid)?,
+                }
+            } else if let Some((key, value)) = req.split_once('=') {
+                // accept both `=` and `==`
+                let value = value.strip_prefix('=').unwrap_or(value);
+                LabelRequirement::Eq {
+                    key: parse_key(key).ok_or_else(invalid)?,
+                    value: parse_value(value).ok_or_else(invalid)?,
+                }
+            } else if let Some(key) = req.strip_prefix('!') {
+                LabelRequirement::NotExists {
+                    key: parse_key(key).ok_or_else(invalid)?,
+                }
+            } else {
+                LabelRequirement::Exists {
+                    key: parse_key(req).ok_or_else(invalid)?,
+                }
+            };
+
+            requirements.push(requirement);
+        }
+
+        Ok(Self { requirements })
+    }
+}
+
+/// label keys are made of alphanumerics, `-`, `_`, `.` and `/`
+pub fn is_valid_label_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 64
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+fn parse_key(key: &str) -> Option<String> {
+    let key = key.trim();
+    is_valid_label_key(key).then(|| key.to_string())
+}
+
+fn parse_value(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.contains(['=', '!', ','])).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_selector_should_parse() {
+        let selector: LabelSelector = "team=payments, priority!=low,env==prod,vip,!archived"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            selector.requirements,
+            vec![
+                LabelRequirement::Eq {
+                    key: "team".to_string(),
+                    value: "payments".to_string()
+                },
+                LabelRequirement::Ne {
+                    key: "priority".to_string(),
+                    value: "low".to_string()
+                },
+                LabelRequirement::Eq {
+                    key: "env".to_string(),
+                    value: "prod".to_string()
+                },
+                LabelRequirement::Exists {
+                    key: "vip".to_string()
+                },
+                LabelRequirement::NotExists {
+                    key: "archived".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn label_selector_should_reject_invalid_input() {
+        assert!("=payments".parse::<LabelSelector>().is_err());
+        assert!("team=pay=ments".parse::<LabelSelector>().is_err());
+        assert!("te am=payments".parse::<LabelSelector>().is_err());
+        assert_eq!("".parse::<LabelSelector>(), Ok(LabelSelector::default()));
+    }
+
+    #[test]
+    fn label_selector_should_match_labels() {
+        let selector: LabelSelector = "team=payments,priority!=low".parse().unwrap();
+        let mut labels = HashMap::from([("team".to_string(), "payments".to_string())]);
+
+        assert!(selector.matches(&labels));
+
+        labels.insert("priority".to_string(), "low".to_string());
+        assert!(!selector.matches(&labels));
+    }
+
+    #[test]
+    fn label_selector_to_json_should_work() {
+        let selector: LabelSelector = "team=payments,!archived".parse().unwrap();
+
+        assert_eq!(
+            selector.to_json().unwrap(),
+            serde_json::json!([
+                {"op": "eq", "key": "team", "value": "payments"},
+                {"op": "not_exists", "key": "archived"},
+            ])
+        );
+        assert_eq!(LabelSelector::default().to_json(), None);
+    }
+}
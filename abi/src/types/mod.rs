@@ -1,13 +1,36 @@
+mod approval;
+mod audit;
+mod bulk;
+mod change;
+mod dry_run;
+mod history;
+mod ical;
+mod ical_import;
+mod label_selector;
+mod proto_json;
 mod request;
 mod reservation;
+mod reservation_filter;
+mod reservation_order;
 mod reservation_query;
+mod reservation_search;
 mod reservation_status;
+mod resource;
+mod webhook;
 
 use std::ops::Bound;
 
+pub use approval::RsvpApprovalDecision;
+pub use audit::RsvpUpdateType;
+pub use bulk::{ImportRow, CSV_COLUMNS};
+pub use change::RESERVATION_FIELDS;
 use chrono::{DateTime, Utc};
+pub use ical::{to_ical, ICAL_PRODID};
+pub use ical_import::{parse_ical, IcalEvent, IcalProperty, Occurrence};
+pub use label_selector::{is_valid_label_key, LabelRequirement, LabelSelector};
 use prost_types::Timestamp;
 pub use reservation::*;
+pub use reservation_filter::FilterCursor;
 use sqlx::postgres::types::PgRange;
 
 use crate::{convert_to_utc_time, Error};
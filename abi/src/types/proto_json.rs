@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use prost_types::{Struct, Timestamp};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    convert_to_json, convert_to_struct, convert_to_timestamp, convert_to_utc_time, ConflictDetail,
+    DryRunResult, Error, FilterPager, Reservation, ReservationFilter, ReservationOrderBy,
+    ReservationQuery, ReservationStatus,
+};
+
+// the proto3 JSON mapping of the messages served over HTTP: lowerCamelCase names (the proto
+// names are accepted too), 64 bit integers as strings, enums by name, timestamps in RFC 3339.
+// Numbers and booleans are also accepted as strings, so query parameters map the same way
+
+impl Reservation {
+    pub fn to_proto_json(&self) -> Value {
+        json!({
+            "id": self.id.to_string(),
+            "tenantId": self.tenant_id,
+            "resourceId": self.resource_id,
+            "userId": self.user_id,
+            "status": status_name(self.status),
+            "start": self.start.as_ref().map(format_time),
+            "end": self.end.as_ref().map(format_time),
+            "note": self.note,
+            "labels": self.labels,
+            "attributes": self.attributes.as_ref().map(convert_to_json),
+        })
+    }
+
+    pub fn from_proto_json(value: Value) -> Result<Self, Error> {
+        let mut fields = Fields::new(value)?;
+
+        let rsvp = Self {
+            id: fields.int64("id")?,
+            tenant_id: fields.string("tenant_id")?,
+            resource_id: fields.string("resource_id")?,
+            user_id: fields.string("user_id")?,
+            status: fields.status("status")? as i32,
+            start: fields.timestamp("start")?,
+            end: fields.timestamp("end")?,
+            note: fields.string("note")?,
+            labels: fields.labels("labels")?,
+            attributes: fields.attributes("attributes")?,
+        };
+        fields.finish()?;
+
+        Ok(rsvp)
+    }
+}
+
+impl ReservationQuery {
+    pub fn from_proto_json(value: Value) -> Result<Self, Error> {
+        let mut fields = Fields::new(value)?;
+
+        let query = Self {
+            resource_id: fields.string("resource_id")?,
+            user_id: fields.string("user_id")?,
+            status: fields.status("status")? as i32,
+            start: fields.timestamp("start")?,
+            end: fields.timestamp("end")?,
+            page: fields.int32("page")?,
+            page_size: fields.int32("page_size")?,
+            is_desc: fields.bool("is_desc")?,
+            order_by: fields.order_by("order_by")? as i32,
+            label_selector: fields.string("label_selector")?,
+        };
+        fields.finish()?;
+
+        Ok(query)
+    }
+}
+
+impl ReservationFilter {
+    pub fn from_proto_json(value: Value) -> Result<Self, Error> {
+        let mut fields = Fields::new(value)?;
+
+        let filter = Self {
+            resource_id: fields.string("resource_id")?,
+            user_id: fields.string("user_id")?,
+            status: fields.status("status")? as i32,
+            cursor: fields.int64("cursor")?,
+            is_desc: fields.bool("is_desc")?,
+            page_size: fields.int32("page_size")?,
+            order_by: fields.order_by("order_by")? as i32,
+            cursor_token: fields.string("cursor_token")?,
+            label_selector: fields.string("label_selector")?,
+        };
+        fields.finish()?;
+
+        Ok(filter)
+    }
+}
+
+impl FilterPager {
+    pub fn to_proto_json(&self) -> Value {
+        json!({
+            "prev": self.prev.to_string(),
+            "next": self.next.to_string(),
+            "total": self.total.to_string(),
+            "prevToken": self.prev_token,
+            "nextToken": self.next_token,
+        })
+    }
+}
+
+impl ConflictDetail {
+    pub fn to_proto_json(&self) -> Value {
+        json!({
+            "id": self.id.to_string(),
+            "resourceId": self.resource_id,
+            "start": self.start.as_ref().map(format_time),
+            "end": self.end.as_ref().map(format_time),
+            "userId": self.user_id,
+            "status": status_name(self.status),
+        })
+    }
+}
+
+impl DryRunResult {
+    pub fn to_proto_json(&self) -> Value {
+        json!({
+            "ok": self.ok,
+            "reason": self.reason,
+            "message": self.message,
+            "conflicts": self
+                .conflicts
+                .iter()
+                .map(ConflictDetail::to_proto_json)
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// the fields of a JSON object, taken by their proto name or its lowerCamelCase form
+struct Fields(Map<String, Value>);
+
+impl Fields {
+    fn new(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Object(fields) => Ok(Self(fields)),
+            _ => Err(Error::InvalidJson("not a JSON object".to_string())),
+        }
+    }
+
+    /// the value of the field, None if it is absent or null
+    fn take(&mut self, name: &str) -> Option<Value> {
+        let value = match self.0.remove(&camel_case(name)) {
+            Some(value) => Some(value),
+            None => self.0.remove(name),
+        };
+
+        value.filter(|v| !v.is_null())
+    }
+
+    fn string(&mut self, name: &str) -> Result<String, Error> {
+        match self.take(name) {
+            None => Ok(String::new()),
+            Some(Value::String(s)) => Ok(s),
+            Some(_) => Err(invalid(name, "a string")),
+        }
+    }
+
+    fn int64(&mut self, name: &str) -> Result<i64, Error> {
+        match self.take(name) {
+            None => Ok(0),
+            Some(Value::Number(n)) => n.as_i64().ok_or_else(|| invalid(name, "an integer")),
+            Some(Value::String(s)) => s.parse().map_err(|_| invalid(name, "an integer")),
+            Some(_) => Err(invalid(name, "an integer")),
+        }
+    }
+
+    fn int32(&mut self, name: &str) -> Result<i32, Error> {
+        self.int64(name)?
+            .try_into()
+            .map_err(|_| invalid(name, "a 32 bit integer"))
+    }
+
+    fn bool(&mut self, name: &str) -> Result<bool, Error> {
+        match self.take(name) {
+            None => Ok(false),
+            Some(Value::Bool(b)) => Ok(b),
+            Some(Value::String(s)) => s.parse().map_err(|_| invalid(name, "a boolean")),
+            Some(_) => Err(invalid(name, "a boolean")),
+        }
+    }
+
+    /// by enum name, short name (e.g. "pending") or number
+    fn status(&mut self, name: &str) -> Result<ReservationStatus, Error> {
+        let status = match self.take(name) {
+            None => return Ok(ReservationStatus::Unknown),
+            Some(Value::Number(n)) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+            Some(Value::String(s)) => enum_value(&s, "RESERVATION_STATUS_", |s| {
+                ReservationStatus::from_str_name(s).map(|status| status as i32)
+            }),
+            Some(_) => None,
+        };
+
+        status
+            .and_then(|status| ReservationStatus::try_from(status).ok())
+            .ok_or_else(|| invalid(name, "a reservation status"))
+    }
+
+    fn order_by(&mut self, name: &str) -> Result<ReservationOrderBy, Error> {
+        let order_by = match self.take(name) {
+            None => return Ok(ReservationOrderBy::Id),
+            Some(Value::Number(n)) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+            Some(Value::String(s)) => enum_value(&s, "RESERVATION_ORDER_BY_", |s| {
+                ReservationOrderBy::from_str_name(s).map(|order_by| order_by as i32)
+            }),
+            Some(_) => None,
+        };
+
+        order_by
+            .and_then(|order_by| ReservationOrderBy::try_from(order_by).ok())
+            .ok_or_else(|| invalid(name, "a sort key"))
+    }
+
+    fn timestamp(&mut self, name: &str) -> Result<Option<Timestamp>, Error> {
+        match self.take(name) {
+            None => Ok(None),
+            Some(Value::String(s)) => DateTime::parse_from_rfc3339(&s)
+                .map(|dt| Some(convert_to_timestamp(dt.with_timezone(&Utc))))
+                .map_err(|_| invalid(name, "an RFC 3339 time")),
+            Some(_) => Err(invalid(name, "an RFC 3339 time")),
+        }
+    }
+
+    fn labels(&mut self, name: &str) -> Result<HashMap<String, String>, Error> {
+        match self.take(name) {
+            None => Ok(Default::default()),
+            Some(labels) => {
+                serde_json::from_value(labels).map_err(|_| invalid(name, "an object of strings"))
+            }
+        }
+    }
+
+    fn attributes(&mut self, name: &str) -> Result<Option<Struct>, Error> {
+        match self.take(name) {
+            None => Ok(None),
+            Some(attributes) => convert_to_struct(attributes)
+                .map(Some)
+                .ok_or_else(|| invalid(name, "an object")),
+        }
+    }
+
+    /// fail on the fields no message has
+    fn finish(self) -> Result<(), Error> {
+        match self.0.keys().next() {
+            Some(name) => Err(Error::InvalidJson(format!("unknown field {}", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut words = name.split('_');
+    let first = words.next().unwrap_or_default().to_string();
+
+    words.fold(first, |mut s, word| {
+        let mut chars = word.chars();
+        if let Some(c) = chars.next() {
+            s.extend(c.to_uppercase());
+            s.push_str(chars.as_str());
+        }
+        s
+    })
+}
+
+/// the number of an enum value given by name, with or without its prefix, or by number
+fn enum_value(s: &str, prefix: &str, from_name: impl Fn(&str) -> Option<i32>) -> Option<i32> {
+    let name = s.trim().to_uppercase();
+    let name = match name.starts_with(prefix) {
+        true => name,
+        false => format!("{}{}", prefix, name),
+    };
+
+    from_name(&name).or_else(|| s.trim().parse().ok())
+}
+
+fn invalid(name: &str, expected: &str) -> Error {
+    Error::InvalidJson(format!("{} is not {}", camel_case(name), expected))
+}
+
+fn status_name(status: i32) -> &'static str {
+    ReservationStatus::try_from(status)
+        .unwrap_or(ReservationStatus::Unknown)
+        .as_str_name()
+}
+
+fn format_time(ts: &Timestamp) -> String {
+    convert_to_utc_time(ts).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rsvp() -> Reservation {
+        let mut rsvp = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "late arrival",
+        );
+        rsvp.id = 42;
+        rsvp.tenant_id = "default".to_string();
+        rsvp.labels
+            .insert("team".to_string(), "payments".to_string());
+        rsvp.attributes = convert_to_struct(json!({ "guests": 2.0 }));
+        rsvp
+    }
+
+    #[test]
+    fn reservation_should_round_trip_as_proto_json() {
+        let value = rsvp().to_proto_json();
+
+        assert_eq!(value["id"], "42");
+        assert_eq!(value["resourceId"], "ocean-view-room-713");
+        assert_eq!(value["status"], "RESERVATION_STATUS_PENDING");
+        assert_eq!(value["start"], "2022-12-26T22:00:00Z");
+        assert_eq!(Reservation::from_proto_json(value).unwrap(), rsvp());
+    }
+
+    #[test]
+    fn proto_json_should_accept_proto_names_and_short_values() {
+        let rsvp = Reservation::from_proto_json(json!({
+            "resource_id": "room-1",
+            "userId": "john",
+            "status": "confirmed",
+            "start": "2024-01-01T08:00:00+01:00",
+            "end": "2024-01-01T09:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(rsvp.resource_id, "room-1");
+        assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+        assert_eq!(
+            rsvp.start.unwrap().seconds,
+            rsvp.end.unwrap().seconds - 7200
+        );
+
+        // query parameters are strings
+        let filter = ReservationFilter::from_proto_json(json!({
+            "user_id": "john",
+            "status": "RESERVATION_STATUS_PENDING",
+            "page_size": "20",
+            "is_desc": "true",
+            "order_by": "start",
+            "cursorToken": "5:2024-01-01T07:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(filter.page_size, 20);
+        assert!(filter.is_desc);
+        assert_eq!(filter.order_by, ReservationOrderBy::Start as i32);
+        assert_eq!(filter.cursor_token, "5:2024-01-01T07:00:00Z");
+    }
+
+    #[test]
+    fn proto_json_should_reject_invalid_fields() {
+        assert_eq!(
+            Reservation::from_proto_json(json!({ "status": "done" })),
+            Err(Error::InvalidJson(
+                "status is not a reservation status".to_string()
+            ))
+        );
+        assert_eq!(
+            ReservationQuery::from_proto_json(json!({ "start": "yesterday" })),
+            Err(Error::InvalidJson(
+                "start is not an RFC 3339 time".to_string()
+            ))
+        );
+        assert_eq!(
+            ReservationFilter::from_proto_json(json!({ "room": "1" })),
+            Err(Error::InvalidJson("unknown field room".to_string()))
+        );
+        assert!(Reservation::from_proto_json(json!([])).is_err());
+    }
+}
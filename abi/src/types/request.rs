@@ -1,6 +1,7 @@
 use crate::{
-    ConfirmRequest, FilterRequest, QueryRequest, Reservation, ReservationFilter, ReservationQuery,
-    ReserveRequest,
+    AuditLogRequest, AuditQuery, CancelRequest, ConfirmRequest, FilterRequest, GetHistoryRequest,
+    GetRequest, IcalFeedRequest, QueryRequest, Reservation, ReservationFilter, ReservationQuery,
+    ReservationSearch, ReserveRequest, Resource, SearchRequest, SetResourceRequest,
 };
 
 macro_rules! impl_new {
@@ -22,7 +23,38 @@ macro_rules! impl_new {
     };
 }
 
-impl_new!(ReserveRequest, reservation, Reservation);
 impl_new!(FilterRequest, filter, ReservationFilter);
 impl_new!(QueryRequest, query, ReservationQuery);
+impl_new!(SearchRequest, search, ReservationSearch);
+impl_new!(SetResourceRequest, resource, Resource);
+impl_new!(AuditLogRequest, query, AuditQuery);
+impl_new!(IcalFeedRequest, query, ReservationQuery);
 impl_new!(ConfirmRequest);
+impl_new!(CancelRequest);
+impl_new!(GetHistoryRequest);
+
+impl GetRequest {
+    pub fn new(value: i64) -> Self {
+        Self {
+            id: value,
+            as_of: None,
+        }
+    }
+}
+
+impl ReserveRequest {
+    pub fn new(value: Reservation) -> Self {
+        Self {
+            reservation: Some(value),
+            validate_only: false,
+        }
+    }
+
+    /// validate the reservation and check conflicts without creating it
+    pub fn new_validate_only(value: Reservation) -> Self {
+        Self {
+            reservation: Some(value),
+            validate_only: true,
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use chrono::{DateTime, FixedOffset, Utc};
 use sqlx::postgres::types::PgRange;
+use sqlx::types::Json;
 use sqlx::Row;
 use sqlx::{postgres::PgRow, FromRow};
+use std::collections::HashMap;
 use std::ops::Bound;
 
-use crate::{convert_to_timestamp, get_timespan, validate_range, Validator};
+use crate::{
+    convert_to_struct, convert_to_timestamp, get_timespan, is_valid_label_key, validate_range,
+    Validator,
+};
 use crate::{Error, Reservation, ReservationStatus};
 
 impl Validator for Reservation {
@@ -19,6 +24,10 @@ impl Validator for Reservation {
 
         validate_range(self.start.as_ref(), self.end.as_ref())?;
 
+        if let Some(key) = self.labels.keys().find(|k| !is_valid_label_key(k)) {
+            return Err(Error::InvalidLabel(key.clone()));
+        }
+
         Ok(())
     }
 }
@@ -39,6 +48,9 @@ impl Reservation {
             end: Some(convert_to_timestamp(end.with_timezone(&Utc))),
             note: note.into(),
             status: ReservationStatus::Pending as i32,
+            labels: HashMap::new(),
+            attributes: None,
+            tenant_id: String::new(),
         }
     }
 
@@ -47,9 +59,9 @@ impl Reservation {
     }
 }
 
-struct NativeRange<T> {
-    start: Option<T>,
-    end: Option<T>,
+pub(crate) struct NativeRange<T> {
+    pub(crate) start: Option<T>,
+    pub(crate) end: Option<T>,
 }
 
 impl<T> From<PgRange<T>> for NativeRange<T> {
@@ -89,6 +101,8 @@ impl FromRow<'_, PgRow> for Reservation {
         let end = timespan.end.unwrap();
 
         let status: RsvpStatus = row.get("status");
+        let labels: Json<HashMap<String, String>> = row.get("labels");
+        let attributes: Option<serde_json::Value> = row.get("attributes");
 
         Ok(Self {
             id: row.get("id"),
@@ -98,6 +112,9 @@ impl FromRow<'_, PgRow> for Reservation {
             end: Some(convert_to_timestamp(end)),
             note: row.get("note"),
             status: ReservationStatus::from(status) as i32,
+            labels: labels.0,
+            attributes: attributes.and_then(convert_to_struct),
+            tenant_id: row.get("tenant_id"),
         })
     }
 }
@@ -0,0 +1,161 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    Error, LabelSelector, ReservationFilter, ReservationOrderBy, ReservationStatus, Validator,
+};
+
+/// keyset pagination cursor, the id of the boundary row and the text form of its sort key.
+/// encoded as `id` or `id:value`, the value is absent when the list is ordered by id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterCursor {
+    pub id: i64,
+    pub value: Option<String>,
+}
+
+impl FilterCursor {
+    pub fn new(id: i64, value: Option<String>) -> Self {
+        Self { id, value }
+    }
+
+    /// check the value is a sort key of the given order, a cursor of another order (or a
+    /// tampered one) would otherwise fail the cast in the database
+    pub fn check_order(&self, order_by: ReservationOrderBy) -> Result<(), Error> {
+        let Some(value) = &self.value else {
+            return Ok(());
+        };
+
+        let valid = match order_by {
+            ReservationOrderBy::Start
+            | ReservationOrderBy::End
+            | ReservationOrderBy::CreatedAt
+            | ReservationOrderBy::UpdatedAt => value.parse::<prost_types::Timestamp>().is_ok(),
+            // ordered by id the value is ignored, users and resources are any text
+            ReservationOrderBy::Default
+            | ReservationOrderBy::Id
+            | ReservationOrderBy::UserId
+            | ReservationOrderBy::ResourceId => true,
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(Error::InvalidCursor(self.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for FilterCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}:{}", self.id, value),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+impl FromStr for FilterCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, value) = match s.split_once(':') {
+            Some((id, value)) => (id, Some(value.to_string())),
+            None => (s, None),
+        };
+
+        let id = id
+            .parse()
+            .map_err(|_| Error::InvalidCursor(s.to_string()))?;
+
+        Ok(Self { id, value })
+    }
+}
+
+impl Validator for ReservationFilter {
+    fn validate(&self) -> Result<(), Error> {
+        ReservationStatus::try_from(self.status).map_err(|_| Error::InvalidStatus(self.status))?;
+        ReservationOrderBy::try_from(self.order_by)
+            .map_err(|_| Error::InvalidOrderBy(self.order_by))?;
+
+        self.get_cursor()?;
+        self.label_selector.parse::<LabelSelector>()?;
+
+        Ok(())
+    }
+}
+
+impl ReservationFilter {
+    /// the cursor token takes precedence over the plain id cursor
+    pub fn get_cursor(&self) -> Result<FilterCursor, Error> {
+        if self.cursor_token.is_empty() {
+            return Ok(FilterCursor::new(self.cursor, None));
+        }
+
+        let cursor: FilterCursor = self.cursor_token.parse()?;
+        let order_by = ReservationOrderBy::try_from(self.order_by)
+            .map_err(|_| Error::InvalidOrderBy(self.order_by))?;
+        cursor.check_order(order_by)?;
+
+        Ok(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_cursor_should_round_trip() {
+        let cursor = FilterCursor::new(3, Some("2024-01-01T07:00:00Z".to_string()));
+        let token = cursor.to_string();
+
+        assert_eq!(token, "3:2024-01-01T07:00:00Z");
+        assert_eq!(token.parse::<FilterCursor>().unwrap(), cursor);
+
+        let cursor = FilterCursor::new(3, None);
+        assert_eq!(cursor.to_string().parse::<FilterCursor>().unwrap(), cursor);
+    }
+
+    #[test]
+    fn filter_cursor_should_reject_invalid_token() {
+        assert_eq!(
+            "abc:def".parse::<FilterCursor>(),
+            Err(Error::InvalidCursor("abc:def".to_string()))
+        );
+    }
+
+    #[test]
+    fn filter_cursor_should_match_the_order() {
+        let filter = |order_by: ReservationOrderBy, token: &str| ReservationFilter {
+            order_by: order_by as i32,
+            cursor_token: token.to_string(),
+            ..Default::default()
+        };
+
+        let ordered = filter(ReservationOrderBy::Start, "5:2024-01-01T07:00:00Z");
+        assert!(ordered.validate().is_ok());
+        assert!(filter(ReservationOrderBy::Start, "5").validate().is_ok());
+        assert!(filter(ReservationOrderBy::UserId, "5:abc")
+            .validate()
+            .is_ok());
+
+        for order_by in [ReservationOrderBy::Start, ReservationOrderBy::UpdatedAt] {
+            assert_eq!(
+                filter(order_by, "5:abc").validate(),
+                Err(Error::InvalidCursor("5:abc".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn filter_cursor_token_should_take_precedence() {
+        let filter = ReservationFilter {
+            cursor: 1,
+            cursor_token: "5:john".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filter.get_cursor().unwrap(),
+            FilterCursor::new(5, Some("john".to_string()))
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use std::fmt;
+
+use crate::ReservationOrderBy;
+
+impl fmt::Display for ReservationOrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReservationOrderBy::Default => write!(f, "default"),
+            ReservationOrderBy::Id => write!(f, "id"),
+            ReservationOrderBy::Start => write!(f, "start"),
+            ReservationOrderBy::End => write!(f, "end"),
+            ReservationOrderBy::CreatedAt => write!(f, "created_at"),
+            ReservationOrderBy::UpdatedAt => write!(f, "updated_at"),
+            ReservationOrderBy::UserId => write!(f, "user_id"),
+            ReservationOrderBy::ResourceId => write!(f, "resource_id"),
+        }
+    }
+}
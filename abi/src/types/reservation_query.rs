@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 use sqlx::postgres::types::PgRange;
 
-use crate::{get_timespan, validate_range, Error, ReservationQuery, ReservationStatus, Validator};
+use crate::{
+    get_timespan, validate_range, Error, LabelSelector, ReservationQuery, ReservationStatus,
+    Validator,
+};
 
 impl Validator for ReservationQuery {
     fn validate(&self) -> Result<(), Error> {
@@ -9,6 +12,8 @@ impl Validator for ReservationQuery {
 
         validate_range(self.start.as_ref(), self.end.as_ref())?;
 
+        self.label_selector.parse::<LabelSelector>()?;
+
         Ok(())
     }
 }
@@ -0,0 +1,72 @@
+use crate::{Error, LabelSelector, ReservationSearch, ReservationStatus, Validator};
+
+impl Validator for ReservationSearch {
+    fn validate(&self) -> Result<(), Error> {
+        if self.query.trim().is_empty() {
+            return Err(Error::InvalidSearchQuery(self.query.clone()));
+        }
+
+        ReservationStatus::try_from(self.status).map_err(|_| Error::InvalidStatus(self.status))?;
+
+        // the time window is optional, but must be valid if both ends are given
+        if let (Some(start), Some(end)) = (self.start.as_ref(), self.end.as_ref()) {
+            if start.seconds > end.seconds {
+                return Err(Error::InvalidTime);
+            }
+        }
+
+        self.label_selector.parse::<LabelSelector>()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Timestamp;
+
+    use super::*;
+    use crate::ReservationSearchBuilder;
+
+    #[test]
+    fn search_without_query_should_reject() {
+        let search = ReservationSearchBuilder::default()
+            .query("  ")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            search.validate(),
+            Err(Error::InvalidSearchQuery("  ".to_string()))
+        );
+    }
+
+    #[test]
+    fn search_with_open_window_should_work() {
+        let search = ReservationSearchBuilder::default()
+            .query("ceo")
+            .start(Timestamp {
+                seconds: 1,
+                nanos: 0,
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(search.validate(), Ok(()));
+
+        let search = ReservationSearchBuilder::default()
+            .query("ceo")
+            .start(Timestamp {
+                seconds: 1,
+                nanos: 0,
+            })
+            .end(Timestamp {
+                seconds: 0,
+                nanos: 0,
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(search.validate(), Err(Error::InvalidTime));
+    }
+}
@@ -0,0 +1,86 @@
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::{Error, Resource, Validator};
+
+impl Resource {
+    /// reservations of the resource must be approved by one of the approvers
+    pub fn with_approvers(id: impl Into<String>, approvers: &[&str]) -> Self {
+        Self {
+            id: id.into(),
+            requires_approval: true,
+            approvers: approvers.iter().map(|a| a.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Validator for Resource {
+    fn validate(&self) -> Result<(), Error> {
+        if self.id.is_empty() {
+            return Err(Error::InvalidResourceId(self.id.clone()));
+        }
+
+        if self.requires_approval && self.auto_confirm {
+            return Err(Error::InvalidResource(
+                "requires_approval and auto_confirm are exclusive".to_string(),
+            ));
+        }
+
+        if self.requires_approval && self.approvers.is_empty() {
+            return Err(Error::InvalidResource(
+                "a resource requiring approval needs approvers".to_string(),
+            ));
+        }
+
+        if self.approvers.iter().any(|a| a.is_empty()) {
+            return Err(Error::InvalidResource("empty approver".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Resource {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.get("id"),
+            requires_approval: row.get("requires_approval"),
+            approvers: row.get("approvers"),
+            auto_confirm: row.get("auto_confirm"),
+            tenant_id: row.get("tenant_id"),
+            resource_type: row.get("resource_type"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_policy_should_be_validated() {
+        assert!(Resource::with_approvers("boardroom", &["alice"])
+            .validate()
+            .is_ok());
+
+        let resource = Resource::with_approvers("boardroom", &[]);
+        assert!(matches!(
+            resource.validate(),
+            Err(Error::InvalidResource(_))
+        ));
+
+        let resource = Resource {
+            auto_confirm: true,
+            ..Resource::with_approvers("boardroom", &["alice"])
+        };
+        assert!(matches!(
+            resource.validate(),
+            Err(Error::InvalidResource(_))
+        ));
+
+        assert_eq!(
+            Resource::default().validate(),
+            Err(Error::InvalidResourceId("".to_string()))
+        );
+    }
+}
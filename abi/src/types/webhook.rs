@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgRow, types::Json, FromRow, Row};
+
+use crate::{
+    convert_to_timestamp, DeadLetter, Error, ListenResponse, Reservation, ReservationType,
+    Validator, Webhook,
+};
+
+impl Webhook {
+    /// true if the change should be delivered to the webhook
+    pub fn wants(&self, change: &ListenResponse) -> bool {
+        let Some(rsvp) = &change.reservation else {
+            return false;
+        };
+
+        (self.event_types.is_empty() || self.event_types.contains(&change.op))
+            && (self.resource_id.is_empty() || self.resource_id == rsvp.resource_id)
+            && (self.user_id.is_empty() || self.user_id == rsvp.user_id)
+    }
+}
+
+impl Validator for Webhook {
+    fn validate(&self) -> Result<(), Error> {
+        let url = url::Url::parse(&self.url)
+            .map_err(|e| Error::InvalidWebhook(format!("invalid url {}: {}", self.url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+            return Err(Error::InvalidWebhook(format!(
+                "url must be http or https: {}",
+                self.url
+            )));
+        }
+
+        if self.secret.is_empty() {
+            return Err(Error::InvalidWebhook("secret is required".to_string()));
+        }
+
+        if let Some(op) = self.event_types.iter().find(|op| {
+            !matches!(
+                ReservationType::try_from(**op),
+                Ok(ReservationType::Create | ReservationType::Update | ReservationType::Delete)
+            )
+        }) {
+            return Err(Error::InvalidWebhook(format!("invalid event type: {}", op)));
+        }
+
+        Ok(())
+    }
+}
+
+impl ListenResponse {
+    /// the JSON payload POSTed to the webhooks for the change
+    pub fn to_webhook_payload(&self) -> Value {
+        let event = match ReservationType::try_from(self.op) {
+            Ok(ReservationType::Create) => "reservation.created",
+            Ok(ReservationType::Update) => "reservation.updated",
+            Ok(ReservationType::Delete) => "reservation.deleted",
+            _ => "reservation.unknown",
+        };
+
+        json!({
+            "type": event,
+            "sequence": self.sequence,
+            "txid": self.txid,
+            "changed_fields": self.changed_fields,
+            "reservation": self.reservation.as_ref().map(Reservation::to_json),
+        })
+    }
+}
+
+impl FromRow<'_, PgRow> for Webhook {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.get("id"),
+            url: row.get("url"),
+            event_types: row.get("event_types"),
+            resource_id: row
+                .get::<Option<String>, _>("resource_id")
+                .unwrap_or_default(),
+            user_id: row.get::<Option<String>, _>("user_id").unwrap_or_default(),
+            secret: row.get("secret"),
+            tenant_id: row.get("tenant_id"),
+        })
+    }
+}
+
+impl FromRow<'_, PgRow> for DeadLetter {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let payload: Json<Value> = row.get("payload");
+        let failed_at: DateTime<Utc> = row.get("failed_at");
+
+        Ok(Self {
+            id: row.get("id"),
+            webhook_id: row.get("webhook_id"),
+            sequence: row.get("seq"),
+            payload: payload.0.to_string(),
+            attempts: row.get("attempts"),
+            last_error: row
+                .get::<Option<String>, _>("last_error")
+                .unwrap_or_default(),
+            failed_at: Some(convert_to_timestamp(failed_at)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(op: ReservationType) -> ListenResponse {
+        ListenResponse {
+            op: op as i32,
+            reservation: Some(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting",
+            )),
+            sequence: 7,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn webhook_should_filter_changes() {
+        let webhook = Webhook {
+            url: "https://example.com/hooks".to_string(),
+            secret: "s3cret".to_string(),
+            event_types: vec![ReservationType::Create as i32],
+            resource_id: "room_01".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(webhook.validate(), Ok(()));
+
+        assert!(webhook.wants(&change(ReservationType::Create)));
+        assert!(!webhook.wants(&change(ReservationType::Delete)));
+        let other_user = Webhook {
+            user_id: "lei".to_string(),
+            ..webhook.clone()
+        };
+        assert!(!other_user.wants(&change(ReservationType::Create)));
+
+        for url in ["ftp://example.com", "http://", "https//example.com"] {
+            let invalid = Webhook {
+                url: url.to_string(),
+                ..webhook.clone()
+            };
+            assert!(matches!(invalid.validate(), Err(Error::InvalidWebhook(_))));
+        }
+    }
+
+    #[test]
+    fn webhook_payload_should_describe_the_change() {
+        let payload = change(ReservationType::Create).to_webhook_payload();
+
+        assert_eq!(payload["type"], "reservation.created");
+        assert_eq!(payload["sequence"], 7);
+        assert_eq!(payload["reservation"]["user_id"], "john");
+        assert_eq!(payload["reservation"]["status"], "pending");
+        assert_eq!(payload["reservation"]["start"], "2022-12-26T22:00:00+00:00");
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
-use prost_types::Timestamp;
+use prost_types::{value::Kind, ListValue, Struct, Timestamp, Value};
 
 pub fn convert_to_utc_time(ts: &Timestamp) -> DateTime<Utc> {
     let naive_datetime = NaiveDateTime::from_timestamp_opt(ts.seconds, ts.nanos as u32);
@@ -15,6 +17,58 @@ pub fn convert_to_timestamp(dt: DateTime<Utc>) -> Timestamp {
     }
 }
 
+pub fn convert_to_json(st: &Struct) -> serde_json::Value {
+    serde_json::Value::Object(
+        st.fields
+            .iter()
+            .map(|(k, v)| (k.clone(), value_to_json(v)))
+            .collect(),
+    )
+}
+
+/// only json objects can be converted to a struct
+pub fn convert_to_struct(value: serde_json::Value) -> Option<Struct> {
+    match json_to_value(value).kind {
+        Some(Kind::StructValue(st)) => Some(st),
+        _ => None,
+    }
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match &value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::NumberValue(n)) => serde_json::Number::from_f64(*n)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Some(Kind::StringValue(s)) => serde_json::Value::String(s.clone()),
+        Some(Kind::BoolValue(b)) => serde_json::Value::Bool(*b),
+        Some(Kind::StructValue(st)) => convert_to_json(st),
+        Some(Kind::ListValue(list)) => {
+            serde_json::Value::Array(list.values.iter().map(value_to_json).collect())
+        }
+    }
+}
+
+fn json_to_value(value: serde_json::Value) -> Value {
+    let kind = match value {
+        serde_json::Value::Null => Kind::NullValue(0),
+        serde_json::Value::Bool(b) => Kind::BoolValue(b),
+        serde_json::Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        serde_json::Value::String(s) => Kind::StringValue(s),
+        serde_json::Value::Array(values) => Kind::ListValue(ListValue {
+            values: values.into_iter().map(json_to_value).collect(),
+        }),
+        serde_json::Value::Object(map) => Kind::StructValue(Struct {
+            fields: map
+                .into_iter()
+                .map(|(k, v)| (k, json_to_value(v)))
+                .collect::<BTreeMap<_, _>>(),
+        }),
+    };
+
+    Value { kind: Some(kind) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +98,19 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn struct_json_conversion_should_round_trip() {
+        let json = serde_json::json!({
+            "seats": 12.0,
+            "catering": true,
+            "equipment": ["projector", "whiteboard"],
+            "contact": {"name": "john", "phone": null},
+        });
+
+        let st = convert_to_struct(json.clone()).unwrap();
+
+        assert_eq!(convert_to_json(&st), json);
+        assert_eq!(convert_to_struct(serde_json::json!([1, 2])), None);
+    }
 }
@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use abi::ReservationStatus;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::{output::Format, time::Zone};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "rsvp",
+    version,
+    about = "Command-line client for the reservation service"
+)]
+pub struct Cli {
+    /// address of the reservation service
+    #[arg(
+        long,
+        env = "RSVP_SERVER",
+        default_value = "http://127.0.0.1:50051",
+        global = true
+    )]
+    pub server: String,
+
+    /// JWT to authenticate with
+    #[arg(long, env = "RSVP_TOKEN", hide_env_values = true, global = true)]
+    pub token: Option<String>,
+
+    /// tenant to act in, the one of the token or the default one if not given
+    #[arg(long, env = "RSVP_TENANT", global = true)]
+    pub tenant: Option<String>,
+
+    /// CA certificate (pem) to verify the service with, connects with TLS
+    #[arg(long, env = "RSVP_CA_CERT", global = true)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// time zone of the times given and shown, an IANA name like Europe/Berlin or "local"
+    #[arg(long, env = "RSVP_TZ", default_value = "local", global = true)]
+    pub tz: Zone,
+
+    /// output format
+    #[arg(short, long, value_enum, default_value_t = Format::Table, global = true)]
+    pub output: Format,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// reserve a resource
+    Reserve {
+        #[arg(short, long)]
+        resource: String,
+        /// user to reserve for, the one of the token if not given
+        #[arg(short, long)]
+        user: Option<String>,
+        /// e.g. "tomorrow 3pm", "2024-03-01 09:00" or RFC 3339
+        #[arg(long)]
+        start: String,
+        #[arg(long)]
+        end: String,
+        #[arg(long, default_value = "")]
+        note: String,
+        /// label of the reservation, repeat for more
+        #[arg(short, long = "label", value_name = "KEY=VALUE", value_parser = parse_pair)]
+        labels: Vec<(String, String)>,
+        /// check the reservation and its conflicts without making it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// confirm a pending reservation
+    Confirm { id: i64 },
+    /// change the note of a reservation
+    Update {
+        id: i64,
+        #[arg(long)]
+        note: String,
+    },
+    /// cancel a reservation
+    Cancel { id: i64 },
+    /// show a reservation
+    Get { id: i64 },
+    /// list the reservations in a time window
+    Query {
+        #[arg(short, long)]
+        user: Option<String>,
+        #[arg(short, long)]
+        resource: Option<String>,
+        #[arg(long)]
+        start: String,
+        #[arg(long)]
+        end: String,
+        #[arg(long, value_enum, default_value_t = Status::Pending)]
+        status: Status,
+        /// e.g. "team=payments,priority!=low"
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+        #[arg(long, default_value_t = 1)]
+        page: i32,
+        #[arg(long, default_value_t = 10)]
+        page_size: i32,
+        #[arg(long)]
+        desc: bool,
+    },
+    /// page through the reservations
+    Filter {
+        #[arg(short, long)]
+        user: Option<String>,
+        #[arg(short, long)]
+        resource: Option<String>,
+        #[arg(long, value_enum, default_value_t = Status::Pending)]
+        status: Status,
+        /// e.g. "team=payments,priority!=low"
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+        /// cursor token of the page to show, printed with the previous page
+        #[arg(long)]
+        cursor: Option<String>,
+        #[arg(long, default_value_t = 10)]
+        page_size: i32,
+        #[arg(long)]
+        desc: bool,
+    },
+    /// follow the changes of the reservations
+    Listen {
+        /// only show the updates changing these fields, e.g. note,start
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        /// resume after the change with this sequence number, 0 for every change. Only the changes
+        /// made from now on are shown without it
+        #[arg(long)]
+        after: Option<i64>,
+    },
+    /// reserve the events of an iCalendar file, "-" reads it from stdin
+    ImportIcal {
+        file: PathBuf,
+        /// resource of the events whose location isn't mapped
+        #[arg(short, long)]
+        resource: Option<String>,
+        /// user of the events whose organizer isn't mapped, the one of the token if not given
+        #[arg(short, long)]
+        user: Option<String>,
+        /// resource of the events at a location, repeat for more
+        #[arg(long, value_name = "LOCATION=RESOURCE", value_parser = parse_pair)]
+        map_resource: Vec<(String, String)>,
+        /// user of the events of an organizer (email address or name), repeat for more
+        #[arg(long, value_name = "ORGANIZER=USER", value_parser = parse_pair)]
+        map_user: Vec<(String, String)>,
+        /// expand recurring events up to this time, a year from now if not given
+        #[arg(long)]
+        until: Option<String>,
+        /// show what would be reserved without reserving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Status {
+    Pending,
+    Confirmed,
+    Blocked,
+}
+
+impl From<Status> for ReservationStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Pending => ReservationStatus::Pending,
+            Status::Confirmed => ReservationStatus::Confirmed,
+            Status::Blocked => ReservationStatus::Blocked,
+        }
+    }
+}
+
+fn parse_pair(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_should_parse() {
+        let cli = Cli::try_parse_from([
+            "rsvp",
+            "--tz",
+            "Europe/Berlin",
+            "-o",
+            "json",
+            "reserve",
+            "-r",
+            "room-1",
+            "--start",
+            "tomorrow 3pm",
+            "--end",
+            "tomorrow 4pm",
+            "-l",
+            "team=payments",
+            "--dry-run",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.output, Format::Json);
+        assert_eq!(cli.tz, "Europe/Berlin".parse().unwrap());
+        let Command::Reserve {
+            resource,
+            labels,
+            dry_run,
+            ..
+        } = cli.command
+        else {
+            panic!("expect reserve");
+        };
+        assert_eq!(resource, "room-1");
+        assert_eq!(labels, [("team".to_string(), "payments".to_string())]);
+        assert!(dry_run);
+    }
+
+    #[test]
+    fn invalid_flags_should_fail() {
+        let cli = Cli::try_parse_from(["rsvp", "reserve", "-r", "room-1", "-l", "team"]);
+        assert!(cli.is_err());
+
+        let cli = Cli::try_parse_from(["rsvp", "--tz", "Mars/Olympus_Mons", "get", "1"]);
+        assert!(cli.is_err());
+    }
+}
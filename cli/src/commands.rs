@@ -0,0 +1,274 @@
+use std::{io::Read, process::ExitCode};
+
+use abi::{
+    CancelRequest, ConfirmRequest, FilterRequest, GetRequest, IcalImportRequest, ListenRequest,
+    QueryRequest, Reservation, ReservationFilter, ReservationQuery, ReservationStatus,
+    ReserveRequest, UpdateRequest,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reservation_client::{ReservationClient, ServiceClient};
+use serde_json::json;
+
+use crate::{
+    args::{Cli, Command},
+    exit::{reason_code, InvalidInput, CONFLICT},
+    output::Printer,
+};
+
+impl Cli {
+    pub async fn run(self) -> Result<ExitCode> {
+        let printer = Printer::new(self.output, self.tz);
+        let now = Utc::now();
+        let time =
+            |s: &str| -> Result<DateTime<Utc>> { Ok(self.tz.parse(s, now).map_err(InvalidInput)?) };
+        let timestamp = |s: &str| time(s).map(abi::convert_to_timestamp);
+        let mut client = self.connect().await?;
+
+        match &self.command {
+            Command::Reserve {
+                resource,
+                user,
+                start,
+                end,
+                note,
+                labels,
+                dry_run,
+            } => {
+                let rsvp = Reservation {
+                    resource_id: resource.clone(),
+                    user_id: user.clone().unwrap_or_default(),
+                    status: ReservationStatus::Pending as i32,
+                    start: Some(timestamp(start)?),
+                    end: Some(timestamp(end)?),
+                    note: note.clone(),
+                    labels: labels.iter().cloned().collect(),
+                    ..Default::default()
+                };
+                let request = ReserveRequest {
+                    reservation: Some(rsvp),
+                    validate_only: *dry_run,
+                };
+                let response = client.reserve(request).await?.into_inner();
+
+                if let Some(dry_run) = response.dry_run.filter(|d| !d.ok) {
+                    let conflicts: Vec<_> = dry_run
+                        .conflicts
+                        .iter()
+                        .map(|c| {
+                            json!({
+                                "id": c.id,
+                                "resource_id": c.resource_id,
+                                "start": c.start.as_ref().map(|ts| self.tz.format(ts)),
+                                "end": c.end.as_ref().map(|ts| self.tz.format(ts)),
+                            })
+                        })
+                        .collect();
+                    print!(
+                        "{}",
+                        printer.value(&json!({
+                            "reason": dry_run.reason,
+                            "message": dry_run.message,
+                            "conflicts": conflicts,
+                        }))
+                    );
+                    return Ok(ExitCode::from(reason_code(&dry_run.reason)));
+                }
+                if let Some(rsvp) = response.reservation {
+                    print!("{}", printer.reservation(&rsvp));
+                }
+            }
+            Command::Confirm { id } => {
+                let response = client.confirm(ConfirmRequest::new(*id)).await?;
+                print!(
+                    "{}",
+                    printer.reservation(&reservation(response.into_inner().reservation)?)
+                );
+            }
+            Command::Update { id, note } => {
+                let request = UpdateRequest {
+                    id: *id,
+                    note: note.clone(),
+                };
+                let response = client.update(request).await?;
+                print!(
+                    "{}",
+                    printer.reservation(&reservation(response.into_inner().reservation)?)
+                );
+            }
+            Command::Cancel { id } => {
+                let response = client.cancel(CancelRequest::new(*id)).await?;
+                print!(
+                    "{}",
+                    printer.reservation(&reservation(response.into_inner().reservation)?)
+                );
+            }
+            Command::Get { id } => {
+                let response = client.get(GetRequest::new(*id)).await?;
+                print!(
+                    "{}",
+                    printer.reservation(&reservation(response.into_inner().reservation)?)
+                );
+            }
+            Command::Query {
+                user,
+                resource,
+                start,
+                end,
+                status,
+                selector,
+                page,
+                page_size,
+                desc,
+            } => {
+                let query = ReservationQuery {
+                    user_id: user.clone().unwrap_or_default(),
+                    resource_id: resource.clone().unwrap_or_default(),
+                    status: ReservationStatus::from(*status) as i32,
+                    start: Some(timestamp(start)?),
+                    end: Some(timestamp(end)?),
+                    page: *page,
+                    page_size: *page_size,
+                    is_desc: *desc,
+                    label_selector: selector.clone().unwrap_or_default(),
+                    ..Default::default()
+                };
+                let mut stream = client.query(QueryRequest::new(query)).await?.into_inner();
+
+                let mut rsvps = Vec::new();
+                while let Some(rsvp) = stream.message().await? {
+                    rsvps.push(rsvp);
+                }
+                print!("{}", printer.reservations(&rsvps));
+            }
+            Command::Filter {
+                user,
+                resource,
+                status,
+                selector,
+                cursor,
+                page_size,
+                desc,
+            } => {
+                let filter = ReservationFilter {
+                    user_id: user.clone().unwrap_or_default(),
+                    resource_id: resource.clone().unwrap_or_default(),
+                    status: ReservationStatus::from(*status) as i32,
+                    cursor_token: cursor.clone().unwrap_or_default(),
+                    page_size: *page_size,
+                    is_desc: *desc,
+                    label_selector: selector.clone().unwrap_or_default(),
+                    ..Default::default()
+                };
+                let response = client
+                    .filter(FilterRequest::new(filter))
+                    .await?
+                    .into_inner();
+                let pager = response.pager.unwrap_or_default();
+
+                if self.output == crate::output::Format::Table {
+                    print!("{}", printer.reservations(&response.reservations));
+                    if !pager.next_token.is_empty() {
+                        eprintln!("next page: --cursor {}", pager.next_token);
+                    }
+                } else {
+                    let rsvps: Vec<_> = response
+                        .reservations
+                        .iter()
+                        .map(Reservation::to_json)
+                        .collect();
+                    print!(
+                        "{}",
+                        printer.value(&json!({
+                            "reservations": rsvps,
+                            "pager": {
+                                "total": pager.total,
+                                "prev_token": pager.prev_token,
+                                "next_token": pager.next_token,
+                            },
+                        }))
+                    );
+                }
+            }
+            Command::Listen { fields, after } => {
+                let request = ListenRequest {
+                    changed_fields: fields.clone(),
+                    after_sequence: *after,
+                };
+                let mut stream = client.listen(request).await?.into_inner();
+                while let Some(change) = stream.message().await? {
+                    print!("{}", printer.change(&change));
+                }
+            }
+            Command::ImportIcal {
+                file,
+                resource,
+                user,
+                map_resource,
+                map_user,
+                until,
+                dry_run,
+            } => {
+                let mut ical = String::new();
+                if file.as_os_str() == "-" {
+                    std::io::stdin().read_to_string(&mut ical)?;
+                } else {
+                    ical = std::fs::read_to_string(file)
+                        .with_context(|| format!("can't read {}", file.display()))?;
+                }
+
+                let request = IcalImportRequest {
+                    ical,
+                    resource_id: resource.clone().unwrap_or_default(),
+                    resource_map: map_resource.iter().cloned().collect(),
+                    user_id: user.clone().unwrap_or_default(),
+                    user_map: map_user.iter().cloned().collect(),
+                    // floating times of the calendar are in the zone of the flag
+                    timezone: self.tz.name().map_err(InvalidInput)?,
+                    until: until.as_deref().map(timestamp).transpose()?,
+                    dry_run: *dry_run,
+                };
+                let response = client.import_ical(request).await?.into_inner();
+                print!("{}", printer.ical_results(&response.results));
+                eprintln!(
+                    "created: {}, skipped: {}, conflicts: {}",
+                    response.created, response.skipped, response.conflicts
+                );
+
+                if response.conflicts > 0 {
+                    return Ok(ExitCode::from(CONFLICT));
+                }
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    async fn connect(&self) -> Result<ServiceClient> {
+        let mut builder = ReservationClient::builder().endpoint(self.server.clone());
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        if let Some(tenant) = &self.tenant {
+            builder = builder.tenant(tenant);
+        }
+        if let Some(ca_cert) = &self.ca_cert {
+            let pem = std::fs::read(ca_cert)
+                .with_context(|| format!("can't read {}", ca_cert.display()))?;
+            builder = builder.ca_certificate(pem);
+        }
+
+        let client = builder.connect().await.map_err(|e| match e {
+            reservation_client::Error::InvalidConfig(message) => InvalidInput(message).into(),
+            reservation_client::Error::Transport(e) => anyhow::Error::new(e),
+            e => anyhow::Error::new(e),
+        })?;
+
+        Ok(client.service_client())
+    }
+}
+
+/// the reservation of a response, the service always sends one
+fn reservation(rsvp: Option<Reservation>) -> Result<Reservation> {
+    rsvp.context("the service sent no reservation")
+}
@@ -0,0 +1,92 @@
+use std::process::ExitCode;
+
+use abi::StatusDetails;
+use tonic::{Code, Status};
+
+/// anything else going wrong
+pub const FAILURE: u8 = 1;
+/// the request is invalid: bad flags, times or fields the service rejects
+pub const INVALID: u8 = 2;
+/// the reservation conflicts with existing ones
+pub const CONFLICT: u8 = 3;
+pub const NOT_FOUND: u8 = 4;
+/// the caller isn't authenticated or not allowed to do it
+pub const DENIED: u8 = 5;
+/// the service can't be reached
+pub const UNAVAILABLE: u8 = 6;
+
+/// exit code of an error, the ones of a failed rpc tell why it failed
+pub fn exit_code(e: &anyhow::Error) -> ExitCode {
+    let code = if let Some(status) = e.downcast_ref::<Status>() {
+        status_code(status)
+    } else if e.downcast_ref::<tonic::transport::Error>().is_some() {
+        UNAVAILABLE
+    } else if e.downcast_ref::<InvalidInput>().is_some() {
+        INVALID
+    } else {
+        FAILURE
+    };
+
+    ExitCode::from(code)
+}
+
+/// exit code of a reason of the error details of the service, e.g. the one of a dry run
+pub fn reason_code(reason: &str) -> u8 {
+    match reason {
+        "RESERVATION_CONFLICT" => CONFLICT,
+        "NOT_FOUND" => NOT_FOUND,
+        "UNAUTHENTICATED" | "PERMISSION_DENIED" => DENIED,
+        "UNKNOWN" | "DATABASE_ERROR" => FAILURE,
+        _ => INVALID,
+    }
+}
+
+fn status_code(status: &Status) -> u8 {
+    if let Some(info) = StatusDetails::from_status(status).error_info {
+        return reason_code(&info.reason);
+    }
+
+    match status.code() {
+        Code::AlreadyExists => CONFLICT,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => INVALID,
+        Code::NotFound => NOT_FOUND,
+        Code::Unauthenticated | Code::PermissionDenied => DENIED,
+        Code::Unavailable | Code::DeadlineExceeded => UNAVAILABLE,
+        _ => FAILURE,
+    }
+}
+
+/// input rejected before anything is sent, e.g. a time that can't be parsed
+#[derive(Debug)]
+pub struct InvalidInput(pub String);
+
+impl std::fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_errors_should_map_to_exit_codes() {
+        let conflict = Status::from(abi::Error::ConflictReservation(Default::default()));
+        assert_eq!(status_code(&conflict), CONFLICT);
+        let invalid = Status::from(abi::Error::InvalidTime);
+        assert_eq!(status_code(&invalid), INVALID);
+        let denied = Status::from(abi::Error::PermissionDenied("no".to_string()));
+        assert_eq!(status_code(&denied), DENIED);
+
+        // statuses without details go by their code
+        assert_eq!(status_code(&Status::not_found("gone")), NOT_FOUND);
+        assert_eq!(status_code(&Status::unavailable("down")), UNAVAILABLE);
+        assert_eq!(status_code(&Status::internal("oops")), FAILURE);
+
+        let e = anyhow::Error::new(InvalidInput("invalid time".to_string()));
+        assert_eq!(exit_code(&e), ExitCode::from(INVALID));
+    }
+}
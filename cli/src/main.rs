@@ -0,0 +1,27 @@
+mod args;
+mod commands;
+mod exit;
+mod output;
+mod time;
+
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use args::Cli;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.run().await {
+        Ok(code) => code,
+        Err(e) => {
+            match e.downcast_ref::<tonic::Status>() {
+                Some(status) => eprintln!("error: {}", status.message()),
+                None => eprintln!("error: {:#}", e),
+            }
+            exit::exit_code(&e)
+        }
+    }
+}
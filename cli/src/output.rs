@@ -0,0 +1,214 @@
+use abi::{IcalImportOutcome, IcalImportResult, ListenResponse, Reservation, ReservationStatus};
+use clap::ValueEnum;
+use comfy_table::{presets, Table};
+use serde_json::{json, Value};
+
+use crate::time::Zone;
+
+/// how results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Yaml,
+}
+
+/// renders results in the chosen format, the times of tables in the chosen zone
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    pub format: Format,
+    pub zone: Zone,
+}
+
+impl Printer {
+    pub fn new(format: Format, zone: Zone) -> Self {
+        Self { format, zone }
+    }
+
+    pub fn reservation(&self, rsvp: &Reservation) -> String {
+        match self.format {
+            Format::Table => self.reservations(std::slice::from_ref(rsvp)),
+            _ => self.value(&rsvp.to_json()),
+        }
+    }
+
+    pub fn reservations(&self, rsvps: &[Reservation]) -> String {
+        if self.format != Format::Table {
+            return self.value(&Value::Array(
+                rsvps.iter().map(Reservation::to_json).collect(),
+            ));
+        }
+
+        let mut table = table(["ID", "USER", "RESOURCE", "STATUS", "START", "END", "NOTE"]);
+        for rsvp in rsvps {
+            table.add_row(vec![
+                rsvp.id.to_string(),
+                rsvp.user_id.clone(),
+                rsvp.resource_id.clone(),
+                status(rsvp.status),
+                self.time(rsvp.start.as_ref()),
+                self.time(rsvp.end.as_ref()),
+                rsvp.note.clone(),
+            ]);
+        }
+
+        format!("{}\n", table)
+    }
+
+    /// a change of the feed, a line of its own in every format so the feed can be followed
+    pub fn change(&self, change: &ListenResponse) -> String {
+        let payload = change.to_webhook_payload();
+        match self.format {
+            Format::Json => format!("{}\n", payload),
+            Format::Yaml => format!("---\n{}", self.value(&payload)),
+            Format::Table => {
+                let rsvp = change.reservation.clone().unwrap_or_default();
+                let fields = match change.changed_fields.is_empty() {
+                    true => String::new(),
+                    false => format!(" [{}]", change.changed_fields.join(",")),
+                };
+                format!(
+                    "{}\t{}{}\t{}\t{}\t{}\t{}\t{}\n",
+                    change.sequence,
+                    payload["type"].as_str().unwrap_or_default(),
+                    fields,
+                    rsvp.id,
+                    rsvp.user_id,
+                    rsvp.resource_id,
+                    self.time(rsvp.start.as_ref()),
+                    self.time(rsvp.end.as_ref()),
+                )
+            }
+        }
+    }
+
+    pub fn ical_results(&self, results: &[IcalImportResult]) -> String {
+        let outcome = |r: &IcalImportResult| {
+            IcalImportOutcome::try_from(r.outcome)
+                .unwrap_or(IcalImportOutcome::Unknown)
+                .as_str_name()
+                .trim_start_matches("ICAL_IMPORT_OUTCOME_")
+                .to_lowercase()
+        };
+
+        if self.format != Format::Table {
+            let results = results
+                .iter()
+                .map(|r| {
+                    json!({
+                        "uid": r.uid,
+                        "outcome": outcome(r),
+                        "reservation": r.reservation.as_ref().map(Reservation::to_json),
+                        "reason": r.reason,
+                    })
+                })
+                .collect();
+            return self.value(&Value::Array(results));
+        }
+
+        let mut table = table(["UID", "OUTCOME", "ID", "RESOURCE", "START", "END", "REASON"]);
+        for r in results {
+            let rsvp = r.reservation.clone().unwrap_or_default();
+            table.add_row(vec![
+                r.uid.clone(),
+                outcome(r),
+                rsvp.id.to_string(),
+                rsvp.resource_id,
+                self.time(rsvp.start.as_ref()),
+                self.time(rsvp.end.as_ref()),
+                r.reason.clone(),
+            ]);
+        }
+
+        format!("{}\n", table)
+    }
+
+    /// any other result, tables show it as YAML
+    pub fn value(&self, value: &Value) -> String {
+        match self.format {
+            Format::Json => format!("{:#}\n", value),
+            Format::Table | Format::Yaml => serde_yaml::to_string(value).unwrap_or_default(),
+        }
+    }
+
+    fn time(&self, ts: Option<&prost_types::Timestamp>) -> String {
+        ts.map(|ts| self.zone.format(ts)).unwrap_or_default()
+    }
+}
+
+fn table<const N: usize>(header: [&str; N]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(presets::NOTHING).set_header(header);
+    table
+}
+
+fn status(status: i32) -> String {
+    ReservationStatus::try_from(status)
+        .unwrap_or(ReservationStatus::Unknown)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rsvp() -> Reservation {
+        let mut rsvp = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "late arrival",
+        );
+        rsvp.id = 42;
+        rsvp
+    }
+
+    fn printer(format: Format) -> Printer {
+        Printer::new(format, "America/Denver".parse().unwrap())
+    }
+
+    #[test]
+    fn reservations_should_print_as_table() {
+        let table = printer(Format::Table).reservations(&[rsvp()]);
+        let lines: Vec<_> = table.lines().map(str::split_whitespace).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0].clone().collect::<Vec<_>>(),
+            ["ID", "USER", "RESOURCE", "STATUS", "START", "END", "NOTE"]
+        );
+        assert!(table.contains("2022-12-26 15:00 MST"));
+        assert!(table.contains("pending"));
+    }
+
+    #[test]
+    fn reservations_should_print_as_json_and_yaml() {
+        let json = printer(Format::Json).reservations(&[rsvp()]);
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["id"], 42);
+        assert_eq!(value[0]["start"], "2022-12-26T22:00:00+00:00");
+
+        let yaml = printer(Format::Yaml).reservation(&rsvp());
+        let value: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(value["user_id"], "john");
+    }
+
+    #[test]
+    fn changes_should_print_a_line_each() {
+        let change = ListenResponse {
+            op: abi::ReservationType::Update as i32,
+            reservation: Some(rsvp()),
+            changed_fields: vec!["note".to_string()],
+            sequence: 7,
+            txid: 1,
+        };
+
+        let line = printer(Format::Table).change(&change);
+        assert!(line.starts_with("7\treservation.updated [note]\t42\tjohn\t"));
+        assert_eq!(line.lines().count(), 1);
+
+        let line = printer(Format::Json).change(&change);
+        assert_eq!(line.lines().count(), 1);
+        assert!(printer(Format::Yaml).change(&change).starts_with("---\n"));
+    }
+}
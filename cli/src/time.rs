@@ -0,0 +1,125 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use interim::{parse_date_string, Dialect};
+use prost_types::Timestamp;
+
+/// the time zone times are given and shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    Local,
+    Tz(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Zone::Local),
+            _ => s
+                .parse()
+                .map(Zone::Tz)
+                .map_err(|_| format!("unknown time zone {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => write!(f, "local"),
+            Zone::Tz(tz) => write!(f, "{}", tz),
+        }
+    }
+}
+
+impl Zone {
+    /// a time as RFC 3339 or in words relative to now, e.g. "tomorrow 3pm", "next friday 9:30",
+    /// "2024-03-01 15:00" or "2 hours". Times without an offset are in the zone
+    pub fn parse(&self, s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s.trim()) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+
+        let parsed = match self {
+            Zone::Local => parse_in(s, now.with_timezone(&Local)),
+            Zone::Tz(tz) => parse_in(s, now.with_timezone(tz)),
+        };
+
+        parsed.ok_or_else(|| format!("invalid time {:?}", s))
+    }
+
+    /// the time in the zone, to the minute
+    pub fn format(&self, ts: &Timestamp) -> String {
+        let dt = abi::convert_to_utc_time(ts);
+        let format = "%Y-%m-%d %H:%M %Z";
+
+        match self {
+            Zone::Local => dt.with_timezone(&Local).format(format).to_string(),
+            Zone::Tz(tz) => dt.with_timezone(tz).format(format).to_string(),
+        }
+    }
+
+    /// the IANA name of the zone, the local one is looked up in the system settings
+    pub fn name(&self) -> Result<String, String> {
+        match self {
+            Zone::Local => iana_time_zone::get_timezone()
+                .map_err(|e| format!("unknown local time zone, pass --tz: {}", e)),
+            Zone::Tz(tz) => Ok(tz.name().to_string()),
+        }
+    }
+}
+
+fn parse_in<Z: TimeZone>(s: &str, now: DateTime<Z>) -> Option<DateTime<Utc>> {
+    parse_date_string(s, now, Dialect::Us)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        // a wednesday
+        "2024-03-06T18:30:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn times_should_parse_in_the_zone() {
+        let zone: Zone = "America/New_York".parse().unwrap();
+
+        assert_eq!(
+            zone.parse("tomorrow 3pm", now()).unwrap(),
+            "2024-03-07T20:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        // DST starts on the 10th in New York
+        assert_eq!(
+            zone.parse("2024-03-11 15:00", now()).unwrap(),
+            "2024-03-11T19:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            zone.parse("friday 9:30", now()).unwrap(),
+            "2024-03-08T14:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        // an offset wins over the zone
+        assert_eq!(
+            zone.parse("2024-03-11T15:00:00+01:00", now()).unwrap(),
+            "2024-03-11T14:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(zone.parse("someday", now()).is_err());
+    }
+
+    #[test]
+    fn zones_should_parse_and_format() {
+        assert_eq!("local".parse::<Zone>().unwrap(), Zone::Local);
+        assert!("Mars/Olympus_Mons".parse::<Zone>().is_err());
+
+        let zone: Zone = "Asia/Tokyo".parse().unwrap();
+        let ts = abi::convert_to_timestamp(now());
+        assert_eq!(zone.format(&ts), "2024-03-07 03:30 JST");
+        assert_eq!(zone.name().unwrap(), "Asia/Tokyo");
+    }
+}
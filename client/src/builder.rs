@@ -0,0 +1,91 @@
+use abi::{
+    convert_to_timestamp, FilterPager, ReservationFilterBuilder, ReservationOrderBy,
+    ReservationQueryBuilder,
+};
+use chrono::{DateTime, TimeZone, Utc};
+
+/// shortcuts for the query builder generated from the proto
+pub trait ReservationQueryBuilderExt {
+    /// the reservations overlapping the window, in any time zone
+    fn window<Tz: TimeZone>(&mut self, start: DateTime<Tz>, end: DateTime<Tz>) -> &mut Self;
+
+    /// sort key and direction
+    fn order(&mut self, order_by: ReservationOrderBy, desc: bool) -> &mut Self;
+}
+
+impl ReservationQueryBuilderExt for ReservationQueryBuilder {
+    fn window<Tz: TimeZone>(&mut self, start: DateTime<Tz>, end: DateTime<Tz>) -> &mut Self {
+        self.start(convert_to_timestamp(start.with_timezone(&Utc)))
+            .end(convert_to_timestamp(end.with_timezone(&Utc)))
+    }
+
+    fn order(&mut self, order_by: ReservationOrderBy, desc: bool) -> &mut Self {
+        self.order_by(order_by as i32).is_desc(desc)
+    }
+}
+
+/// shortcuts for the filter builder generated from the proto
+pub trait ReservationFilterBuilderExt {
+    /// sort key and direction
+    fn order(&mut self, order_by: ReservationOrderBy, desc: bool) -> &mut Self;
+
+    /// the page after the one of the pager
+    fn after(&mut self, pager: &FilterPager) -> &mut Self;
+}
+
+impl ReservationFilterBuilderExt for ReservationFilterBuilder {
+    fn order(&mut self, order_by: ReservationOrderBy, desc: bool) -> &mut Self {
+        self.order_by(order_by as i32).is_desc(desc)
+    }
+
+    fn after(&mut self, pager: &FilterPager) -> &mut Self {
+        self.cursor_token(pager.next_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    #[test]
+    fn query_builder_should_take_any_zone() {
+        let start: DateTime<FixedOffset> = "2024-01-01T00:00:00-07:00".parse().unwrap();
+        let end: DateTime<FixedOffset> = "2024-01-02T00:00:00-07:00".parse().unwrap();
+
+        let query = ReservationQueryBuilder::default()
+            .user_id("john")
+            .window(start, end)
+            .order(ReservationOrderBy::Start, true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query.start,
+            Some(convert_to_timestamp(
+                "2024-01-01T07:00:00Z".parse().unwrap()
+            ))
+        );
+        assert_eq!(query.order_by, ReservationOrderBy::Start as i32);
+        assert!(query.is_desc);
+        // the defaults of the builder are kept
+        assert_eq!(query.page, 1);
+    }
+
+    #[test]
+    fn filter_builder_should_continue_after_pager() {
+        let pager = FilterPager {
+            next_token: "5:john".to_string(),
+            ..Default::default()
+        };
+
+        let filter = ReservationFilterBuilder::default()
+            .order(ReservationOrderBy::UserId, false)
+            .after(&pager)
+            .build()
+            .unwrap();
+
+        assert_eq!(filter.cursor_token, "5:john");
+        assert_eq!(filter.page_size, 10);
+    }
+}
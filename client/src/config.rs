@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use tonic::{
+    metadata::{Ascii, MetadataValue},
+    service::Interceptor,
+    transport::{Certificate, Channel, ClientTlsConfig, Endpoint},
+    Request, Status,
+};
+
+use crate::{Error, ReservationClient};
+
+/// the header the service reads the tenant of a request from
+const TENANT_HEADER: &str = "x-tenant-id";
+
+/// how often and how fast idempotent requests are retried when the service is unavailable.
+/// the listen stream uses it to reconnect, the attempts start over after every change received
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// the wait before the given retry (starting at 1), doubled every retry up to the max
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// adds the token and the tenant to every request
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    authorization: Option<MetadataValue<Ascii>>,
+    tenant: Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for Credentials {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(authorization) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+        }
+        if let Some(tenant) = &self.tenant {
+            request.metadata_mut().insert(TENANT_HEADER, tenant.clone());
+        }
+
+        Ok(request)
+    }
+}
+
+/// configures and connects a `ReservationClient`.
+/// requests are balanced over the endpoints if more than one is given
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    endpoints: Vec<String>,
+    token: Option<String>,
+    tenant: Option<String>,
+    ca_cert: Option<Vec<u8>>,
+    domain: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![],
+            token: None,
+            tenant: None,
+            ca_cert: None,
+            domain: None,
+            timeout: Some(Duration::from_secs(10)),
+            connect_timeout: Some(Duration::from_secs(5)),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.push(url.into());
+        self
+    }
+
+    pub fn endpoints(mut self, urls: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.endpoints.extend(urls.into_iter().map(Into::into));
+        self
+    }
+
+    /// JWT sent as bearer token
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// tenant to act in, the one of the token or the default one if not set
+    pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// CA certificate (pem) to verify the service with, connects with TLS
+    pub fn ca_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_cert = Some(pem.into());
+        self
+    }
+
+    /// domain the certificate of the service is issued for, the host of the endpoint if not set
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// deadline of a unary request (or of reading a whole query), none to wait forever
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// connect to the endpoint, or lazily to the endpoints if balancing over several
+    pub async fn connect(self) -> Result<ReservationClient, Error> {
+        let endpoints = self.build_endpoints()?;
+        let channel = match endpoints.as_slice() {
+            [endpoint] => endpoint.connect().await?,
+            _ => Channel::balance_list(endpoints.into_iter()),
+        };
+
+        self.build(channel)
+    }
+
+    /// connect on the first request
+    pub fn connect_lazy(self) -> Result<ReservationClient, Error> {
+        let endpoints = self.build_endpoints()?;
+        let channel = match endpoints.as_slice() {
+            [endpoint] => endpoint.connect_lazy(),
+            _ => Channel::balance_list(endpoints.into_iter()),
+        };
+
+        self.build(channel)
+    }
+
+    fn build_endpoints(&self) -> Result<Vec<Endpoint>, Error> {
+        if self.endpoints.is_empty() {
+            return Err(Error::InvalidConfig("no endpoint".to_string()));
+        }
+
+        self.endpoints
+            .iter()
+            .map(|url| {
+                let mut endpoint = Endpoint::from_shared(url.clone())
+                    .map_err(|_| Error::InvalidConfig(format!("invalid endpoint {}", url)))?;
+                if let Some(timeout) = self.connect_timeout {
+                    endpoint = endpoint.connect_timeout(timeout);
+                }
+                if let Some(pem) = &self.ca_cert {
+                    let mut tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(pem));
+                    if let Some(domain) = &self.domain {
+                        tls = tls.domain_name(domain);
+                    }
+                    endpoint = endpoint.tls_config(tls)?;
+                }
+                Ok(endpoint)
+            })
+            .collect()
+    }
+
+    fn build(self, channel: Channel) -> Result<ReservationClient, Error> {
+        let header = |value: String| {
+            value
+                .parse::<MetadataValue<Ascii>>()
+                .map_err(|_| Error::InvalidConfig(format!("invalid header value {}", value)))
+        };
+        let credentials = Credentials {
+            authorization: self
+                .token
+                .map(|token| header(format!("Bearer {}", token)))
+                .transpose()?,
+            tenant: self.tenant.map(header).transpose()?,
+        };
+
+        Ok(ReservationClient::new(
+            channel,
+            credentials,
+            self.timeout,
+            self.retry,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_should_double_up_to_max() {
+        let retry = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+
+        let backoffs: Vec<_> = (1..=5).map(|i| retry.backoff(i).as_millis()).collect();
+        assert_eq!(backoffs, [100, 200, 400, 500, 500]);
+        assert_eq!(retry.backoff(100), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn builder_should_reject_invalid_config() {
+        let ret = ClientBuilder::default().connect_lazy();
+        assert!(matches!(ret, Err(Error::InvalidConfig(_))));
+
+        let ret = ClientBuilder::default()
+            .endpoint("http://127.0.0.1:50051")
+            .token("bad\ntoken")
+            .connect_lazy();
+        assert!(matches!(ret, Err(Error::InvalidConfig(_))));
+
+        let ret = ClientBuilder::default()
+            .endpoints(["http://127.0.0.1:50051", "http://127.0.0.1:50052"])
+            .tenant("acme")
+            .connect_lazy();
+        assert!(ret.is_ok());
+    }
+}
@@ -0,0 +1,159 @@
+use abi::{ConflictDetail, StatusDetails};
+use tonic::{Code, Status};
+
+/// errors of the client, decoded from the status details sent by the service so callers
+/// can match on them instead of parsing messages
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Conflict reservation: {message}")]
+    Conflict {
+        message: String,
+        conflicts: Vec<ConflictDetail>,
+    },
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid request ({reason}): {message}")]
+    InvalidArgument {
+        reason: String,
+        message: String,
+        fields: Vec<String>,
+    },
+
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Request timed out")]
+    Timeout,
+
+    #[error("Invalid client config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("The service sent no reservation")]
+    EmptyResponse,
+
+    /// any other failed rpc, with the reason of its details if it has one
+    #[error("Rpc error ({}): {}", .0.code(), .0.message())]
+    Rpc(Box<Status>),
+}
+
+impl Error {
+    /// the machine readable reason sent by the service, e.g. `RESERVATION_CONFLICT`
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            Error::Conflict { .. } => Some("RESERVATION_CONFLICT".to_string()),
+            Error::NotFound(_) => Some("NOT_FOUND".to_string()),
+            Error::InvalidArgument { reason, .. } => Some(reason.clone()),
+            Error::Unauthenticated(_) => Some("UNAUTHENTICATED".to_string()),
+            Error::PermissionDenied(_) => Some("PERMISSION_DENIED".to_string()),
+            Error::Rpc(status) => StatusDetails::from_status(status)
+                .reason()
+                .map(str::to_string),
+            _ => None,
+        }
+    }
+
+    /// the request may succeed if it is sent again
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Unavailable(_) | Error::Timeout)
+    }
+}
+
+impl From<Status> for Error {
+    fn from(status: Status) -> Self {
+        let details = StatusDetails::from_status(&status);
+        let message = status.message().to_string();
+
+        match (details.reason(), status.code()) {
+            (Some("RESERVATION_CONFLICT"), _) => Error::Conflict {
+                message,
+                conflicts: details.conflicts,
+            },
+            (Some("NOT_FOUND"), _) | (None, Code::NotFound) => Error::NotFound(message),
+            (Some("UNAUTHENTICATED"), _) | (None, Code::Unauthenticated) => {
+                Error::Unauthenticated(message)
+            }
+            (Some("PERMISSION_DENIED"), _) | (None, Code::PermissionDenied) => {
+                Error::PermissionDenied(message)
+            }
+            (reason, Code::InvalidArgument) => Error::InvalidArgument {
+                reason: reason.unwrap_or("INVALID_ARGUMENT").to_string(),
+                message,
+                fields: details
+                    .field_violations
+                    .into_iter()
+                    .map(|violation| violation.field)
+                    .collect(),
+            },
+            (_, Code::Unavailable) => Error::Unavailable(message),
+            (_, Code::DeadlineExceeded) => Error::Timeout,
+            // the service always sends details, a bare unknown status is a broken connection
+            (None, Code::Unknown) => Error::Unavailable(message),
+            _ => Error::Rpc(Box::new(status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi::{ReservationConflict, ReservationConflictInfo, ReservationStatus, ReservationWindow};
+
+    #[test]
+    fn conflict_status_should_decode_conflicts() {
+        let window = ReservationWindow::new(
+            "room_01".to_string(),
+            "2024-01-01T07:00:00+00:00".parse().unwrap(),
+            "2024-01-03T07:00:00+00:00".parse().unwrap(),
+        );
+        let conflicts = vec![ReservationConflict {
+            id: 1,
+            user_id: Some("john".to_string()),
+            status: ReservationStatus::Confirmed,
+            window,
+        }];
+        let status: Status =
+            abi::Error::ConflictReservation(ReservationConflictInfo::new(conflicts)).into();
+
+        let err = Error::from(status);
+        let Error::Conflict { conflicts, .. } = &err else {
+            panic!("expect conflict, got {:?}", err);
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, 1);
+        assert_eq!(conflicts[0].resource_id, "room_01");
+        assert_eq!(err.reason().as_deref(), Some("RESERVATION_CONFLICT"));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn status_should_map_by_reason_then_code() {
+        let err = Error::from(Status::from(abi::Error::InvalidUserId("".to_string())));
+        let Error::InvalidArgument { reason, fields, .. } = &err else {
+            panic!("expect invalid argument, got {:?}", err);
+        };
+        assert_eq!(reason, "INVALID_USER_ID");
+        assert_eq!(fields, &["user_id"]);
+
+        let err = Error::from(Status::from(abi::Error::NotFound));
+        assert!(matches!(err, Error::NotFound(_)));
+
+        let err = Error::from(Status::from(abi::Error::ApprovalRequired(1)));
+        assert_eq!(err.reason().as_deref(), Some("APPROVAL_REQUIRED"));
+
+        assert!(Error::from(Status::unavailable("down")).is_retryable());
+        assert!(Error::from(Status::unknown("h2 protocol error")).is_retryable());
+        assert!(Error::from(Status::deadline_exceeded("slow")).is_retryable());
+        assert!(!Error::from(Status::from(abi::Error::Unknown)).is_retryable());
+    }
+}
@@ -0,0 +1,324 @@
+use std::{future::Future, time::Duration};
+
+use abi::{
+    reservation_service_client::ReservationServiceClient, CancelRequest, ConfirmRequest,
+    DryRunResult, FilterPager, FilterRequest, GetRequest, ListenRequest, ListenResponse,
+    QueryRequest, Reservation, ReservationFilter, ReservationQuery, ReserveRequest, UpdateRequest,
+};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use tonic::{
+    codec::Streaming, service::interceptor::InterceptedService, transport::Channel, Status,
+};
+
+mod builder;
+mod config;
+mod error;
+
+pub use builder::{ReservationFilterBuilderExt, ReservationQueryBuilderExt};
+pub use config::{ClientBuilder, Credentials, RetryPolicy};
+pub use error::Error;
+
+/// the response header of listen telling the sequence number the stream starts after
+const LISTEN_SEQUENCE_HEADER: &str = "x-listen-sequence";
+
+pub type ServiceClient = ReservationServiceClient<InterceptedService<Channel, Credentials>>;
+pub type ReservationStream = BoxStream<'static, Result<Reservation, Error>>;
+pub type PageStream = BoxStream<'static, Result<Page, Error>>;
+pub type ListenStream = BoxStream<'static, Result<ListenResponse, Error>>;
+
+/// a page of the filtered reservations
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    pub reservations: Vec<Reservation>,
+    pub pager: FilterPager,
+}
+
+/// typed client of the reservation service. reads, confirms and updates are retried when the
+/// service is unavailable, reserves and cancels aren't as they may have been applied
+#[derive(Debug, Clone)]
+pub struct ReservationClient {
+    inner: ServiceClient,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+impl ReservationClient {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// connect to a single endpoint with the default settings
+    pub async fn connect(url: impl Into<String>) -> Result<Self, Error> {
+        Self::builder().endpoint(url).connect().await
+    }
+
+    pub(crate) fn new(
+        channel: Channel,
+        credentials: Credentials,
+        timeout: Option<Duration>,
+        retry: RetryPolicy,
+    ) -> Self {
+        Self {
+            inner: ReservationServiceClient::with_interceptor(channel, credentials),
+            timeout,
+            retry,
+        }
+    }
+
+    /// the generated client sharing the channel and credentials, for the rpcs not wrapped here
+    pub fn service_client(&self) -> ServiceClient {
+        self.inner.clone()
+    }
+
+    pub async fn reserve(&self, rsvp: Reservation) -> Result<Reservation, Error> {
+        self.call(false, move |mut client| {
+            let request = ReserveRequest::new(rsvp.clone());
+            async move { client.reserve(request).await }
+        })
+        .await
+        .and_then(|response| {
+            response
+                .into_inner()
+                .reservation
+                .ok_or(Error::EmptyResponse)
+        })
+    }
+
+    /// check the reservation against the rules and the existing reservations without making it
+    pub async fn validate(&self, rsvp: Reservation) -> Result<DryRunResult, Error> {
+        self.call(true, move |mut client| {
+            let request = ReserveRequest {
+                reservation: Some(rsvp.clone()),
+                validate_only: true,
+            };
+            async move { client.reserve(request).await }
+        })
+        .await
+        .map(|response| response.into_inner().dry_run.unwrap_or_default())
+    }
+
+    pub async fn confirm(&self, id: i64) -> Result<Reservation, Error> {
+        self.call(true, move |mut client| async move {
+            client.confirm(ConfirmRequest::new(id)).await
+        })
+        .await
+        .and_then(|response| {
+            response
+                .into_inner()
+                .reservation
+                .ok_or(Error::EmptyResponse)
+        })
+    }
+
+    pub async fn update_note(
+        &self,
+        id: i64,
+        note: impl Into<String>,
+    ) -> Result<Reservation, Error> {
+        let note = note.into();
+        self.call(true, move |mut client| {
+            let request = UpdateRequest {
+                id,
+                note: note.clone(),
+            };
+            async move { client.update(request).await }
+        })
+        .await
+        .and_then(|response| {
+            response
+                .into_inner()
+                .reservation
+                .ok_or(Error::EmptyResponse)
+        })
+    }
+
+    pub async fn cancel(&self, id: i64) -> Result<Reservation, Error> {
+        self.call(false, move |mut client| async move {
+            client.cancel(CancelRequest::new(id)).await
+        })
+        .await
+        .and_then(|response| {
+            response
+                .into_inner()
+                .reservation
+                .ok_or(Error::EmptyResponse)
+        })
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Reservation, Error> {
+        self.call(true, move |mut client| async move {
+            client.get(GetRequest::new(id)).await
+        })
+        .await
+        .and_then(|response| {
+            response
+                .into_inner()
+                .reservation
+                .ok_or(Error::EmptyResponse)
+        })
+    }
+
+    /// the reservations of a page of the query, read within the timeout
+    pub async fn query(&self, query: ReservationQuery) -> Result<Vec<Reservation>, Error> {
+        self.call(true, move |mut client| {
+            let request = QueryRequest::new(query.clone());
+            async move {
+                let mut stream = client.query(request).await?.into_inner();
+                let mut rsvps = Vec::new();
+                while let Some(rsvp) = stream.message().await? {
+                    rsvps.push(rsvp);
+                }
+                Ok(rsvps)
+            }
+        })
+        .await
+    }
+
+    pub async fn filter(&self, filter: ReservationFilter) -> Result<Page, Error> {
+        self.call(true, move |mut client| {
+            let request = FilterRequest::new(filter.clone());
+            async move { client.filter(request).await }
+        })
+        .await
+        .map(|response| {
+            let response = response.into_inner();
+            Page {
+                reservations: response.reservations,
+                pager: response.pager.unwrap_or_default(),
+            }
+        })
+    }
+
+    /// the pages of the filter from its cursor on, fetched as they are polled
+    pub fn pages(&self, filter: ReservationFilter) -> PageStream {
+        let client = self.clone();
+
+        stream::try_unfold(Some(filter), move |filter| {
+            let client = client.clone();
+            async move {
+                let Some(filter) = filter else {
+                    return Ok(None);
+                };
+
+                let page = client.filter(filter.clone()).await?;
+                let next = match page.pager.next_token.is_empty() || page.reservations.is_empty() {
+                    true => None,
+                    false => Some(ReservationFilter {
+                        cursor_token: page.pager.next_token.clone(),
+                        ..filter
+                    }),
+                };
+
+                Ok(Some((page, next)))
+            }
+        })
+        .boxed()
+    }
+
+    /// every reservation of the filter from its cursor on, a page at a time
+    pub fn filter_all(&self, filter: ReservationFilter) -> ReservationStream {
+        self.pages(filter)
+            .map_ok(|page| stream::iter(page.reservations.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// follow the changes of the reservations. the stream reconnects when the connection breaks,
+    /// resuming after the last change received, and ends after an error it can't recover from.
+    /// it connects when first polled, without an `after_sequence` it only sends the changes from
+    /// then on: the stream resumes after the sequence the service started from, or fails instead
+    /// of skipping changes if the service didn't report it
+    pub fn listen(&self, request: ListenRequest) -> ListenStream {
+        let listener = Listener {
+            client: self.clone(),
+            request,
+            stream: None,
+            retries: 0,
+            connected: false,
+        };
+
+        stream::unfold(Some(listener), |listener| async move {
+            let mut listener = listener?;
+
+            loop {
+                let err = match &mut listener.stream {
+                    None => match listener.client.inner.listen(listener.request.clone()).await {
+                        Ok(response) => {
+                            if listener.request.after_sequence.is_none() {
+                                listener.request.after_sequence = response
+                                    .metadata()
+                                    .get(LISTEN_SEQUENCE_HEADER)
+                                    .and_then(|seq| seq.to_str().ok()?.parse().ok());
+                            }
+                            listener.connected = true;
+                            listener.stream = Some(response.into_inner());
+                            continue;
+                        }
+                        Err(status) => Error::from(status),
+                    },
+                    Some(stream) => match stream.message().await {
+                        Ok(Some(change)) => {
+                            listener.request.after_sequence = Some(change.sequence);
+                            listener.retries = 0;
+                            return Some((Ok(change), Some(listener)));
+                        }
+                        Ok(None) => Error::Unavailable("the listen stream ended".to_string()),
+                        Err(status) => Error::from(status),
+                    },
+                };
+
+                listener.stream = None;
+                let retry = listener.client.retry;
+                // reconnecting with no sequence to resume after would skip the changes in between
+                let resumable = listener.request.after_sequence.is_some() || !listener.connected;
+                if err.is_retryable() && resumable && listener.retries < retry.max_retries {
+                    listener.retries += 1;
+                    tokio::time::sleep(retry.backoff(listener.retries)).await;
+                    continue;
+                }
+
+                return Some((Err(err), None));
+            }
+        })
+        .boxed()
+    }
+
+    /// send a request built by `f`, within the timeout and retried if asked and possible
+    async fn call<T, F, Fut>(&self, retry: bool, f: F) -> Result<T, Error>
+    where
+        F: Fn(ServiceClient) -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let mut retries = 0;
+
+        loop {
+            let ret = match self.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, f(self.inner.clone())).await {
+                    Ok(ret) => ret.map_err(Error::from),
+                    Err(_) => Err(Error::Timeout),
+                },
+                None => f(self.inner.clone()).await.map_err(Error::from),
+            };
+
+            match ret {
+                Err(e) if retry && e.is_retryable() && retries < self.retry.max_retries => {
+                    retries += 1;
+                    tokio::time::sleep(self.retry.backoff(retries)).await;
+                }
+                ret => return ret,
+            }
+        }
+    }
+}
+
+struct Listener {
+    client: ReservationClient,
+    request: ListenRequest,
+    stream: Option<Streaming<ListenResponse>>,
+    retries: u32,
+    /// a stream was opened, the changes since may be skipped by connecting again from now
+    connected: bool,
+}
@@ -0,0 +1,150 @@
+#[path = "../../service/src/test_util.rs"]
+mod test_utils;
+
+use std::time::Duration;
+
+use abi::{
+    Config, ListenRequest, Reservation, ReservationFilterBuilder, ReservationQueryBuilder,
+    ReservationStatus,
+};
+use chrono::{DateTime, FixedOffset};
+use futures::{StreamExt, TryStreamExt};
+use reservation_client::{Error, ReservationClient, ReservationQueryBuilderExt, RetryPolicy};
+use reservation_service::start_server;
+use tokio::time;
+
+use test_utils::TestConfig;
+
+#[tokio::test]
+async fn client_should_work() {
+    let config = TestConfig::with_server_port(50010);
+    start_service(config.clone()).await;
+
+    let url = config.server.url(false);
+    let client = ReservationClient::builder()
+        .endpoints([url.clone(), url])
+        .connect()
+        .await
+        .unwrap();
+
+    // the stream connects when first polled
+    let mut changes = client.listen(ListenRequest::default());
+    let change = tokio::spawn(async move { changes.next().await });
+    time::sleep(Duration::from_millis(200)).await;
+
+    let rsvp = client
+        .reserve(Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        ))
+        .await
+        .unwrap();
+    assert!(rsvp.id > 0);
+
+    // conflicts are decoded, no need to parse the message
+    let conflicting = Reservation::new_pending(
+        "alice",
+        "room_01",
+        "2022-12-28T15:00:00-0700".parse().unwrap(),
+        "2022-12-31T12:00:00-0700".parse().unwrap(),
+        "",
+    );
+    let err = client.reserve(conflicting.clone()).await.unwrap_err();
+    let Error::Conflict { conflicts, .. } = err else {
+        panic!("expect conflict, got {:?}", err);
+    };
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].id, rsvp.id);
+
+    let dry_run = client.validate(conflicting).await.unwrap();
+    assert!(!dry_run.ok);
+    assert_eq!(dry_run.reason, "RESERVATION_CONFLICT");
+
+    let rsvp = client.confirm(rsvp.id).await.unwrap();
+    assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+    let rsvp = client.update_note(rsvp.id, "late arrival").await.unwrap();
+    assert_eq!(client.get(rsvp.id).await.unwrap(), rsvp);
+
+    let err = client.get(10_000).await.unwrap_err();
+    assert!(matches!(err, Error::NotFound(_)));
+
+    // the listen stream sees the changes made after it was opened
+    let change = time::timeout(Duration::from_secs(5), change)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(change.reservation.unwrap().id, rsvp.id);
+
+    for i in 0..24 {
+        client
+            .reserve(Reservation::new_pending(
+                "john",
+                format!("house_{}", i),
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+    }
+
+    let filter = ReservationFilterBuilder::default()
+        .user_id("john")
+        .status(ReservationStatus::Pending as i32)
+        .page_size(10)
+        .build()
+        .unwrap();
+    let pages: Vec<_> = client.pages(filter.clone()).try_collect().await.unwrap();
+    assert_eq!(
+        pages
+            .iter()
+            .map(|p| p.reservations.len())
+            .collect::<Vec<_>>(),
+        [10, 10, 4]
+    );
+    let rsvps: Vec<_> = client.filter_all(filter).try_collect().await.unwrap();
+    assert_eq!(rsvps.len(), 24);
+
+    let start: DateTime<FixedOffset> = "2022-12-01T00:00:00-0700".parse().unwrap();
+    let end: DateTime<FixedOffset> = "2023-01-01T00:00:00-0700".parse().unwrap();
+    let query = ReservationQueryBuilder::default()
+        .user_id("john")
+        .status(ReservationStatus::Confirmed as i32)
+        .window(start, end)
+        .build()
+        .unwrap();
+    assert_eq!(client.query(query).await.unwrap(), vec![rsvp]);
+}
+
+#[tokio::test]
+async fn client_should_give_up_after_retries() {
+    let client = ReservationClient::builder()
+        .endpoint("http://127.0.0.1:50019")
+        .retry(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+        })
+        .connect_lazy()
+        .unwrap();
+
+    let err = client.get(1).await.unwrap_err();
+    assert!(err.is_retryable(), "expect unavailable, got {:?}", err);
+
+    let mut changes = client.listen(ListenRequest::default());
+    assert!(changes.next().await.unwrap().is_err());
+    assert!(changes.next().await.is_none());
+}
+
+async fn start_service(config: Config) {
+    tokio::spawn(async move {
+        start_server(&config).await.unwrap();
+    });
+
+    time::sleep(Duration::from_millis(500)).await;
+}
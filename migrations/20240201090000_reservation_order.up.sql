@@ -0,0 +1,176 @@
+CREATE TYPE rsvp.reservation_order AS ENUM (
+  'id',
+  'start',
+  'end',
+  'created_at',
+  'updated_at',
+  'user_id',
+  'resource_id'
+);
+
+-- the sql expression of the sort key
+CREATE OR REPLACE FUNCTION rsvp.order_key(order_by rsvp.reservation_order) RETURNS TEXT
+AS $$
+    SELECT CASE order_by
+        WHEN 'start' THEN 'lower(timespan)'
+        WHEN 'end' THEN 'upper(timespan)'
+        WHEN 'created_at' THEN 'created_at'
+        -- updated_at is null until the first update, fall back to created_at to keep the key total
+        WHEN 'updated_at' THEN 'COALESCE(updated_at, created_at)'
+        WHEN 'user_id' THEN 'user_id'
+        WHEN 'resource_id' THEN 'resource_id'
+        ELSE 'id'
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- the sql type of the sort key, used to cast the cursor value
+CREATE OR REPLACE FUNCTION rsvp.order_key_type(order_by rsvp.reservation_order) RETURNS TEXT
+AS $$
+    SELECT CASE
+        WHEN order_by IN ('start', 'end', 'created_at', 'updated_at') THEN 'timestamptz'
+        WHEN order_by IN ('user_id', 'resource_id') THEN 'text'
+        ELSE 'bigint'
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+DROP FUNCTION rsvp.query;
+DROP FUNCTION rsvp.filter;
+
+CREATE OR REPLACE FUNCTION rsvp.query(
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    status rsvp.reservation_status DEFAULT 'pending',
+    is_desc boolean DEFAULT false,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _key TEXT;
+    _dir TEXT;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    -- query is ordered by start time by default
+    _key := rsvp.order_key(COALESCE(order_by, 'start'));
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %L @> timespan AND status = %L::rsvp.reservation_status AND %s
+         ORDER BY %s %s, id %s LIMIT %L::integer OFFSET %L::integer',
+         _during,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        _key,
+        _dir,
+        _dir,
+        page_size,
+        (page - 1) * page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION rsvp.filter(
+    uid varchar(64),
+    rid varchar(64),
+    status rsvp.reservation_status DEFAULT 'pending',
+    cursor bigint DEFAULT NULL,
+    is_desc boolean DEFAULT false,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL,
+    cursor_value text DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _key TEXT;
+    _op TEXT;
+    _dir TEXT;
+    _cond TEXT;
+BEGIN
+    -- filter is ordered by id by default
+    order_by := COALESCE(order_by, 'id');
+    _key := rsvp.order_key(order_by);
+    _op := CASE WHEN is_desc THEN '<' ELSE '>' END;
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- keyset condition: (key, id) must be after the cursor row in the walking direction
+    IF cursor IS NULL OR cursor <= 0 THEN
+        _cond := 'TRUE';
+    ELSIF order_by = 'id' THEN
+        _cond := format('id %s %s', _op, cursor);
+    ELSIF cursor_value IS NOT NULL THEN
+        _cond := format('(%s, id) %s (%L::%s, %s)', _key, _op, cursor_value, rsvp.order_key_type(order_by), cursor);
+    ELSE
+        -- no cursor value given, read the sort key from the cursor row itself
+        _cond := format('(%s, id) %s (SELECT %s, id FROM rsvp.reservations WHERE id = %s)', _key, _op, _key, cursor);
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %s AND status = %L::rsvp.reservation_status AND %s
+        ORDER BY %s %s, id %s LIMIT %L::integer',
+        _cond,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        _key,
+        _dir,
+        _dir,
+        page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,3 @@
+DROP FUNCTION rsvp.search;
+DROP INDEX rsvp.reservation_search_idx;
+DROP FUNCTION rsvp.search_document;
@@ -0,0 +1,238 @@
+DROP FUNCTION rsvp.query;
+DROP FUNCTION rsvp.filter;
+DROP FUNCTION rsvp.search;
+DROP INDEX rsvp.reservation_search_idx;
+DROP FUNCTION rsvp.search_document;
+DROP FUNCTION rsvp.label_condition;
+DROP INDEX rsvp.reservation_attributes_idx;
+DROP INDEX rsvp.reservation_labels_idx;
+
+ALTER TABLE rsvp.reservations
+  DROP COLUMN labels,
+  DROP COLUMN attributes;
+
+-- restore the functions of 20240201090000_reservation_order and 20240205090000_reservation_search
+CREATE OR REPLACE FUNCTION rsvp.query(
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    status rsvp.reservation_status DEFAULT 'pending',
+    is_desc boolean DEFAULT false,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _key TEXT;
+    _dir TEXT;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    -- query is ordered by start time by default
+    _key := rsvp.order_key(COALESCE(order_by, 'start'));
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %L @> timespan AND status = %L::rsvp.reservation_status AND %s
+         ORDER BY %s %s, id %s LIMIT %L::integer OFFSET %L::integer',
+         _during,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        _key,
+        _dir,
+        _dir,
+        page_size,
+        (page - 1) * page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION rsvp.filter(
+    uid varchar(64),
+    rid varchar(64),
+    status rsvp.reservation_status DEFAULT 'pending',
+    cursor bigint DEFAULT NULL,
+    is_desc boolean DEFAULT false,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL,
+    cursor_value text DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _key TEXT;
+    _op TEXT;
+    _dir TEXT;
+    _cond TEXT;
+BEGIN
+    -- filter is ordered by id by default
+    order_by := COALESCE(order_by, 'id');
+    _key := rsvp.order_key(order_by);
+    _op := CASE WHEN is_desc THEN '<' ELSE '>' END;
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- keyset condition: (key, id) must be after the cursor row in the walking direction
+    IF cursor IS NULL OR cursor <= 0 THEN
+        _cond := 'TRUE';
+    ELSIF order_by = 'id' THEN
+        _cond := format('id %s %s', _op, cursor);
+    ELSIF cursor_value IS NOT NULL THEN
+        _cond := format('(%s, id) %s (%L::%s, %s)', _key, _op, cursor_value, rsvp.order_key_type(order_by), cursor);
+    ELSE
+        -- no cursor value given, read the sort key from the cursor row itself
+        _cond := format('(%s, id) %s (SELECT %s, id FROM rsvp.reservations WHERE id = %s)', _key, _op, _key, cursor);
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %s AND status = %L::rsvp.reservation_status AND %s
+        ORDER BY %s %s, id %s LIMIT %L::integer',
+        _cond,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        _key,
+        _dir,
+        _dir,
+        page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
+
+-- the full text document of a reservation, extend it when new searchable fields are added
+CREATE OR REPLACE FUNCTION rsvp.search_document(note text) RETURNS tsvector
+AS $$
+    SELECT to_tsvector('english', COALESCE(note, ''));
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE INDEX reservation_search_idx ON rsvp.reservations USING gin (rsvp.search_document(note));
+
+CREATE OR REPLACE FUNCTION rsvp.search(
+    q text,
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    _status rsvp.reservation_status DEFAULT NULL,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10
+) RETURNS TABLE (
+    id bigint,
+    resource_id varchar(64),
+    user_id varchar(64),
+    status rsvp.reservation_status,
+    timespan tstzrange,
+    note text,
+    rank real,
+    snippet text
+)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _query TSQUERY;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    _query := websearch_to_tsquery('english', q);
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT id, resource_id, user_id, status, timespan, note,
+            ts_rank_cd(rsvp.search_document(note), %1$L::tsquery) AS rank,
+            ts_headline(''english'', COALESCE(note, ''''), %1$L::tsquery, ''MaxFragments=2, MinWords=5, MaxWords=20'') AS snippet
+         FROM rsvp.reservations
+         WHERE rsvp.search_document(note) @@ %1$L::tsquery AND %2$L @> timespan AND %3$s AND %4$s
+         ORDER BY rank DESC, id ASC LIMIT %5$L::integer OFFSET %6$L::integer',
+        _query,
+        _during,
+        CASE
+            WHEN _status IS NULL THEN 'TRUE'
+            ELSE 'status = ' || quote_literal(_status) || '::rsvp.reservation_status'
+        END,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        page_size,
+        (page - 1) * page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
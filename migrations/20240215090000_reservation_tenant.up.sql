@@ -0,0 +1,327 @@
+-- reservations belong to a tenant, resources of different tenants never conflict
+ALTER TABLE rsvp.reservations
+  ADD COLUMN tenant_id varchar(64) NOT NULL DEFAULT 'default';
+
+-- new reservations go to the tenant of the transaction, see below
+ALTER TABLE rsvp.reservations
+  ALTER COLUMN tenant_id SET DEFAULT current_setting('rsvp.tenant_id', true);
+
+ALTER TABLE rsvp.reservations DROP CONSTRAINT reservations_conflict;
+ALTER TABLE rsvp.reservations ADD CONSTRAINT reservations_conflict
+  EXCLUDE USING gist (tenant_id WITH =, resource_id WITH =, timespan WITH &&);
+
+DROP INDEX rsvp.reservation_resource_id_idx;
+DROP INDEX rsvp.reservation_user_id_idx;
+CREATE INDEX reservation_tenant_resource_id_idx ON rsvp.reservations (tenant_id, resource_id);
+CREATE INDEX reservation_tenant_user_id_idx ON rsvp.reservations (tenant_id, user_id);
+
+-- the change feed is per tenant as well
+ALTER TABLE rsvp.reservation_changes
+  ADD COLUMN tenant_id varchar(64) NOT NULL DEFAULT 'default';
+ALTER TABLE rsvp.reservation_changes ALTER COLUMN tenant_id DROP DEFAULT;
+
+CREATE INDEX reservation_changes_tenant_id_idx ON rsvp.reservation_changes (tenant_id, id);
+
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF (TG_OP = 'INSERT') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, NULL, row_to_json(NEW), 'create');
+  ELSIF (TG_OP = 'UPDATE') THEN
+    IF (OLD.status <> NEW.status) THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, row_to_json(OLD), row_to_json(NEW), 'update');
+    END IF;
+  ELSIF (TG_OP = 'DELETE') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (OLD.id, OLD.tenant_id, row_to_json(OLD), NULL, 'delete');
+  END IF;
+  -- notify a channel called reservation_update
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- the role every request runs as (SET LOCAL ROLE rsvp_tenant), together with
+-- SET LOCAL rsvp.tenant_id it confines the request to a single tenant by row level security.
+-- roles are shared by all databases of the cluster, it may exist already
+DO $$
+BEGIN
+  CREATE ROLE rsvp_tenant NOLOGIN;
+EXCEPTION
+  WHEN duplicate_object OR unique_violation THEN NULL;
+END
+$$;
+
+DO $$
+BEGIN
+  EXECUTE format('GRANT rsvp_tenant TO %I', current_user);
+EXCEPTION
+  WHEN duplicate_object OR unique_violation THEN NULL;
+END
+$$;
+
+GRANT USAGE ON SCHEMA rsvp TO rsvp_tenant;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA rsvp TO rsvp_tenant;
+GRANT USAGE ON ALL SEQUENCES IN SCHEMA rsvp TO rsvp_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA rsvp GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO rsvp_tenant;
+ALTER DEFAULT PRIVILEGES IN SCHEMA rsvp GRANT USAGE ON SEQUENCES TO rsvp_tenant;
+
+ALTER TABLE rsvp.reservations ENABLE ROW LEVEL SECURITY;
+CREATE POLICY reservations_tenant_isolation ON rsvp.reservations
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+ALTER TABLE rsvp.reservation_changes ENABLE ROW LEVEL SECURITY;
+CREATE POLICY reservation_changes_tenant_isolation ON rsvp.reservation_changes
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+-- the sql condition of an explicit tenant, on top of row level security
+CREATE OR REPLACE FUNCTION rsvp.tenant_condition(tenant varchar(64)) RETURNS TEXT
+AS $$
+BEGIN
+    IF tenant IS NULL THEN
+        RETURN 'TRUE';
+    END IF;
+
+    RETURN format('tenant_id = %L', tenant);
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- the functions return the new column
+DROP FUNCTION rsvp.query;
+DROP FUNCTION rsvp.filter;
+DROP FUNCTION rsvp.search;
+
+CREATE OR REPLACE FUNCTION rsvp.query(
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    status rsvp.reservation_status DEFAULT 'pending',
+    is_desc boolean DEFAULT false,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL,
+    selector jsonb DEFAULT NULL,
+    tenant varchar(64) DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _key TEXT;
+    _dir TEXT;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    -- query is ordered by start time by default
+    _key := rsvp.order_key(COALESCE(order_by, 'start'));
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %L @> timespan AND status = %L::rsvp.reservation_status AND %s AND %s AND %s
+         ORDER BY %s %s, id %s LIMIT %L::integer OFFSET %L::integer',
+         _during,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        rsvp.label_condition(selector),
+        rsvp.tenant_condition(tenant),
+        _key,
+        _dir,
+        _dir,
+        page_size,
+        (page - 1) * page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION rsvp.filter(
+    uid varchar(64),
+    rid varchar(64),
+    status rsvp.reservation_status DEFAULT 'pending',
+    cursor bigint DEFAULT NULL,
+    is_desc boolean DEFAULT false,
+    page_size integer DEFAULT 10,
+    order_by rsvp.reservation_order DEFAULT NULL,
+    cursor_value text DEFAULT NULL,
+    selector jsonb DEFAULT NULL,
+    tenant varchar(64) DEFAULT NULL
+) RETURNS TABLE (LIKE rsvp.reservations)
+AS $$
+DECLARE
+    _sql TEXT;
+    _key TEXT;
+    _op TEXT;
+    _dir TEXT;
+    _cond TEXT;
+BEGIN
+    -- filter is ordered by id by default
+    order_by := COALESCE(order_by, 'id');
+    _key := rsvp.order_key(order_by);
+    _op := CASE WHEN is_desc THEN '<' ELSE '>' END;
+    _dir := CASE WHEN is_desc THEN 'DESC' ELSE 'ASC' END;
+
+    -- keyset condition: (key, id) must be after the cursor row in the walking direction
+    IF cursor IS NULL OR cursor <= 0 THEN
+        _cond := 'TRUE';
+    ELSIF order_by = 'id' THEN
+        _cond := format('id %s %s', _op, cursor);
+    ELSIF cursor_value IS NOT NULL THEN
+        _cond := format('(%s, id) %s (%L::%s, %s)', _key, _op, cursor_value, rsvp.order_key_type(order_by), cursor);
+    ELSE
+        -- no cursor value given, read the sort key from the cursor row itself
+        _cond := format('(%s, id) %s (SELECT %s, id FROM rsvp.reservations WHERE id = %s)', _key, _op, _key, cursor);
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT * FROM rsvp.reservations WHERE %s AND status = %L::rsvp.reservation_status AND %s AND %s AND %s
+        ORDER BY %s %s, id %s LIMIT %L::integer',
+        _cond,
+         status,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        rsvp.label_condition(selector),
+        rsvp.tenant_condition(tenant),
+        _key,
+        _dir,
+        _dir,
+        page_size
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION rsvp.search(
+    q text,
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    _status rsvp.reservation_status DEFAULT NULL,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10,
+    selector jsonb DEFAULT NULL,
+    tenant varchar(64) DEFAULT NULL
+) RETURNS TABLE (
+    id bigint,
+    resource_id varchar(64),
+    user_id varchar(64),
+    status rsvp.reservation_status,
+    timespan tstzrange,
+    note text,
+    created_at timestamptz,
+    updated_at timestamptz,
+    labels jsonb,
+    attributes jsonb,
+    tenant_id varchar(64),
+    rank real,
+    snippet text
+)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _query TSQUERY;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    _query := websearch_to_tsquery('english', q);
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT id, resource_id, user_id, status, timespan, note, created_at, updated_at, labels, attributes, tenant_id,
+            ts_rank_cd(rsvp.search_document(note, labels, attributes), %1$L::tsquery) AS rank,
+            ts_headline(''english'', COALESCE(note, ''''), %1$L::tsquery, ''MaxFragments=2, MinWords=5, MaxWords=20'') AS snippet
+         FROM rsvp.reservations
+         WHERE rsvp.search_document(note, labels, attributes) @@ %1$L::tsquery AND %2$L @> timespan AND %3$s AND %4$s AND %7$s AND %8$s
+         ORDER BY rank DESC, id ASC LIMIT %5$L::integer OFFSET %6$L::integer',
+        _query,
+        _during,
+        CASE
+            WHEN _status IS NULL THEN 'TRUE'
+            ELSE 'status = ' || quote_literal(_status) || '::rsvp.reservation_status'
+        END,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        page_size,
+        (page - 1) * page_size,
+        rsvp.label_condition(selector),
+        rsvp.tenant_condition(tenant)
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
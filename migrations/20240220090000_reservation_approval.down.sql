@@ -0,0 +1,3 @@
+DROP TABLE rsvp.approvals;
+DROP TABLE rsvp.resources;
+DROP TYPE rsvp.approval_decision;
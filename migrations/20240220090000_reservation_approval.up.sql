@@ -0,0 +1,49 @@
+CREATE TYPE rsvp.approval_decision AS ENUM (
+  'approved',
+  'rejected'
+);
+
+-- how the reservations of a resource are confirmed, resources without a row are confirmed by confirm
+CREATE TABLE rsvp.resources (
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  id varchar(64) NOT NULL,
+  requires_approval boolean NOT NULL DEFAULT false,
+  approvers varchar(64)[] NOT NULL DEFAULT '{}',
+  auto_confirm boolean NOT NULL DEFAULT false,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ,
+
+  CONSTRAINT resources_pkey PRIMARY KEY (tenant_id, id),
+  CONSTRAINT resources_confirmation CHECK (NOT (requires_approval AND auto_confirm))
+);
+
+CREATE INDEX resources_approvers_idx ON rsvp.resources USING gin (approvers);
+
+CREATE TRIGGER update_updated_at BEFORE UPDATE ON rsvp.resources
+FOR EACH ROW EXECUTE PROCEDURE rsvp.update_updated_at_column();
+
+-- decisions of the approvers, rejected reservations are deleted so they are only kept here
+CREATE TABLE rsvp.approvals (
+  id BIGSERIAL NOT NULL,
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  reservation_id bigint NOT NULL,
+  approver varchar(64) NOT NULL,
+  decision rsvp.approval_decision NOT NULL,
+  comment text NOT NULL DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT approvals_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX approvals_reservation_id_idx ON rsvp.approvals (tenant_id, reservation_id);
+
+-- the privileges of rsvp_tenant come from the default privileges of 20240215090000_reservation_tenant
+ALTER TABLE rsvp.resources ENABLE ROW LEVEL SECURITY;
+CREATE POLICY resources_tenant_isolation ON rsvp.resources
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+ALTER TABLE rsvp.approvals ENABLE ROW LEVEL SECURITY;
+CREATE POLICY approvals_tenant_isolation ON rsvp.approvals
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
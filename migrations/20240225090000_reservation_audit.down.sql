@@ -0,0 +1,4 @@
+DROP TRIGGER audit_trigger ON rsvp.resources;
+DROP TRIGGER audit_trigger ON rsvp.reservations;
+DROP FUNCTION rsvp.audit_trigger;
+DROP TABLE rsvp.audit_log;
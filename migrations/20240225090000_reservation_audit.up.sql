@@ -0,0 +1,66 @@
+-- who changed what and why. The manager sets rsvp.actor, rsvp.request_id, rsvp.rpc and rsvp.reason
+-- for every transaction, changes made without them are recorded with the fields empty
+CREATE TABLE rsvp.audit_log (
+  id BIGSERIAL NOT NULL,
+  tenant_id varchar(64) NOT NULL,
+  reservation_id bigint,
+  resource_id varchar(64) NOT NULL,
+  user_id varchar(64),
+  actor varchar(64),
+  request_id varchar(64),
+  rpc varchar(64),
+  reason text,
+  op rsvp.reservation_update_type NOT NULL,
+  before JSONB,
+  after JSONB,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT audit_log_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX audit_log_reservation_id_idx ON rsvp.audit_log (tenant_id, reservation_id);
+CREATE INDEX audit_log_resource_id_idx ON rsvp.audit_log (tenant_id, resource_id);
+CREATE INDEX audit_log_user_id_idx ON rsvp.audit_log (tenant_id, user_id);
+CREATE INDEX audit_log_actor_idx ON rsvp.audit_log (tenant_id, actor);
+
+ALTER TABLE rsvp.audit_log ENABLE ROW LEVEL SECURITY;
+CREATE POLICY audit_log_tenant_isolation ON rsvp.audit_log
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+-- requests may only append to the audit log
+REVOKE UPDATE, DELETE ON rsvp.audit_log FROM rsvp_tenant;
+
+CREATE OR REPLACE FUNCTION rsvp.audit_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _old JSONB := to_jsonb(OLD);
+  _new JSONB := to_jsonb(NEW);
+  _row JSONB := COALESCE(to_jsonb(NEW), to_jsonb(OLD));
+BEGIN
+  INSERT INTO rsvp.audit_log (tenant_id, reservation_id, resource_id, user_id, actor, request_id, rpc, reason, op, before, after)
+  VALUES (
+    _row->>'tenant_id',
+    CASE WHEN TG_TABLE_NAME = 'reservations' THEN (_row->>'id')::bigint END,
+    CASE WHEN TG_TABLE_NAME = 'reservations' THEN _row->>'resource_id' ELSE _row->>'id' END,
+    _row->>'user_id',
+    NULLIF(current_setting('rsvp.actor', true), ''),
+    NULLIF(current_setting('rsvp.request_id', true), ''),
+    NULLIF(current_setting('rsvp.rpc', true), ''),
+    NULLIF(current_setting('rsvp.reason', true), ''),
+    CASE TG_OP
+      WHEN 'INSERT' THEN 'create'
+      WHEN 'UPDATE' THEN 'update'
+      ELSE 'delete'
+    END::rsvp.reservation_update_type,
+    _old,
+    _new
+  );
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_trigger AFTER INSERT OR UPDATE OR DELETE ON rsvp.reservations
+FOR EACH ROW EXECUTE PROCEDURE rsvp.audit_trigger();
+
+CREATE TRIGGER audit_trigger AFTER INSERT OR UPDATE OR DELETE ON rsvp.resources
+FOR EACH ROW EXECUTE PROCEDURE rsvp.audit_trigger();
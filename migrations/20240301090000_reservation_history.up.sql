@@ -0,0 +1,45 @@
+-- when the change was made, the versions of a reservation are rebuilt from its changes
+ALTER TABLE rsvp.reservation_changes
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- the time of the earlier changes is the time the row was written, deletes keep the time of the migration
+UPDATE rsvp.reservation_changes
+  SET created_at = COALESCE((new->>'updated_at')::timestamptz, (new->>'created_at')::timestamptz)
+  WHERE op IN ('create', 'update') AND new IS NOT NULL;
+
+CREATE INDEX reservation_changes_reservation_id_idx ON rsvp.reservation_changes (tenant_id, reservation_id, created_at);
+
+-- record note changes as well, the history would miss them otherwise
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF (TG_OP = 'INSERT') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, NULL, row_to_json(NEW), 'create');
+  ELSIF (TG_OP = 'UPDATE') THEN
+    IF (OLD.status <> NEW.status OR OLD.note IS DISTINCT FROM NEW.note) THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, row_to_json(OLD), row_to_json(NEW), 'update');
+    END IF;
+  ELSIF (TG_OP = 'DELETE') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (OLD.id, OLD.tenant_id, row_to_json(OLD), NULL, 'delete');
+  END IF;
+  -- notify a channel called reservation_update
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- every version of a reservation: the row after each change, the last row for a delete.
+-- Columns added after a change was recorded take the value of the change row or their default
+CREATE OR REPLACE FUNCTION rsvp.reservation_versions(rid bigint) RETURNS TABLE (
+  version bigint,
+  op rsvp.reservation_update_type,
+  changed_at TIMESTAMPTZ,
+  r rsvp.reservations
+) AS $$
+  SELECT c.id::bigint, c.op, c.created_at, jsonb_populate_record(
+    NULL::rsvp.reservations,
+    jsonb_build_object('tenant_id', c.tenant_id, 'labels', '{}'::jsonb) || COALESCE(c.new, c.old)
+  )
+  FROM rsvp.reservation_changes c
+  WHERE c.reservation_id = rid
+  ORDER BY c.id;
+$$ LANGUAGE sql STABLE;
@@ -0,0 +1,19 @@
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF (TG_OP = 'INSERT') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, NULL, row_to_json(NEW), 'create');
+  ELSIF (TG_OP = 'UPDATE') THEN
+    IF (OLD.status <> NEW.status OR OLD.note IS DISTINCT FROM NEW.note) THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, row_to_json(OLD), row_to_json(NEW), 'update');
+    END IF;
+  ELSIF (TG_OP = 'DELETE') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (OLD.id, OLD.tenant_id, row_to_json(OLD), NULL, 'delete');
+  END IF;
+  -- notify a channel called reservation_update
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE rsvp.reservation_changes DROP COLUMN changed_fields;
+DROP FUNCTION rsvp.changed_fields;
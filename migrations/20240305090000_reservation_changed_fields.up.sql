@@ -0,0 +1,41 @@
+-- the reservation fields an update changed, the timespan is reported as start and end
+CREATE OR REPLACE FUNCTION rsvp.changed_fields(old JSONB, new JSONB) RETURNS TEXT[] AS $$
+  SELECT COALESCE(array_agg(field ORDER BY field), '{}') FROM (
+    SELECT n.key AS field FROM jsonb_each(new) n
+    WHERE n.key NOT IN ('timespan', 'created_at', 'updated_at') AND n.value IS DISTINCT FROM old->n.key
+    UNION ALL
+    SELECT 'start'
+    WHERE lower((old->>'timespan')::tstzrange) IS DISTINCT FROM lower((new->>'timespan')::tstzrange)
+    UNION ALL
+    SELECT 'end'
+    WHERE upper((old->>'timespan')::tstzrange) IS DISTINCT FROM upper((new->>'timespan')::tstzrange)
+  ) fields;
+$$ LANGUAGE sql STABLE;
+
+ALTER TABLE rsvp.reservation_changes
+  ADD COLUMN changed_fields TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE rsvp.reservation_changes
+  SET changed_fields = rsvp.changed_fields(old, new)
+  WHERE op = 'update';
+
+-- any column change is an update, not only the status
+CREATE OR REPLACE FUNCTION rsvp.reservations_trigger() RETURNS TRIGGER AS $$
+DECLARE
+  _fields TEXT[];
+BEGIN
+  IF (TG_OP = 'INSERT') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (NEW.id, NEW.tenant_id, NULL, row_to_json(NEW), 'create');
+  ELSIF (TG_OP = 'UPDATE') THEN
+    _fields := rsvp.changed_fields(to_jsonb(OLD), to_jsonb(NEW));
+    IF (cardinality(_fields) > 0) THEN
+      INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op, changed_fields) VALUES (NEW.id, NEW.tenant_id, row_to_json(OLD), row_to_json(NEW), 'update', _fields);
+    END IF;
+  ELSIF (TG_OP = 'DELETE') THEN
+    INSERT INTO rsvp.reservation_changes (reservation_id, tenant_id, old, new, op) VALUES (OLD.id, OLD.tenant_id, row_to_json(OLD), NULL, 'delete');
+  END IF;
+  -- notify a channel called reservation_update
+  NOTIFY reservation_update;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
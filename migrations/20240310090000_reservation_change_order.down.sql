@@ -0,0 +1,5 @@
+DROP FUNCTION rsvp.sequence_changes;
+DROP INDEX rsvp.reservation_changes_unsequenced_idx;
+DROP INDEX rsvp.reservation_changes_seq_idx;
+ALTER TABLE rsvp.reservation_changes DROP COLUMN seq, DROP COLUMN txid;
+DROP SEQUENCE rsvp.reservation_change_seq;
@@ -0,0 +1,39 @@
+-- ids are handed out when a change is made, not when it commits: a consumer moving its cursor
+-- past an id could skip a change committing later with a lower id. Changes are sequenced once
+-- every transaction that could still add an earlier change has finished, so the sequence follows
+-- the order the changes become visible in and a cursor never skips one
+ALTER TABLE rsvp.reservation_changes
+  ADD COLUMN txid bigint NOT NULL DEFAULT txid_current(),
+  ADD COLUMN seq bigint;
+
+CREATE SEQUENCE rsvp.reservation_change_seq;
+
+-- the changes made so far are committed, sequence them in id order
+UPDATE rsvp.reservation_changes c SET txid = 0, seq = s.seq
+FROM (
+  SELECT id, nextval('rsvp.reservation_change_seq') AS seq
+  FROM (SELECT id FROM rsvp.reservation_changes ORDER BY id) ordered
+) s
+WHERE c.id = s.id;
+
+CREATE UNIQUE INDEX reservation_changes_seq_idx ON rsvp.reservation_changes (tenant_id, seq);
+CREATE INDEX reservation_changes_unsequenced_idx ON rsvp.reservation_changes (tenant_id, txid, id) WHERE seq IS NULL;
+
+-- sequence the changes of the tenant made by transactions older than any running one,
+-- in transaction then id order. One sequencer runs at a time per tenant
+CREATE OR REPLACE FUNCTION rsvp.sequence_changes() RETURNS void AS $$
+BEGIN
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.reservation_changes'), hashtext(current_setting('rsvp.tenant_id', true)));
+
+  UPDATE rsvp.reservation_changes c SET seq = s.seq
+  FROM (
+    SELECT id, nextval('rsvp.reservation_change_seq') AS seq
+    FROM (
+      SELECT id FROM rsvp.reservation_changes
+      WHERE seq IS NULL AND txid < txid_snapshot_xmin(txid_current_snapshot())
+      ORDER BY txid, id
+    ) visible
+  ) s
+  WHERE c.id = s.id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,4 @@
+DROP FUNCTION rsvp.webhook_tenants;
+DROP TABLE rsvp.webhook_dead_letters;
+DROP TABLE rsvp.webhook_deliveries;
+DROP TABLE rsvp.webhooks;
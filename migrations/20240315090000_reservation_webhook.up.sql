@@ -0,0 +1,72 @@
+-- http endpoints the changes are POSTed to, reservation_changes is the outbox
+CREATE TABLE rsvp.webhooks (
+  id BIGSERIAL NOT NULL,
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  url text NOT NULL,
+  -- rsvp.ReservationType values, empty for all
+  event_types integer[] NOT NULL DEFAULT '{}',
+  resource_id varchar(64),
+  user_id varchar(64),
+  secret text NOT NULL,
+  -- sequence number of the last change turned into deliveries
+  cursor bigint NOT NULL DEFAULT 0,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT webhooks_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX webhooks_tenant_id_idx ON rsvp.webhooks (tenant_id);
+
+-- payloads waiting to be delivered, a delivery is retried until it succeeds or is given up on
+CREATE TABLE rsvp.webhook_deliveries (
+  id BIGSERIAL NOT NULL,
+  webhook_id bigint NOT NULL REFERENCES rsvp.webhooks (id) ON DELETE CASCADE,
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  seq bigint NOT NULL,
+  payload JSONB NOT NULL,
+  attempts integer NOT NULL DEFAULT 0,
+  last_error text,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT webhook_deliveries_pkey PRIMARY KEY (id),
+  CONSTRAINT webhook_deliveries_change UNIQUE (webhook_id, seq)
+);
+
+CREATE INDEX webhook_deliveries_due_idx ON rsvp.webhook_deliveries (tenant_id, next_attempt_at);
+
+CREATE TABLE rsvp.webhook_dead_letters (
+  id BIGSERIAL NOT NULL,
+  webhook_id bigint NOT NULL REFERENCES rsvp.webhooks (id) ON DELETE CASCADE,
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  seq bigint NOT NULL,
+  payload JSONB NOT NULL,
+  attempts integer NOT NULL,
+  last_error text,
+  failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT webhook_dead_letters_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX webhook_dead_letters_webhook_id_idx ON rsvp.webhook_dead_letters (tenant_id, webhook_id);
+
+ALTER TABLE rsvp.webhooks ENABLE ROW LEVEL SECURITY;
+CREATE POLICY webhooks_tenant_isolation ON rsvp.webhooks
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+ALTER TABLE rsvp.webhook_deliveries ENABLE ROW LEVEL SECURITY;
+CREATE POLICY webhook_deliveries_tenant_isolation ON rsvp.webhook_deliveries
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+ALTER TABLE rsvp.webhook_dead_letters ENABLE ROW LEVEL SECURITY;
+CREATE POLICY webhook_dead_letters_tenant_isolation ON rsvp.webhook_dead_letters
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+-- the delivery worker serves every tenant, it finds them here. Runs as the owner of the tables,
+-- bypassing row level security
+CREATE OR REPLACE FUNCTION rsvp.webhook_tenants() RETURNS SETOF varchar(64) AS $$
+  SELECT DISTINCT tenant_id FROM rsvp.webhooks;
+$$ LANGUAGE sql STABLE SECURITY DEFINER SET search_path = pg_catalog;
@@ -0,0 +1,4 @@
+DROP FUNCTION rsvp.reminder_tenants;
+DROP TABLE rsvp.reminder_cursors;
+DROP TABLE rsvp.reminders;
+ALTER TABLE rsvp.resources DROP COLUMN resource_type;
@@ -0,0 +1,54 @@
+-- reminders are scheduled by the type of the resource
+ALTER TABLE rsvp.resources ADD COLUMN resource_type varchar(64) NOT NULL DEFAULT '';
+
+-- reminders of the upcoming reservations, kept once sent so a reminder is never sent twice
+CREATE TABLE rsvp.reminders (
+  id BIGSERIAL NOT NULL,
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  reservation_id bigint NOT NULL,
+  user_id varchar(64) NOT NULL,
+  resource_id varchar(64) NOT NULL,
+  start_at TIMESTAMPTZ NOT NULL,
+  -- minutes before the start of the reservation
+  offset_min integer NOT NULL,
+  remind_at TIMESTAMPTZ NOT NULL,
+  attempts integer NOT NULL DEFAULT 0,
+  last_error text,
+  next_attempt_at TIMESTAMPTZ NOT NULL,
+  sent_at TIMESTAMPTZ,
+  failed_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+  CONSTRAINT reminders_pkey PRIMARY KEY (id),
+  CONSTRAINT reminders_once UNIQUE (reservation_id, offset_min, remind_at)
+);
+
+CREATE INDEX reminders_reservation_id_idx ON rsvp.reminders (tenant_id, reservation_id);
+CREATE INDEX reminders_due_idx ON rsvp.reminders (tenant_id, next_attempt_at)
+  WHERE sent_at IS NULL AND failed_at IS NULL;
+
+-- sequence number of the last change the reminders of the tenant were scheduled for
+CREATE TABLE rsvp.reminder_cursors (
+  tenant_id varchar(64) NOT NULL DEFAULT current_setting('rsvp.tenant_id', true),
+  cursor bigint NOT NULL DEFAULT 0,
+
+  CONSTRAINT reminder_cursors_pkey PRIMARY KEY (tenant_id)
+);
+
+ALTER TABLE rsvp.reminders ENABLE ROW LEVEL SECURITY;
+CREATE POLICY reminders_tenant_isolation ON rsvp.reminders
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+ALTER TABLE rsvp.reminder_cursors ENABLE ROW LEVEL SECURITY;
+CREATE POLICY reminder_cursors_tenant_isolation ON rsvp.reminder_cursors
+  USING (tenant_id = current_setting('rsvp.tenant_id', true))
+  WITH CHECK (tenant_id = current_setting('rsvp.tenant_id', true));
+
+-- the scheduler serves every tenant having reservations or reminders to cancel. Runs as the
+-- owner of the tables, bypassing row level security
+CREATE OR REPLACE FUNCTION rsvp.reminder_tenants() RETURNS SETOF varchar(64) AS $$
+  SELECT tenant_id FROM rsvp.reservations
+  UNION
+  SELECT tenant_id FROM rsvp.reminders WHERE sent_at IS NULL AND failed_at IS NULL;
+$$ LANGUAGE sql STABLE SECURITY DEFINER SET search_path = pg_catalog;
@@ -0,0 +1,97 @@
+-- the note is escaped before it's highlighted, so the snippet is safe to show as html
+CREATE OR REPLACE FUNCTION rsvp.html_escape(s text) RETURNS text
+AS $$
+    SELECT replace(replace(replace(replace(replace(s, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), '''', '&#39;');
+$$ LANGUAGE sql IMMUTABLE;
+
+-- search returns the total of the hits for paging, the result type changes
+DROP FUNCTION rsvp.search;
+
+CREATE OR REPLACE FUNCTION rsvp.search(
+    q text,
+    uid varchar(64),
+    rid varchar(64),
+    _start timestamp with time zone,
+    _end timestamp with time zone,
+    _status rsvp.reservation_status DEFAULT NULL,
+    page integer DEFAULT 1,
+    page_size integer DEFAULT 10,
+    selector jsonb DEFAULT NULL,
+    tenant varchar(64) DEFAULT NULL
+) RETURNS TABLE (
+    id bigint,
+    resource_id varchar(64),
+    user_id varchar(64),
+    status rsvp.reservation_status,
+    timespan tstzrange,
+    note text,
+    created_at timestamptz,
+    updated_at timestamptz,
+    labels jsonb,
+    attributes jsonb,
+    tenant_id varchar(64),
+    rank real,
+    snippet text,
+    total bigint
+)
+AS $$
+DECLARE
+    _sql TEXT;
+    _during TSTZRANGE;
+    _query TSQUERY;
+BEGIN
+    -- if page is less than 1, set it to 1
+    IF page < 1 THEN
+        page := 1;
+    END IF;
+
+    -- if page_size is less than 1 or more than 10000, set it to 1
+    IF page_size < 1 OR page_size > 10000 THEN
+        page_size := 1;
+    END IF;
+
+    -- if start is null, set it to -infinity, and if end is null, set it to infinity
+    _during := TSTZRANGE(
+        COALESCE(_start, '-infinity'::timestamp with time zone),
+        COALESCE(_end, 'infinity'::timestamp with time zone)
+    );
+
+    _query := websearch_to_tsquery('english', q);
+
+    -- format the sql query based on the parameters
+    _sql := format(
+        'SELECT id, resource_id, user_id, status, timespan, note, created_at, updated_at, labels, attributes, tenant_id,
+            ts_rank_cd(rsvp.search_document(note, labels, attributes), %1$L::tsquery) AS rank,
+            ts_headline(''english'', rsvp.html_escape(COALESCE(note, '''')), %1$L::tsquery, ''MaxFragments=2, MinWords=5, MaxWords=20'') AS snippet,
+            count(*) OVER () AS total
+         FROM rsvp.reservations
+         WHERE rsvp.search_document(note, labels, attributes) @@ %1$L::tsquery AND %2$L @> timespan AND %3$s AND %4$s AND %7$s AND %8$s
+         ORDER BY rank DESC, id ASC LIMIT %5$L::integer OFFSET %6$L::integer',
+        _query,
+        _during,
+        CASE
+            WHEN _status IS NULL THEN 'TRUE'
+            ELSE 'status = ' || quote_literal(_status) || '::rsvp.reservation_status'
+        END,
+        CASE
+            WHEN rid IS NOT NULL AND uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid) || ' AND resource_id =' || quote_literal(rid)
+            WHEN uid IS NOT NULL THEN
+                'user_id = ' || quote_literal(uid)
+            WHEN rid IS NOT NULL THEN
+                'resource_id = ' || quote_literal(rid)
+            ELSE
+                'TRUE'
+        END,
+        page_size,
+        (page - 1) * page_size,
+        rsvp.label_condition(selector),
+        rsvp.tenant_condition(tenant)
+    );
+
+    -- log the query
+    RAISE NOTICE 'Executing query: %', _sql;
+    -- execute the query
+    RETURN QUERY EXECUTE _sql;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,5 @@
+CREATE OR REPLACE FUNCTION rsvp.reminder_tenants() RETURNS SETOF varchar(64) AS $$
+  SELECT tenant_id FROM rsvp.reservations
+  UNION
+  SELECT tenant_id FROM rsvp.reminders WHERE sent_at IS NULL AND failed_at IS NULL;
+$$ LANGUAGE sql STABLE SECURITY DEFINER SET search_path = pg_catalog;
@@ -0,0 +1,29 @@
+-- the scheduler serves every tenant having changes past its reminder cursor, or reminders still
+-- to send. The tenants are found by skipping through the change index, then one index probe per
+-- tenant tells whether it has a change to schedule. Runs as the owner of the tables, bypassing
+-- row level security
+CREATE OR REPLACE FUNCTION rsvp.reminder_tenants() RETURNS SETOF varchar(64) AS $$
+  WITH RECURSIVE tenants AS (
+    (SELECT tenant_id FROM rsvp.reservation_changes ORDER BY tenant_id LIMIT 1)
+    UNION ALL
+    SELECT (
+      SELECT c.tenant_id FROM rsvp.reservation_changes c
+      WHERE c.tenant_id > t.tenant_id ORDER BY c.tenant_id LIMIT 1
+    )
+    FROM tenants t WHERE t.tenant_id IS NOT NULL
+  )
+  SELECT t.tenant_id FROM tenants t
+  LEFT JOIN rsvp.reminder_cursors r ON r.tenant_id = t.tenant_id
+  WHERE t.tenant_id IS NOT NULL AND (
+    EXISTS (
+      SELECT 1 FROM rsvp.reservation_changes c
+      WHERE c.tenant_id = t.tenant_id AND c.seq > COALESCE(r.cursor, 0)
+    )
+    OR EXISTS (
+      SELECT 1 FROM rsvp.reservation_changes c
+      WHERE c.tenant_id = t.tenant_id AND c.seq IS NULL
+    )
+  )
+  UNION
+  SELECT tenant_id FROM rsvp.reminders WHERE sent_at IS NULL AND failed_at IS NULL;
+$$ LANGUAGE sql STABLE SECURITY DEFINER SET search_path = pg_catalog;
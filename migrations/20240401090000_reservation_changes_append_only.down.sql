@@ -0,0 +1,18 @@
+CREATE OR REPLACE FUNCTION rsvp.sequence_changes() RETURNS void AS $$
+BEGIN
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.reservation_changes'), hashtext(current_setting('rsvp.tenant_id', true)));
+
+  UPDATE rsvp.reservation_changes c SET seq = s.seq
+  FROM (
+    SELECT id, nextval('rsvp.reservation_change_seq') AS seq
+    FROM (
+      SELECT id FROM rsvp.reservation_changes
+      WHERE seq IS NULL AND txid < txid_snapshot_xmin(txid_current_snapshot())
+      ORDER BY txid, id
+    ) visible
+  ) s
+  WHERE c.id = s.id;
+END;
+$$ LANGUAGE plpgsql;
+
+GRANT UPDATE, DELETE ON rsvp.reservation_changes TO rsvp_tenant;
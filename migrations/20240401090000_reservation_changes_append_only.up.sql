@@ -0,0 +1,23 @@
+-- history, point in time reads, the change feed and the webhook outbox read the changes:
+-- requests may only append to them
+REVOKE UPDATE, DELETE ON rsvp.reservation_changes FROM rsvp_tenant;
+
+-- sequencing still updates the changes, so it runs as the owner of the tables. Row level
+-- security doesn't apply to the owner, the changes of the tenant are picked explicitly
+CREATE OR REPLACE FUNCTION rsvp.sequence_changes() RETURNS void AS $$
+BEGIN
+  PERFORM pg_advisory_xact_lock(hashtext('rsvp.reservation_changes'), hashtext(current_setting('rsvp.tenant_id', true)));
+
+  UPDATE rsvp.reservation_changes c SET seq = s.seq
+  FROM (
+    SELECT id, nextval('rsvp.reservation_change_seq') AS seq
+    FROM (
+      SELECT id FROM rsvp.reservation_changes
+      WHERE tenant_id = current_setting('rsvp.tenant_id', true)
+        AND seq IS NULL AND txid < txid_snapshot_xmin(txid_current_snapshot())
+      ORDER BY txid, id
+    ) visible
+  ) s
+  WHERE c.id = s.id;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = pg_catalog;
@@ -0,0 +1,59 @@
+use abi::Error;
+use sqlx::migrate::Migrator;
+
+use crate::ReservationManager;
+
+/// the migrations this build expects the database to be at
+static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// postgres error code of a missing table, the migrations table is missing before the first run
+const UNDEFINED_TABLE: &str = "42P01";
+
+impl ReservationManager {
+    /// versions of the migrations this build has but the database hasn't applied yet. Fails if
+    /// the pool can't get a connection
+    pub async fn pending_migrations(&self) -> Result<Vec<i64>, Error> {
+        let applied: Vec<i64> =
+            match sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await
+            {
+                Ok(applied) => applied,
+                Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some(UNDEFINED_TABLE) => {
+                    vec![]
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn pending_migrations_should_be_empty_when_migrated() {
+        let manager = ReservationManager::new(migrated_pool);
+        assert!(manager.pending_migrations().await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "pool", skip_migrations))]
+    async fn pending_migrations_should_list_all_when_not_migrated() {
+        let manager = ReservationManager::new(pool);
+        let pending = manager.pending_migrations().await.unwrap();
+        assert_eq!(
+            pending.len(),
+            MIGRATOR
+                .iter()
+                .filter(|migration| !migration.migration_type.is_down_migration())
+                .count()
+        );
+    }
+}
@@ -1,18 +1,60 @@
+mod health;
 mod manager;
+mod outbox;
+mod reminder;
 
 use abi::{DbConfig, Error, ReservationId};
 use async_trait::async_trait;
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use tokio::sync::mpsc;
 
-#[derive(Debug)]
+pub use outbox::Delivery;
+pub use reminder::Reminder;
+
+/// tenant of the reservations when none is given
+pub const DEFAULT_TENANT: &str = "default";
+
+/// who makes the changes and why, recorded in the audit log with every change of the manager
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+    /// the rpc (or any other entry point) the change is made through
+    pub rpc: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone)]
 pub struct ReservationManager {
     pub pool: PgPool,
+    /// every statement of the manager is confined to this tenant by row level security
+    pub tenant: String,
+    pub audit: AuditContext,
 }
 
 impl ReservationManager {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            tenant: DEFAULT_TENANT.to_string(),
+            audit: AuditContext::default(),
+        }
+    }
+
+    /// a manager working on the reservations of the given tenant, sharing the same pool
+    pub fn with_tenant(&self, tenant: impl Into<String>) -> Self {
+        Self {
+            tenant: tenant.into(),
+            ..self.clone()
+        }
+    }
+
+    /// a manager recording the given context with its changes, sharing the same pool
+    pub fn with_audit(&self, audit: AuditContext) -> Self {
+        Self {
+            audit,
+            ..self.clone()
+        }
     }
 
     pub async fn from_config(config: &DbConfig) -> Result<Self, Error> {
@@ -32,6 +74,23 @@ pub trait Rsvp {
     /// create a reservation
     async fn reserve(&self, reserve: abi::Reservation) -> Result<abi::Reservation, Error>;
 
+    /// validate the reservation and check conflicts like `reserve` does, without creating it
+    async fn validate_reserve(&self, reserve: abi::Reservation) -> Result<abi::Reservation, Error>;
+
+    /// create the reservations, each one succeeding or failing on its own. A dry run checks them
+    /// like validate_reserve without creating any
+    async fn reserve_all(
+        &self,
+        reserves: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<Vec<Result<abi::Reservation, Error>>, Error>;
+
+    /// create all of the reservations or none of them, the results tell which ones failed
+    async fn reserve_all_or_none(
+        &self,
+        reserves: Vec<abi::Reservation>,
+    ) -> Result<Vec<Result<abi::Reservation, Error>>, Error>;
+
     /// change reservation status (if current status is pending, change it to confirmed)
     async fn change_status(&self, reservation_id: ReservationId)
         -> Result<abi::Reservation, Error>;
@@ -49,15 +108,98 @@ pub trait Rsvp {
     /// get reservation by id
     async fn get(&self, reservation_id: ReservationId) -> Result<abi::Reservation, Error>;
 
-    /// get all reservations
+    /// get the reservation as it was at the given time, NotFound if it didn't exist then
+    async fn get_as_of(
+        &self,
+        reservation_id: ReservationId,
+        as_of: prost_types::Timestamp,
+    ) -> Result<abi::Reservation, Error>;
+
+    /// every version of the reservation, oldest first, a deleted reservation ends with a
+    /// delete version holding its last state
+    async fn get_history(
+        &self,
+        reservation_id: ReservationId,
+    ) -> Result<Vec<abi::ReservationVersion>, Error>;
+
+    /// stream reservations matching the query, rows are sent as soon as they are fetched
     async fn query(
         &self,
         query: abi::ReservationQuery,
     ) -> mpsc::Receiver<Result<abi::Reservation, abi::Error>>;
 
-    /// get reservations order by id
+    /// stream every reservation matching the query, of any status unless it gives one. The
+    /// window may be open, pages are ignored
+    async fn export(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> mpsc::Receiver<Result<abi::Reservation, abi::Error>>;
+
+    /// stream the changes committed after the call, the updates are filtered by the fields
+    /// they change. The stream ends once the receiver is dropped
+    async fn listen(
+        &self,
+        request: abi::ListenRequest,
+    ) -> mpsc::Receiver<Result<abi::ListenResponse, abi::Error>>;
+
+    /// get reservations page by page, ordered by the given sort key (id by default)
     async fn filter(
         &self,
         query: abi::ReservationFilter,
     ) -> Result<(abi::FilterPager, Vec<abi::Reservation>), Error>;
+
+    /// full text search over reservation notes, ranked by relevance
+    async fn search(
+        &self,
+        search: abi::ReservationSearch,
+    ) -> Result<(abi::SearchPager, Vec<abi::SearchHit>), Error>;
+
+    /// create or replace how the reservations of a resource are confirmed
+    async fn set_resource(&self, resource: abi::Resource) -> Result<abi::Resource, Error>;
+
+    /// get a resource, a resource never set has no restrictions
+    async fn get_resource(&self, id: &str) -> Result<abi::Resource, Error>;
+
+    /// confirm a pending reservation of a resource requiring approval
+    async fn approve(
+        &self,
+        reservation_id: ReservationId,
+        approver: &str,
+        comment: String,
+    ) -> Result<(abi::Reservation, abi::Approval), Error>;
+
+    /// delete a pending reservation of a resource requiring approval, the decision is kept
+    async fn reject(
+        &self,
+        reservation_id: ReservationId,
+        approver: &str,
+        comment: String,
+    ) -> Result<(abi::Reservation, abi::Approval), Error>;
+
+    /// pending reservations the approver can approve, oldest first, after the cursor.
+    /// Returns the cursor of the next page as well, -1 if there is none
+    async fn approval_queue(
+        &self,
+        approver: &str,
+        cursor: ReservationId,
+        page_size: i32,
+    ) -> Result<(Vec<abi::Reservation>, ReservationId), Error>;
+
+    /// start POSTing the changes made from now on to the webhook
+    async fn create_webhook(&self, webhook: abi::Webhook) -> Result<abi::Webhook, Error>;
+
+    async fn get_webhook(&self, id: i64) -> Result<abi::Webhook, Error>;
+
+    async fn list_webhooks(&self) -> Result<Vec<abi::Webhook>, Error>;
+
+    /// delete a webhook with its pending deliveries and dead letters
+    async fn delete_webhook(&self, id: i64) -> Result<abi::Webhook, Error>;
+
+    /// the deliveries of the webhook given up on, of every webhook if the id is 0
+    async fn dead_letters(&self, webhook_id: i64) -> Result<Vec<abi::DeadLetter>, Error>;
+
+    /// the audit entries matching the query, oldest first, after the cursor.
+    /// Returns the cursor of the next page as well, -1 if there is none
+    async fn audit_log(&self, query: abi::AuditQuery)
+        -> Result<(Vec<abi::AuditEntry>, i64), Error>;
 }
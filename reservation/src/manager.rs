@@ -1,15 +1,85 @@
 use futures::StreamExt;
+use std::ops::Bound;
 use tokio::sync::mpsc;
 
-use abi::{convert_to_utc_time, ReservationStatus, Validator};
+use abi::{
+    convert_to_json, convert_to_timestamp, convert_to_utc_time, ApprovalDecision, FilterCursor,
+    LabelSelector, ReservationConflictInfo, ReservationOrderBy, ReservationStatus, Validator,
+};
 use async_trait::async_trait;
-use sqlx::{Either, Row};
+use sqlx::{
+    postgres::{types::PgRange, PgListener, PgRow},
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
+    Acquire, Either, FromRow, PgExecutor, Postgres, Row, Transaction,
+};
 
 use crate::{Error, ReservationId, ReservationManager, Rsvp};
 
-#[async_trait]
-impl Rsvp for ReservationManager {
-    async fn reserve(&self, rsvp: abi::Reservation) -> Result<abi::Reservation, Error> {
+/// channel the reservation trigger notifies on every change
+const CHANGE_CHANNEL: &str = "reservation_update";
+/// changes read from the feed at once
+const CHANGE_BATCH: i64 = 1000;
+/// how long the feed is idle at most before it is read again
+const CHANGE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// reservations reserve_all commits at once
+const RESERVE_BATCH: usize = 100;
+/// reservations export reads at once
+const EXPORT_PAGE: i32 = 1000;
+
+impl ReservationManager {
+    /// start a transaction that only sees the reservations of the tenant of the manager
+    pub(crate) async fn begin(&self) -> Result<Transaction<'static, Postgres>, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let audit = &self.audit;
+        sqlx::query(
+            "SELECT set_config('rsvp.tenant_id', $1, true), set_config('rsvp.actor', $2, true),
+                set_config('rsvp.request_id', $3, true), set_config('rsvp.rpc', $4, true),
+                set_config('rsvp.reason', $5, true)",
+        )
+        .bind(&self.tenant)
+        .bind(audit.actor.as_deref().unwrap_or_default())
+        .bind(audit.request_id.as_deref().unwrap_or_default())
+        .bind(audit.rpc.as_deref().unwrap_or_default())
+        .bind(audit.reason.as_deref().unwrap_or_default())
+        .execute(&mut tx)
+        .await?;
+        // row level security doesn't apply to the owner of the tables
+        sqlx::query("SET LOCAL ROLE rsvp_tenant")
+            .execute(&mut tx)
+            .await?;
+
+        Ok(tx)
+    }
+
+    /// sequence number of the last change of the tenant, 0 if there is none
+    pub async fn last_change(&self) -> Result<i64, Error> {
+        let mut tx = self.begin().await?;
+        let seq = last_change(&mut tx).await?;
+        tx.commit().await?;
+
+        Ok(seq)
+    }
+
+    /// the next changes of the tenant after the given sequence number, in sequence order
+    async fn changes_after(&self, cursor: i64) -> Result<Vec<abi::ListenResponse>, Error> {
+        let mut tx = self.begin().await?;
+        let changes = changes_after(&mut tx, cursor).await?;
+        tx.commit().await?;
+
+        Ok(changes)
+    }
+
+    /// insert the reservation in the given transaction, a conflict is reported with all the
+    /// reservations it conflicts with
+    async fn insert(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        rsvp: abi::Reservation,
+    ) -> Result<abi::Reservation, Error> {
         rsvp.validate()?;
 
         let mut return_rsvp = rsvp.clone();
@@ -18,28 +88,240 @@ impl Rsvp for ReservationManager {
 
         let status = ReservationStatus::try_from(rsvp.status).unwrap_or(ReservationStatus::Pending);
 
+        // the resource may decide how its reservations are confirmed
+        let resource: Option<(bool, bool)> = sqlx::query_as(
+            "SELECT requires_approval, auto_confirm FROM rsvp.resources WHERE id = $1",
+        )
+        .bind(&rsvp.resource_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let status = match (resource, status) {
+            (Some((true, _)), ReservationStatus::Confirmed) => ReservationStatus::Pending,
+            (Some((_, true)), ReservationStatus::Pending) => ReservationStatus::Confirmed,
+            _ => status,
+        };
+        return_rsvp.status = status as i32;
+
+        // a savepoint keeps the transaction usable to look up the conflicts
+        let mut savepoint = tx.begin().await?;
+
         let id = sqlx::query(
-            "INSERT INTO rsvp.reservations (user_id, resource_id, timespan, note, status)
-            VALUES ($1, $2, $3, $4, $5::rsvp.reservation_status)
+            "INSERT INTO rsvp.reservations (user_id, resource_id, timespan, note, status, labels, attributes, tenant_id)
+            VALUES ($1, $2, $3, $4, $5::rsvp.reservation_status, $6, $7, $8)
             RETURNING id",
         )
         .bind(rsvp.user_id)
-        .bind(rsvp.resource_id)
-        .bind(timespan)
+        .bind(&rsvp.resource_id)
+        .bind(&timespan)
         .bind(rsvp.note)
         .bind(status.to_string())
-        .fetch_one(&self.pool)
-        .await?
-        .get(0);
+        .bind(Json(rsvp.labels))
+        .bind(rsvp.attributes.as_ref().map(convert_to_json))
+        .bind(&self.tenant)
+        .fetch_one(&mut savepoint)
+        .await;
+
+        let id = match id {
+            Ok(row) => {
+                savepoint.commit().await?;
+                row.get(0)
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                return match Error::from(e) {
+                    // the exclusion violation only reports one conflicting key, look up all of them
+                    Error::ConflictReservation(_) => Err(Error::ConflictReservation(
+                        self.conflicts(&mut *tx, &rsvp.resource_id, &timespan)
+                            .await?,
+                    )),
+                    e => Err(e),
+                };
+            }
+        };
 
         return_rsvp.id = id;
+        return_rsvp.tenant_id = self.tenant.clone();
 
         Ok(return_rsvp)
     }
 
+    /// lock the reservation if it is waiting for the decision of an approver
+    async fn awaiting_approval(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: ReservationId,
+    ) -> Result<(), Error> {
+        id.validate()?;
+
+        let rsvp: abi::Reservation =
+            sqlx::query_as("SELECT * FROM rsvp.reservations WHERE id = $1 FOR UPDATE")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+        let requires_approval: Option<bool> =
+            sqlx::query_scalar("SELECT requires_approval FROM rsvp.resources WHERE id = $1")
+                .bind(&rsvp.resource_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        if rsvp.status != ReservationStatus::Pending as i32 || requires_approval != Some(true) {
+            return Err(Error::NotAwaitingApproval(id));
+        }
+
+        Ok(())
+    }
+
+    /// record the decision of the approver on the reservation
+    async fn record_approval(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: ReservationId,
+        approver: &str,
+        decision: ApprovalDecision,
+        comment: String,
+    ) -> Result<abi::Approval, Error> {
+        if approver.is_empty() {
+            return Err(Error::MissingField("approver".to_string()));
+        }
+
+        let approval = sqlx::query_as(
+            "INSERT INTO rsvp.approvals (reservation_id, approver, decision, comment, tenant_id)
+            VALUES ($1, $2, $3::rsvp.approval_decision, $4, $5)
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(approver)
+        .bind(decision.to_string())
+        .bind(comment)
+        .bind(&self.tenant)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Ok(approval)
+    }
+
+    /// reservations of the resource overlapping the given timespan
+    async fn conflicts<'e, E>(
+        &self,
+        executor: E,
+        rid: &str,
+        timespan: &PgRange<DateTime<Utc>>,
+    ) -> Result<ReservationConflictInfo, Error>
+    where
+        E: PgExecutor<'e>,
+    {
+        let conflicts = sqlx::query_as(
+            "SELECT id, user_id, resource_id, status, timespan FROM rsvp.reservations
+            WHERE resource_id = $1 AND timespan && $2
+            ORDER BY lower(timespan), id",
+        )
+        .bind(rid)
+        .bind(timespan)
+        .fetch_all(executor)
+        .await?;
+
+        Ok(ReservationConflictInfo::new(conflicts))
+    }
+}
+
+#[async_trait]
+impl Rsvp for ReservationManager {
+    async fn reserve(&self, rsvp: abi::Reservation) -> Result<abi::Reservation, Error> {
+        let mut tx = self.begin().await?;
+        let rsvp = self.insert(&mut tx, rsvp).await?;
+        tx.commit().await?;
+
+        Ok(rsvp)
+    }
+
+    async fn validate_reserve(&self, rsvp: abi::Reservation) -> Result<abi::Reservation, Error> {
+        // the insert runs the same checks as a real reserve, rolling back discards the
+        // reservation, its change row and the notification
+        let mut tx = self.begin().await?;
+        let ret = self.insert(&mut tx, rsvp).await;
+        tx.rollback().await?;
+
+        let mut rsvp = ret?;
+        rsvp.id = 0;
+
+        Ok(rsvp)
+    }
+
+    async fn reserve_all(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+        dry_run: bool,
+    ) -> Result<Vec<Result<abi::Reservation, Error>>, Error> {
+        let mut results = Vec::with_capacity(rsvps.len());
+
+        // a dry run checks all of them in one transaction, so they may conflict with each other
+        if dry_run {
+            let mut tx = self.begin().await?;
+            for rsvp in rsvps {
+                let ret = self.insert(&mut tx, rsvp).await.map(|mut rsvp| {
+                    rsvp.id = 0;
+                    rsvp
+                });
+                results.push(ret);
+            }
+            tx.rollback().await?;
+
+            return Ok(results);
+        }
+
+        for batch in rsvps.chunks(RESERVE_BATCH) {
+            let mut tx = self.begin().await?;
+            for rsvp in batch {
+                results.push(self.insert(&mut tx, rsvp.clone()).await);
+            }
+            tx.commit().await?;
+        }
+
+        Ok(results)
+    }
+
+    async fn reserve_all_or_none(
+        &self,
+        rsvps: Vec<abi::Reservation>,
+    ) -> Result<Vec<Result<abi::Reservation, Error>>, Error> {
+        let mut tx = self.begin().await?;
+        let mut results = Vec::with_capacity(rsvps.len());
+        for rsvp in rsvps {
+            results.push(self.insert(&mut tx, rsvp).await);
+        }
+
+        if results.iter().all(Result::is_ok) {
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+            for rsvp in results.iter_mut().flatten() {
+                rsvp.id = 0;
+            }
+        }
+
+        Ok(results)
+    }
+
     async fn change_status(&self, id: ReservationId) -> Result<abi::Reservation, Error> {
         id.validate()?;
 
+        let mut tx = self.begin().await?;
+
+        // reservations of a resource requiring approval are confirmed by approve
+        let requires_approval: Option<bool> = sqlx::query_scalar(
+            "SELECT requires_approval FROM rsvp.resources
+            WHERE id = (SELECT resource_id FROM rsvp.reservations WHERE id = $1)",
+        )
+        .bind(id)
+        .fetch_optional(&mut tx)
+        .await?;
+
+        if requires_approval == Some(true) {
+            return Err(Error::ApprovalRequired(id));
+        }
+
         // if current status is pending, change is to confirmed, otherwise do nothing
         let rsvp: abi::Reservation = sqlx::query_as(
             "UPDATE rsvp.reservations
@@ -48,9 +330,11 @@ impl Rsvp for ReservationManager {
             RETURNING *",
         )
         .bind(id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut tx)
         .await?;
 
+        tx.commit().await?;
+
         Ok(rsvp)
     }
 
@@ -61,6 +345,8 @@ impl Rsvp for ReservationManager {
     ) -> Result<abi::Reservation, Error> {
         id.validate()?;
 
+        let mut tx = self.begin().await?;
+
         // change note for the reservation
         let rsvp: abi::Reservation = sqlx::query_as(
             "UPDATE rsvp.reservations
@@ -70,9 +356,11 @@ impl Rsvp for ReservationManager {
         )
         .bind(id)
         .bind(note)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut tx)
         .await?;
 
+        tx.commit().await?;
+
         Ok(rsvp)
     }
 
@@ -81,11 +369,15 @@ impl Rsvp for ReservationManager {
 
         id.validate()?;
 
+        let mut tx = self.begin().await?;
+
         let _ = sqlx::query("DELETE FROM rsvp.reservations WHERE id = $1")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut tx)
             .await?;
 
+        tx.commit().await?;
+
         Ok(())
     }
 
@@ -94,18 +386,73 @@ impl Rsvp for ReservationManager {
 
         id.validate()?;
 
+        let mut tx = self.begin().await?;
+
         let rsvp: abi::Reservation = sqlx::query_as(
-            "SELECT id, user_id, resource_id, timespan, note, status
+            "SELECT id, user_id, resource_id, timespan, note, status, labels, attributes, tenant_id
             FROM rsvp.reservations
             WHERE id = $1",
         )
         .bind(id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut tx)
         .await?;
 
+        tx.commit().await?;
+
         Ok(rsvp)
     }
 
+    async fn get_as_of(
+        &self,
+        id: ReservationId,
+        as_of: prost_types::Timestamp,
+    ) -> Result<abi::Reservation, Error> {
+        id.validate()?;
+
+        let mut tx = self.begin().await?;
+
+        let version: abi::ReservationVersion = sqlx::query_as(
+            "SELECT version, op, changed_at, (r).* FROM rsvp.reservation_versions($1)
+            WHERE changed_at <= $2
+            ORDER BY version DESC
+            LIMIT 1",
+        )
+        .bind(id)
+        .bind(convert_to_utc_time(&as_of))
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if version.op == abi::ReservationType::Delete as i32 {
+            return Err(Error::NotFound);
+        }
+
+        version.reservation.ok_or(Error::NotFound)
+    }
+
+    async fn get_history(&self, id: ReservationId) -> Result<Vec<abi::ReservationVersion>, Error> {
+        id.validate()?;
+
+        let mut tx = self.begin().await?;
+
+        let versions: Vec<abi::ReservationVersion> = sqlx::query_as(
+            "SELECT version, op, changed_at, (r).* FROM rsvp.reservation_versions($1)
+            ORDER BY version",
+        )
+        .bind(id)
+        .fetch_all(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if versions.is_empty() {
+            return Err(Error::NotFound);
+        }
+
+        Ok(versions)
+    }
+
     async fn query(
         &self,
         query: abi::ReservationQuery,
@@ -114,42 +461,209 @@ impl Rsvp for ReservationManager {
         let end = query.end.map(|v| convert_to_utc_time(&v));
         let status =
             ReservationStatus::try_from(query.status).unwrap_or(ReservationStatus::Pending);
+        let order_by =
+            ReservationOrderBy::try_from(query.order_by).unwrap_or(ReservationOrderBy::Default);
 
         let (tx, rx) = mpsc::channel(128);
 
-        let mut rsvps = sqlx::query_as(
-            "SELECT * FROM rsvp.query($1, $2, $3, $4, $5::rsvp.reservation_status, $6, $7, $8)",
-        )
-        .bind(str_to_option(&query.user_id))
-        .bind(str_to_option(&query.resource_id))
-        .bind(start)
-        .bind(end)
-        .bind(status.to_string())
-        .bind(query.is_desc)
-        .bind(query.page)
-        .bind(query.page_size)
-        .fetch_many(&self.pool);
+        let selector = match query.label_selector.parse::<LabelSelector>() {
+            Ok(selector) => selector,
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return rx;
+            }
+        };
 
-        while let Some(ret) = rsvps.next().await {
-            match ret {
-                Ok(Either::Left(r)) => {
-                    println!("Query result: {:?}", r);
+        let manager = self.clone();
+
+        // stream rows to the receiver as they are fetched, the bounded channel gives us
+        // backpressure, and the query is dropped (cancelled) once the receiver goes away
+        tokio::spawn(async move {
+            let mut db_tx = match manager.begin().await {
+                Ok(db_tx) => db_tx,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
                 }
-                Ok(Either::Right(r)) => {
-                    if tx.send(Ok(r)).await.is_err() {
-                        // rx is dropped, so client disconnected
-                        break;
+            };
+
+            let mut rsvps = sqlx::query_as(
+                "SELECT * FROM rsvp.query($1, $2, $3, $4, $5::rsvp.reservation_status, $6, $7, $8, $9::rsvp.reservation_order, $10, $11)",
+            )
+            .bind(str_to_option(&query.user_id))
+            .bind(str_to_option(&query.resource_id))
+            .bind(start)
+            .bind(end)
+            .bind(status.to_string())
+            .bind(query.is_desc)
+            .bind(query.page)
+            .bind(query.page_size)
+            .bind(order_to_option(order_by))
+            .bind(selector.to_json())
+            .bind(&manager.tenant)
+            .fetch_many(&mut db_tx);
+
+            loop {
+                let ret = tokio::select! {
+                    ret = rsvps.next() => ret,
+                    // rx is dropped while waiting for the database, so client disconnected
+                    _ = tx.closed() => break,
+                };
+
+                let item = match ret {
+                    Some(Ok(Either::Left(_))) => continue,
+                    Some(Ok(Either::Right(r))) => Ok(r),
+                    Some(Err(e)) => Err(e.into()),
+                    None => break,
+                };
+
+                if tx.send(item).await.is_err() {
+                    // rx is dropped, so client disconnected
+                    break;
+                }
+            }
+        });
+
+        rx
+    }
+
+    async fn export(
+        &self,
+        query: abi::ReservationQuery,
+    ) -> mpsc::Receiver<Result<abi::Reservation, abi::Error>> {
+        let (tx, rx) = mpsc::channel(128);
+
+        let selector = match query.label_selector.parse::<LabelSelector>() {
+            Ok(selector) => selector,
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return rx;
+            }
+        };
+        let statuses = match ReservationStatus::try_from(query.status) {
+            Ok(ReservationStatus::Unknown) | Err(_) => vec![
+                ReservationStatus::Unknown,
+                ReservationStatus::Pending,
+                ReservationStatus::Confirmed,
+                ReservationStatus::Blocked,
+            ],
+            Ok(status) => vec![status],
+        };
+        let start = query.start.map(|v| convert_to_utc_time(&v));
+        let end = query.end.map(|v| convert_to_utc_time(&v));
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let exported = async {
+                // pages ordered by id in one transaction neither skip nor repeat rows
+                let mut db_tx = manager.begin().await?;
+                for status in statuses {
+                    for page in 1.. {
+                        let rsvps: Vec<abi::Reservation> = sqlx::query_as(
+                            "SELECT * FROM rsvp.query($1, $2, $3, $4, $5::rsvp.reservation_status, false, $6, $7, $8::rsvp.reservation_order, $9, $10)",
+                        )
+                        .bind(str_to_option(&query.user_id))
+                        .bind(str_to_option(&query.resource_id))
+                        .bind(start)
+                        .bind(end)
+                        .bind(status.to_string())
+                        .bind(page)
+                        .bind(EXPORT_PAGE)
+                        .bind(order_to_option(ReservationOrderBy::Id))
+                        .bind(selector.to_json())
+                        .bind(&manager.tenant)
+                        .fetch_all(&mut db_tx)
+                        .await?;
+
+                        let last = rsvps.len() < EXPORT_PAGE as usize;
+                        for rsvp in rsvps {
+                            if tx.send(Ok(rsvp)).await.is_err() {
+                                // rx is dropped, so client disconnected
+                                return Ok(());
+                            }
+                        }
+                        if last {
+                            break;
+                        }
                     }
                 }
-                Err(e) => {
-                    println!("Query error: {:?}", e);
-                    if tx.send(Err(e.into())).await.is_err() {
+
+                Ok::<_, Error>(())
+            };
+
+            if let Err(e) = exported.await {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+
+        rx
+    }
+
+    async fn listen(
+        &self,
+        request: abi::ListenRequest,
+    ) -> mpsc::Receiver<Result<abi::ListenResponse, abi::Error>> {
+        let (tx, rx) = mpsc::channel(128);
+
+        if let Err(e) = request.validate() {
+            let _ = tx.send(Err(e)).await;
+            return rx;
+        }
+
+        // subscribe before the cursor is read, so a change made in between still wakes us up
+        let subscribed = async {
+            let mut listener = PgListener::connect_with(&self.pool).await?;
+            listener.listen(CHANGE_CHANNEL).await?;
+            let cursor = match request.after_sequence {
+                Some(seq) => seq,
+                None => self.last_change().await?,
+            };
+            Ok::<_, Error>((listener, cursor))
+        };
+        let (mut listener, mut cursor) = match subscribed.await {
+            Ok(subscribed) => subscribed,
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return rx;
+            }
+        };
+
+        let manager = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let changes = match manager.changes_after(cursor).await {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        break;
+                    }
+                };
+                let drained = changes.len() < CHANGE_BATCH as usize;
+
+                for change in changes {
+                    cursor = change.sequence;
+                    if request.matches(&change) && tx.send(Ok(change)).await.is_err() {
                         // rx is dropped, so client disconnected
+                        return;
+                    }
+                }
+
+                if drained {
+                    // a committed change waits for the older transactions still running to be
+                    // sequenced, look again after a while even if nothing else is committed
+                    let ret = tokio::select! {
+                        ret = listener.recv() => ret.map(|_| ()),
+                        _ = tokio::time::sleep(CHANGE_POLL_INTERVAL) => Ok(()),
+                        _ = tx.closed() => break,
+                    };
+                    if let Err(e) = ret {
+                        let _ = tx.send(Err(e.into())).await;
                         break;
                     }
                 }
             }
-        }
+        });
 
         rx
     }
@@ -158,40 +672,67 @@ impl Rsvp for ReservationManager {
         &self,
         query: abi::ReservationFilter,
     ) -> Result<(abi::FilterPager, Vec<abi::Reservation>), Error> {
+        query.validate()?;
+
         let status =
             ReservationStatus::try_from(query.status).unwrap_or(ReservationStatus::Pending);
+        let order_by = match ReservationOrderBy::try_from(query.order_by) {
+            Ok(ReservationOrderBy::Default) | Err(_) => ReservationOrderBy::Id,
+            Ok(order_by) => order_by,
+        };
+        let cursor = query.get_cursor()?;
+        let selector: LabelSelector = query.label_selector.parse()?;
 
-        let mut rsvps: Vec<abi::Reservation> = sqlx::query_as(
-            "SELECT * FROM rsvp.filter($1, $2, $3::rsvp.reservation_status, $4, $5, $6)",
+        let mut tx = self.begin().await?;
+
+        let mut rows: Vec<PgRow> = sqlx::query(
+            "SELECT * FROM rsvp.filter($1, $2, $3::rsvp.reservation_status, $4, $5, $6, $7::rsvp.reservation_order, $8, $9, $10)",
         )
         .bind(str_to_option(&query.user_id))
         .bind(str_to_option(&query.resource_id))
         .bind(status.to_string())
-        .bind(query.cursor)
+        .bind(cursor.id)
         .bind(query.is_desc)
         .bind(query.page_size)
-        .fetch_all(&self.pool)
+        .bind(order_by.to_string())
+        .bind(cursor.value)
+        .bind(selector.to_json())
+        .bind(&self.tenant)
+        .fetch_all(&mut tx)
         .await?;
 
         let total = sqlx::query("SELECT COUNT(*) FROM rsvp.reservations")
-            .fetch_one(&self.pool)
+            .fetch_one(&mut tx)
             .await?
             .get::<i64, _>(0);
 
+        tx.commit().await?;
+
         if query.is_desc {
-            rsvps.reverse();
+            rows.reverse();
         }
 
+        let rsvps = rows
+            .iter()
+            .map(abi::Reservation::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
         let mut prev = -1;
         let mut next = -1;
+        let mut prev_token = String::new();
+        let mut next_token = String::new();
 
-        if let Some(start) = rsvps.first() {
-            prev = start.id;
+        if let Some(start) = rows.first() {
+            let cursor = row_to_cursor(start, order_by);
+            prev = cursor.id;
+            prev_token = cursor.to_string();
         }
 
-        if rsvps.len() == (query.page_size as usize) {
-            if let Some(end) = rsvps.last() {
-                next = end.id;
+        if rows.len() == (query.page_size as usize) {
+            if let Some(end) = rows.last() {
+                let cursor = row_to_cursor(end, order_by);
+                next = cursor.id;
+                next_token = cursor.to_string();
             }
         }
 
@@ -200,81 +741,540 @@ impl Rsvp for ReservationManager {
             next,
             // TODO optimize total sum
             total,
+            prev_token,
+            next_token,
         };
 
         Ok((pager, rsvps))
     }
-}
 
-fn str_to_option(s: &str) -> Option<String> {
-    if s.is_empty() {
-        None
-    } else {
-        Some(s.to_string())
-    }
-}
+    async fn search(
+        &self,
+        search: abi::ReservationSearch,
+    ) -> Result<(abi::SearchPager, Vec<abi::SearchHit>), Error> {
+        search.validate()?;
+
+        let start = search.start.map(|v| convert_to_utc_time(&v));
+        let end = search.end.map(|v| convert_to_utc_time(&v));
+        let status = match ReservationStatus::try_from(search.status) {
+            Ok(ReservationStatus::Unknown) | Err(_) => None,
+            Ok(status) => Some(status.to_string()),
+        };
+        let selector: LabelSelector = search.label_selector.parse()?;
 
-#[cfg(test)]
-mod tests {
+        let mut tx = self.begin().await?;
 
-    use super::*;
-    use abi::Reservation;
-    use abi::ReservationConflictInfo;
-    use abi::ReservationWindow;
-    use prost_types::Timestamp;
+        let rows: Vec<PgRow> = sqlx::query(
+            "SELECT * FROM rsvp.search($1, $2, $3, $4, $5, $6::rsvp.reservation_status, $7, $8, $9, $10)",
+        )
+        .bind(search.query)
+        .bind(str_to_option(&search.user_id))
+        .bind(str_to_option(&search.resource_id))
+        .bind(start)
+        .bind(end)
+        .bind(status)
+        .bind(search.page)
+        .bind(search.page_size)
+        .bind(selector.to_json())
+        .bind(&self.tenant)
+        .fetch_all(&mut tx)
+        .await?;
 
-    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
-    async fn reserve_should_work_for_valid_window() {
-        let manager = ReservationManager::new(migrated_pool);
+        tx.commit().await?;
+
+        let hits = rows
+            .iter()
+            .map(|row| {
+                Ok(abi::SearchHit {
+                    reservation: Some(abi::Reservation::from_row(row)?),
+                    rank: row.get("rank"),
+                    snippet: row.get("snippet"),
+                })
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        // every hit carries the total, pages are normalized like rsvp.search does
+        let total: i64 = rows.first().map(|row| row.get("total")).unwrap_or_default();
+        let page = search.page.max(1);
+        let page_size = match search.page_size {
+            1..=10000 => search.page_size,
+            _ => 1,
+        };
+        let pager = abi::SearchPager {
+            prev: page - 1,
+            next: match (page as i64) * (page_size as i64) < total {
+                true => page + 1,
+                false => 0,
+            },
+            total,
+        };
 
-        let rsvp = Reservation::new_pending(
-            "john",
-            "ocean_view_room_1",
-            "2024-01-01T00:00:00-0700".parse().unwrap(),
-            "2024-01-03T00:00:00-0700".parse().unwrap(),
-            "I'll arrive at 3pm, Please help to upgrade to executive room if possible.",
-        );
+        Ok((pager, hits))
+    }
 
-        let rsvp = manager.reserve(rsvp).await.unwrap();
+    async fn set_resource(&self, resource: abi::Resource) -> Result<abi::Resource, Error> {
+        resource.validate()?;
 
-        assert!(rsvp.id != 0);
-    }
+        let mut tx = self.begin().await?;
 
-    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
-    async fn reserve_conflict_reservation_should_reject() {
-        let manager = ReservationManager::new(migrated_pool);
+        let resource = sqlx::query_as(
+            "INSERT INTO rsvp.resources (id, requires_approval, approvers, auto_confirm, tenant_id, resource_type)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (tenant_id, id) DO UPDATE SET
+                requires_approval = EXCLUDED.requires_approval,
+                approvers = EXCLUDED.approvers,
+                auto_confirm = EXCLUDED.auto_confirm,
+                resource_type = EXCLUDED.resource_type
+            RETURNING *",
+        )
+        .bind(resource.id)
+        .bind(resource.requires_approval)
+        .bind(resource.approvers)
+        .bind(resource.auto_confirm)
+        .bind(&self.tenant)
+        .bind(resource.resource_type)
+        .fetch_one(&mut tx)
+        .await?;
 
-        let rsvp1 = Reservation::new_pending(
-            "john",
-            "ocean_view_room_2",
-            "2024-01-01T00:00:00-0700".parse().unwrap(),
-            "2024-01-03T00:00:00-0700".parse().unwrap(),
-            "I'll arrive at 3pm, Please help to upgrade to executive room if possible.",
-        );
+        tx.commit().await?;
 
-        let rsvp2 = Reservation::new_pending(
-            "lei",
-            "ocean_view_room_2",
-            "2024-01-02T00:00:00-0700".parse().unwrap(),
-            "2024-01-04T00:00:00-0700".parse().unwrap(),
-            "Hello, I'm Lei, Please help to upgrade to executive room if possible.",
-        );
+        Ok(resource)
+    }
 
-        let _ = manager.reserve(rsvp1).await.unwrap();
-        let err = manager.reserve(rsvp2).await.unwrap_err();
+    async fn get_resource(&self, id: &str) -> Result<abi::Resource, Error> {
+        if id.is_empty() {
+            return Err(Error::InvalidResourceId(id.to_string()));
+        }
 
-        let info =
-            Error::ConflictReservation(ReservationConflictInfo::Parsed(ReservationWindow::new(
-                "ocean_view_room_2".to_string(),
-                "2024-01-01T07:00:00+00:00".parse().unwrap(),
-                "2024-01-03T07:00:00+00:00".parse().unwrap(),
-            )));
+        let mut tx = self.begin().await?;
 
-        assert_eq!(err, info);
+        let resource: Option<abi::Resource> =
+            sqlx::query_as("SELECT * FROM rsvp.resources WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&mut tx)
+                .await?;
+
+        tx.commit().await?;
+
+        Ok(resource.unwrap_or_else(|| abi::Resource {
+            id: id.to_string(),
+            tenant_id: self.tenant.clone(),
+            ..Default::default()
+        }))
     }
 
-    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
-    async fn change_reservation_status_should_work() {
+    async fn approve(
+        &self,
+        id: ReservationId,
+        approver: &str,
+        comment: String,
+    ) -> Result<(abi::Reservation, abi::Approval), Error> {
+        let mut tx = self.begin().await?;
+
+        self.awaiting_approval(&mut tx, id).await?;
+
+        let rsvp = sqlx::query_as(
+            "UPDATE rsvp.reservations
+            SET status = 'confirmed'::rsvp.reservation_status
+            WHERE id = $1
+            RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        let approval = self
+            .record_approval(&mut tx, id, approver, ApprovalDecision::Approved, comment)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok((rsvp, approval))
+    }
+
+    async fn reject(
+        &self,
+        id: ReservationId,
+        approver: &str,
+        comment: String,
+    ) -> Result<(abi::Reservation, abi::Approval), Error> {
+        let mut tx = self.begin().await?;
+
+        self.awaiting_approval(&mut tx, id).await?;
+
+        let rsvp = sqlx::query_as("DELETE FROM rsvp.reservations WHERE id = $1 RETURNING *")
+            .bind(id)
+            .fetch_one(&mut tx)
+            .await?;
+
+        let approval = self
+            .record_approval(&mut tx, id, approver, ApprovalDecision::Rejected, comment)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok((rsvp, approval))
+    }
+
+    async fn approval_queue(
+        &self,
+        approver: &str,
+        cursor: ReservationId,
+        page_size: i32,
+    ) -> Result<(Vec<abi::Reservation>, ReservationId), Error> {
+        if approver.is_empty() {
+            return Err(Error::MissingField("approver".to_string()));
+        }
+
+        // if page_size is less than 1 or more than 10000, use the default page size
+        let page_size = if (1..=10000).contains(&page_size) {
+            page_size
+        } else {
+            10
+        };
+
+        let mut tx = self.begin().await?;
+
+        let rsvps: Vec<abi::Reservation> = sqlx::query_as(
+            "SELECT r.* FROM rsvp.reservations r
+            JOIN rsvp.resources res ON res.tenant_id = r.tenant_id AND res.id = r.resource_id
+            WHERE res.requires_approval AND res.approvers @> ARRAY[$1]::varchar[]
+                AND r.status = 'pending'::rsvp.reservation_status AND r.id > $2
+            ORDER BY r.id
+            LIMIT $3",
+        )
+        .bind(approver)
+        .bind(cursor)
+        .bind(page_size)
+        .fetch_all(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let next = match rsvps.last() {
+            Some(last) if rsvps.len() == page_size as usize => last.id,
+            _ => -1,
+        };
+
+        Ok((rsvps, next))
+    }
+
+    async fn create_webhook(&self, webhook: abi::Webhook) -> Result<abi::Webhook, Error> {
+        webhook.validate()?;
+
+        let mut tx = self.begin().await?;
+        // only the changes made from now on are delivered
+        let cursor = last_change(&mut tx).await?;
+
+        let webhook = sqlx::query_as(
+            "INSERT INTO rsvp.webhooks (url, event_types, resource_id, user_id, secret, cursor)
+            VALUES ($1, $2, NULLIF($3, ''), NULLIF($4, ''), $5, $6)
+            RETURNING *",
+        )
+        .bind(&webhook.url)
+        .bind(&webhook.event_types)
+        .bind(&webhook.resource_id)
+        .bind(&webhook.user_id)
+        .bind(&webhook.secret)
+        .bind(cursor)
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(webhook)
+    }
+
+    async fn get_webhook(&self, id: i64) -> Result<abi::Webhook, Error> {
+        let mut tx = self.begin().await?;
+        let webhook = sqlx::query_as("SELECT * FROM rsvp.webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(webhook)
+    }
+
+    async fn list_webhooks(&self) -> Result<Vec<abi::Webhook>, Error> {
+        let mut tx = self.begin().await?;
+        let webhooks = sqlx::query_as("SELECT * FROM rsvp.webhooks ORDER BY id")
+            .fetch_all(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, id: i64) -> Result<abi::Webhook, Error> {
+        let mut tx = self.begin().await?;
+        let webhook = sqlx::query_as("DELETE FROM rsvp.webhooks WHERE id = $1 RETURNING *")
+            .bind(id)
+            .fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(webhook)
+    }
+
+    async fn dead_letters(&self, webhook_id: i64) -> Result<Vec<abi::DeadLetter>, Error> {
+        let mut tx = self.begin().await?;
+        let dead_letters = sqlx::query_as(
+            "SELECT * FROM rsvp.webhook_dead_letters
+            WHERE $1 = 0 OR webhook_id = $1
+            ORDER BY id",
+        )
+        .bind(webhook_id)
+        .fetch_all(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(dead_letters)
+    }
+
+    async fn audit_log(
+        &self,
+        query: abi::AuditQuery,
+    ) -> Result<(Vec<abi::AuditEntry>, i64), Error> {
+        query.validate()?;
+
+        // if page_size is less than 1 or more than 10000, use the default page size
+        let page_size = if (1..=10000).contains(&query.page_size) {
+            query.page_size
+        } else {
+            10
+        };
+
+        let mut tx = self.begin().await?;
+
+        let entries: Vec<abi::AuditEntry> = sqlx::query_as(
+            "SELECT * FROM rsvp.audit_log
+            WHERE ($1 = 0 OR reservation_id = $1)
+                AND ($2 = '' OR user_id = $2 OR actor = $2)
+                AND ($3 = '' OR resource_id = $3)
+                AND id > $4
+            ORDER BY id
+            LIMIT $5",
+        )
+        .bind(query.reservation_id)
+        .bind(&query.user_id)
+        .bind(&query.resource_id)
+        .bind(query.cursor)
+        .bind(page_size)
+        .fetch_all(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let next = match entries.last() {
+            Some(last) if entries.len() == page_size as usize => last.id,
+            _ => -1,
+        };
+
+        Ok((entries, next))
+    }
+}
+
+/// build the keyset cursor of a row for the given sort key
+fn row_to_cursor(row: &PgRow, order_by: ReservationOrderBy) -> FilterCursor {
+    let time_key = |dt: DateTime<Utc>| convert_to_timestamp(dt).to_string();
+
+    let value = match order_by {
+        ReservationOrderBy::Default | ReservationOrderBy::Id => None,
+        ReservationOrderBy::Start | ReservationOrderBy::End => {
+            let timespan: PgRange<DateTime<Utc>> = row.get("timespan");
+            let bound = match order_by {
+                ReservationOrderBy::Start => timespan.start,
+                _ => timespan.end,
+            };
+            match bound {
+                Bound::Included(dt) | Bound::Excluded(dt) => Some(time_key(dt)),
+                Bound::Unbounded => None,
+            }
+        }
+        ReservationOrderBy::CreatedAt => Some(time_key(row.get("created_at"))),
+        ReservationOrderBy::UpdatedAt => {
+            let updated_at: Option<DateTime<Utc>> = row.get("updated_at");
+            Some(time_key(
+                updated_at.unwrap_or_else(|| row.get("created_at")),
+            ))
+        }
+        ReservationOrderBy::UserId => Some(row.get("user_id")),
+        ReservationOrderBy::ResourceId => Some(row.get("resource_id")),
+    };
+
+    FilterCursor::new(row.get("id"), value)
+}
+
+fn order_to_option(order_by: ReservationOrderBy) -> Option<String> {
+    match order_by {
+        ReservationOrderBy::Default => None,
+        _ => Some(order_by.to_string()),
+    }
+}
+
+fn str_to_option(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+/// sequence number of the last change of the tenant of the transaction, 0 if there is none
+pub(crate) async fn last_change(tx: &mut Transaction<'_, Postgres>) -> Result<i64, Error> {
+    sqlx::query("SELECT rsvp.sequence_changes()")
+        .execute(&mut *tx)
+        .await?;
+    let seq = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(seq), 0) FROM rsvp.reservation_changes WHERE seq IS NOT NULL",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    Ok(seq)
+}
+
+/// the next changes of the tenant of the transaction after the given sequence number, in
+/// sequence order
+pub(crate) async fn changes_after(
+    tx: &mut Transaction<'_, Postgres>,
+    cursor: i64,
+) -> Result<Vec<abi::ListenResponse>, Error> {
+    sqlx::query("SELECT rsvp.sequence_changes()")
+        .execute(&mut *tx)
+        .await?;
+    let changes = sqlx::query_as(
+        "SELECT c.seq, c.txid, c.op, c.changed_fields, r.*
+        FROM rsvp.reservation_changes c, jsonb_populate_record(
+            NULL::rsvp.reservations,
+            jsonb_build_object('tenant_id', c.tenant_id, 'labels', '{}'::jsonb) || COALESCE(c.new, c.old)
+        ) r
+        WHERE c.seq > $1
+        ORDER BY c.seq
+        LIMIT $2",
+    )
+    .bind(cursor)
+    .bind(CHANGE_BATCH)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use abi::Reservation;
+    use abi::ReservationConflict;
+    use abi::ReservationWindow;
+    use prost_types::Timestamp;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_should_work_for_valid_window() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let rsvp = Reservation::new_pending(
+            "john",
+            "ocean_view_room_1",
+            "2024-01-01T00:00:00-0700".parse().unwrap(),
+            "2024-01-03T00:00:00-0700".parse().unwrap(),
+            "I'll arrive at 3pm, Please help to upgrade to executive room if possible.",
+        );
+
+        let rsvp = manager.reserve(rsvp).await.unwrap();
+
+        assert!(rsvp.id != 0);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_conflict_reservation_should_reject() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let rsvp1 = Reservation::new_pending(
+            "john",
+            "ocean_view_room_2",
+            "2024-01-01T00:00:00-0700".parse().unwrap(),
+            "2024-01-03T00:00:00-0700".parse().unwrap(),
+            "I'll arrive at 3pm, Please help to upgrade to executive room if possible.",
+        );
+
+        let rsvp2 = Reservation::new_pending(
+            "lei",
+            "ocean_view_room_2",
+            "2024-01-02T00:00:00-0700".parse().unwrap(),
+            "2024-01-04T00:00:00-0700".parse().unwrap(),
+            "Hello, I'm Lei, Please help to upgrade to executive room if possible.",
+        );
+
+        let rsvp1 = manager.reserve(rsvp1).await.unwrap();
+        let err = manager.reserve(rsvp2).await.unwrap_err();
+
+        let info =
+            Error::ConflictReservation(ReservationConflictInfo::new(vec![ReservationConflict {
+                id: rsvp1.id,
+                user_id: Some("john".to_string()),
+                status: ReservationStatus::Pending,
+                window: ReservationWindow::new(
+                    "ocean_view_room_2".to_string(),
+                    "2024-01-01T07:00:00+00:00".parse().unwrap(),
+                    "2024-01-03T07:00:00+00:00".parse().unwrap(),
+                ),
+            }]));
+
+        assert_eq!(err, info);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_conflict_should_report_every_conflicting_reservation() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let morning = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean_view_room_2",
+                "2024-01-01T08:00:00-0700".parse().unwrap(),
+                "2024-01-01T12:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+        let afternoon = manager
+            .reserve(Reservation::new_pending(
+                "lei",
+                "ocean_view_room_2",
+                "2024-01-01T13:00:00-0700".parse().unwrap(),
+                "2024-01-01T17:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+        let afternoon = manager.change_status(afternoon.id).await.unwrap();
+
+        let err = manager
+            .reserve(Reservation::new_pending(
+                "tyr",
+                "ocean_view_room_2",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap_err();
+
+        let Error::ConflictReservation(info) = err else {
+            panic!("expect conflict reservation error");
+        };
+
+        let ids: Vec<_> = info.conflicts.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![morning.id, afternoon.id]);
+        assert_eq!(info.conflicts[1].user_id, Some("lei".to_string()));
+        assert_eq!(info.conflicts[1].status, ReservationStatus::Confirmed);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn change_reservation_status_should_work() {
         let manager = ReservationManager::new(migrated_pool);
         let rsvp = Reservation::new_pending(
             "john",
@@ -327,6 +1327,197 @@ mod tests {
         assert_eq!(rsvp.note, note);
     }
 
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn history_should_keep_every_version() {
+        let manager = ReservationManager::new(migrated_pool);
+        let rsvp = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean_view_room_3",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "I'll arrive at 3pm",
+            ))
+            .await
+            .unwrap();
+        let updated = manager
+            .update_note(rsvp.id, "I'll arrive at 5pm".to_string())
+            .await
+            .unwrap();
+        let confirmed = manager.change_status(rsvp.id).await.unwrap();
+        manager.delete(rsvp.id).await.unwrap();
+
+        let versions = manager.get_history(rsvp.id).await.unwrap();
+        let ops: Vec<_> = versions.iter().map(|v| v.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                abi::ReservationType::Create as i32,
+                abi::ReservationType::Update as i32,
+                abi::ReservationType::Update as i32,
+                abi::ReservationType::Delete as i32,
+            ]
+        );
+        assert_eq!(versions[0].reservation, Some(rsvp.clone()));
+        assert_eq!(versions[1].reservation, Some(updated.clone()));
+        assert_eq!(versions[3].reservation, Some(confirmed.clone()));
+
+        let as_of = |i: usize| versions[i].changed_at.clone().unwrap();
+        assert_eq!(manager.get_as_of(rsvp.id, as_of(0)).await.unwrap(), rsvp);
+        assert_eq!(manager.get_as_of(rsvp.id, as_of(1)).await.unwrap(), updated);
+        assert_eq!(
+            manager.get_as_of(rsvp.id, as_of(2)).await.unwrap(),
+            confirmed
+        );
+        assert_eq!(
+            manager.get_as_of(rsvp.id, as_of(3)).await.unwrap_err(),
+            Error::NotFound
+        );
+        // before it was made
+        assert_eq!(
+            manager
+                .get_as_of(rsvp.id, Timestamp::default())
+                .await
+                .unwrap_err(),
+            Error::NotFound
+        );
+        assert_eq!(
+            manager.get_history(rsvp.id + 1).await.unwrap_err(),
+            Error::NotFound
+        );
+
+        // requests can't rewrite or erase the history
+        for sql in [
+            "UPDATE rsvp.reservation_changes SET new = NULL",
+            "DELETE FROM rsvp.reservation_changes",
+        ] {
+            let mut tx = manager.begin().await.unwrap();
+            let ret = sqlx::query(sql).execute(&mut tx).await;
+            assert!(ret.is_err(), "{} should be denied", sql);
+        }
+        assert_eq!(manager.get_history(rsvp.id).await.unwrap().len(), 4);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_filter_updates_by_changed_field() {
+        let manager = ReservationManager::new(migrated_pool);
+        let earlier = manager
+            .reserve(Reservation::new_pending(
+                "lei",
+                "ocean_view_room_1",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+
+        let mut rx = manager
+            .listen(abi::ListenRequest {
+                changed_fields: vec!["note".to_string()],
+                ..Default::default()
+            })
+            .await;
+
+        let rsvp = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean_view_room_3",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "I'll arrive at 3pm",
+            ))
+            .await
+            .unwrap();
+        manager.change_status(rsvp.id).await.unwrap();
+        let updated = manager
+            .update_note(rsvp.id, "I'll arrive at 5pm".to_string())
+            .await
+            .unwrap();
+        manager.delete(rsvp.id).await.unwrap();
+
+        let mut changes = Vec::new();
+        for _ in 0..3 {
+            let change = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            changes.push(change);
+        }
+
+        // the earlier reservation and the status change are not sent
+        assert_eq!(changes[0].op, abi::ReservationType::Create as i32);
+        assert_eq!(changes[0].reservation, Some(rsvp.clone()));
+        assert_eq!(changes[1].op, abi::ReservationType::Update as i32);
+        assert_eq!(changes[1].changed_fields, vec!["note".to_string()]);
+        assert_eq!(changes[1].reservation, Some(updated.clone()));
+        assert_eq!(changes[2].op, abi::ReservationType::Delete as i32);
+        assert_eq!(changes[2].reservation, Some(updated));
+        assert_ne!(changes[0].reservation.as_ref().unwrap().id, earlier.id);
+
+        let mut rx = manager
+            .listen(abi::ListenRequest {
+                changed_fields: vec!["timespan".to_string()],
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(
+            rx.recv().await.unwrap().unwrap_err(),
+            Error::InvalidChangedField("timespan".to_string())
+        );
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn listen_should_follow_commit_order() {
+        let manager = ReservationManager::new(migrated_pool);
+        let mut rx = manager.listen(abi::ListenRequest::default()).await;
+        let rsvp = |rid: &str| {
+            Reservation::new_pending(
+                "john",
+                rid,
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+        async fn next(
+            rx: &mut mpsc::Receiver<Result<abi::ListenResponse, Error>>,
+        ) -> abi::ListenResponse {
+            let next = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv());
+            next.await.unwrap().unwrap().unwrap()
+        }
+
+        // a transaction makes a change but commits after a later one
+        let mut slow = manager.begin().await.unwrap();
+        let slow_rsvp = manager
+            .insert(&mut slow, rsvp("ocean_view_room_1"))
+            .await
+            .unwrap();
+        let fast_rsvp = manager.reserve(rsvp("ocean_view_room_2")).await.unwrap();
+
+        // the later change waits for the earlier transaction to finish
+        let waiting = tokio::time::timeout(std::time::Duration::from_millis(1500), rx.recv());
+        assert!(waiting.await.is_err());
+        slow.commit().await.unwrap();
+
+        let first = next(&mut rx).await;
+        let second = next(&mut rx).await;
+        assert_eq!(first.reservation.unwrap().id, slow_rsvp.id);
+        assert_eq!(second.reservation.as_ref().unwrap().id, fast_rsvp.id);
+        assert!(first.sequence < second.sequence);
+        assert!(first.txid < second.txid);
+
+        // and a listener resumes after the last change it has seen
+        let mut rx = manager
+            .listen(abi::ListenRequest {
+                after_sequence: Some(first.sequence),
+                ..Default::default()
+            })
+            .await;
+        assert_eq!(next(&mut rx).await, second);
+    }
+
     #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
     async fn delete_reservation_by_id_should_work() {
         let manager = ReservationManager::new(migrated_pool);
@@ -557,4 +1748,822 @@ mod tests {
         assert_eq!(pager.prev, 3);
         assert_eq!(pager.next, 12);
     }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn filter_order_by_start_should_paginate_by_keyset() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        // insert in reverse start order, so id order and start order differ
+        let mut inserted = vec![];
+        for i in (1..6).rev() {
+            let insert = Reservation::new_pending(
+                "john",
+                "ocean_view_room_3",
+                format!("2024-01-{:02}T00:00:00-0700", i).parse().unwrap(),
+                format!("2024-01-{:02}T00:00:00-0700", i + 1)
+                    .parse()
+                    .unwrap(),
+                "I'll arrive at 3pm, Please help to upgrade to executive room if possible.",
+            );
+            inserted.push(manager.reserve(insert).await.unwrap());
+        }
+        inserted.reverse();
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .order_by(ReservationOrderBy::Start as i32)
+            .page_size(2)
+            .build()
+            .unwrap();
+
+        let (pager, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, inserted[0..2]);
+        assert_eq!(pager.next, inserted[1].id);
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .order_by(ReservationOrderBy::Start as i32)
+            .cursor_token(pager.next_token)
+            .page_size(2)
+            .build()
+            .unwrap();
+
+        let (pager, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, inserted[2..4]);
+
+        // walk back from the prev token
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .order_by(ReservationOrderBy::Start as i32)
+            .cursor_token(pager.prev_token)
+            .is_desc(true)
+            .page_size(2)
+            .build()
+            .unwrap();
+
+        let (_, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, inserted[0..2]);
+
+        // a plain id cursor works as well, the sort key is read from the cursor row
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .order_by(ReservationOrderBy::Start as i32)
+            .cursor(inserted[3].id)
+            .page_size(2)
+            .build()
+            .unwrap();
+
+        let (pager, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, inserted[4..5]);
+        assert_eq!(pager.next, -1);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn filter_order_by_user_id_should_break_ties_by_id() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let mut inserted = vec![];
+        for (i, uid) in ["lei", "john", "lei", "john"].iter().enumerate() {
+            let insert = Reservation::new_pending(
+                *uid,
+                format!("room_{}", i),
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "",
+            );
+            inserted.push(manager.reserve(insert).await.unwrap());
+        }
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .order_by(ReservationOrderBy::UserId as i32)
+            .page_size(3)
+            .build()
+            .unwrap();
+
+        let (pager, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(
+            rsvps,
+            vec![
+                inserted[1].clone(),
+                inserted[3].clone(),
+                inserted[0].clone()
+            ]
+        );
+        assert_eq!(pager.next_token, format!("{}:lei", inserted[0].id));
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .order_by(ReservationOrderBy::UserId as i32)
+            .cursor_token(pager.next_token)
+            .page_size(3)
+            .build()
+            .unwrap();
+
+        let (_, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, vec![inserted[2].clone()]);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_order_by_end_should_work() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let short = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_1",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+        let long = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_2",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-05T00:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+
+        let query = abi::ReservationQueryBuilder::default()
+            .user_id("john")
+            .start("2024-01-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2024-01-09T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .order_by(ReservationOrderBy::End as i32)
+            .is_desc(true)
+            .build()
+            .unwrap();
+
+        let mut rx = manager.query(query).await;
+        assert_eq!(rx.recv().await, Some(Ok(long)));
+        assert_eq!(rx.recv().await, Some(Ok(short)));
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn search_reservation_should_rank_and_highlight() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let lunch = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_1",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "Team lunch, the CEO may join",
+            ))
+            .await
+            .unwrap();
+        let board = manager
+            .reserve(Reservation::new_pending(
+                "lei",
+                "room_2",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "Board meeting with the CEO, CEO will present the budget",
+            ))
+            .await
+            .unwrap();
+        manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_3",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "Quiet room for focus time",
+            ))
+            .await
+            .unwrap();
+
+        let search = abi::ReservationSearchBuilder::default()
+            .query("ceo")
+            .build()
+            .unwrap();
+
+        let (_, hits) = manager.search(search).await.unwrap();
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].reservation, Some(board));
+        assert_eq!(hits[1].reservation, Some(lunch.clone()));
+        assert!(hits[0].rank > hits[1].rank);
+        assert!(hits[1].snippet.contains("<b>CEO</b>"));
+
+        // combined with the existing filters
+        let search = abi::ReservationSearchBuilder::default()
+            .query("ceo")
+            .user_id("john")
+            .status(ReservationStatus::Pending as i32)
+            .build()
+            .unwrap();
+
+        let (_, hits) = manager.search(search).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].reservation, Some(lunch));
+
+        let search = abi::ReservationSearchBuilder::default()
+            .query("ceo")
+            .end("2023-12-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .build()
+            .unwrap();
+
+        assert!(manager.search(search).await.unwrap().1.is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn search_reservation_should_page_and_escape_snippets() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        for i in 0..3 {
+            manager
+                .reserve(Reservation::new_pending(
+                    "john",
+                    format!("room_{}", i),
+                    "2024-01-01T00:00:00-0700".parse().unwrap(),
+                    "2024-01-02T00:00:00-0700".parse().unwrap(),
+                    "<script>alert(1)</script> & the CEO",
+                ))
+                .await
+                .unwrap();
+        }
+
+        let search = |page: i32| {
+            abi::ReservationSearchBuilder::default()
+                .query("ceo")
+                .page(page)
+                .page_size(2)
+                .build()
+                .unwrap()
+        };
+
+        let (pager, hits) = manager.search(search(1)).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(
+            pager,
+            abi::SearchPager {
+                prev: 0,
+                next: 2,
+                total: 3
+            }
+        );
+        assert!(hits[0]
+            .snippet
+            .ends_with("alert(1)&lt;/script&gt; &amp; the <b>CEO</b>"));
+        assert!(!hits[0].snippet.contains("<script"));
+
+        let (pager, hits) = manager.search(search(2)).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            pager,
+            abi::SearchPager {
+                prev: 1,
+                next: 0,
+                total: 3
+            }
+        );
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reservation_labels_and_attributes_should_round_trip() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let mut insert = Reservation::new_pending(
+            "john",
+            "ocean_view_room_3",
+            "2024-01-01T00:00:00-0700".parse().unwrap(),
+            "2024-01-03T00:00:00-0700".parse().unwrap(),
+            "",
+        );
+        insert
+            .labels
+            .insert("team".to_string(), "payments".to_string());
+        insert.attributes = abi::convert_to_struct(serde_json::json!({
+            "seats": 12.0,
+            "equipment": ["projector"],
+        }));
+
+        let rsvp = manager.reserve(insert).await.unwrap();
+        let fetched = manager.get(rsvp.id).await.unwrap();
+
+        assert_eq!(fetched, rsvp);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn label_selector_should_filter_reservations() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        let mut inserted = vec![];
+        for (i, labels) in [
+            vec![("team", "payments"), ("priority", "high")],
+            vec![("team", "payments"), ("priority", "low")],
+            vec![("team", "payments")],
+            vec![("team", "search"), ("priority", "high")],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut insert = Reservation::new_pending(
+                "john",
+                format!("room_{}", i),
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "weekly sync",
+            );
+            insert.labels = labels
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            inserted.push(manager.reserve(insert).await.unwrap());
+        }
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .label_selector("team=payments,priority!=low")
+            .build()
+            .unwrap();
+
+        let (_, rsvps) = manager.filter(filter).await.unwrap();
+        assert_eq!(rsvps, vec![inserted[0].clone(), inserted[2].clone()]);
+
+        let query = abi::ReservationQueryBuilder::default()
+            .user_id("john")
+            .start("2024-01-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2024-01-09T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .label_selector("priority,team!=payments")
+            .build()
+            .unwrap();
+
+        let mut rx = manager.query(query).await;
+        assert_eq!(rx.recv().await, Some(Ok(inserted[3].clone())));
+        assert_eq!(rx.recv().await, None);
+
+        // label values are searchable
+        let search = abi::ReservationSearchBuilder::default()
+            .query("search")
+            .label_selector("!priority")
+            .build()
+            .unwrap();
+        assert!(manager.search(search).await.unwrap().1.is_empty());
+
+        let search = abi::ReservationSearchBuilder::default()
+            .query("search")
+            .build()
+            .unwrap();
+        let (_, hits) = manager.search(search).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].reservation, Some(inserted[3].clone()));
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .label_selector("=payments")
+            .build()
+            .unwrap();
+        assert_eq!(
+            manager.filter(filter).await.unwrap_err(),
+            Error::InvalidLabelSelector("=payments".to_string())
+        );
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn query_should_stream_more_rows_than_channel_capacity() {
+        let manager = ReservationManager::new(migrated_pool);
+
+        for i in 0..200 {
+            let insert = Reservation::new_pending(
+                "john",
+                format!("room_{}", i),
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-02T00:00:00-0700".parse().unwrap(),
+                "",
+            );
+            manager.reserve(insert).await.unwrap();
+        }
+
+        let query = abi::ReservationQueryBuilder::default()
+            .user_id("john")
+            .start("2024-01-01T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .end("2024-01-09T00:00:00-0700".parse::<Timestamp>().unwrap())
+            .page_size(200)
+            .build()
+            .unwrap();
+
+        let mut rx = manager.query(query.clone()).await;
+        let mut count = 0;
+        while let Some(rsvp) = rx.recv().await {
+            rsvp.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 200);
+
+        // dropping the receiver early stops the query and releases the connection
+        let mut rx = manager.query(query).await;
+        assert!(rx.recv().await.is_some());
+        drop(rx);
+
+        let filter = abi::ReservationFilterBuilder::default()
+            .user_id("john")
+            .build()
+            .unwrap();
+        let (pager, _) = manager.filter(filter).await.unwrap();
+        assert_eq!(pager.total, 200);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn validate_reserve_should_not_write_anything() {
+        let manager = ReservationManager::new(migrated_pool);
+        let rsvp = Reservation::new_pending(
+            "john",
+            "ocean_view_room_1",
+            "2024-01-01T00:00:00-0700".parse().unwrap(),
+            "2024-01-03T00:00:00-0700".parse().unwrap(),
+            "",
+        );
+
+        let validated = manager.validate_reserve(rsvp.clone()).await.unwrap();
+        assert_eq!(validated.id, 0);
+
+        let count: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM rsvp.reservations) + (SELECT COUNT(*) FROM rsvp.reservation_changes)",
+        )
+        .fetch_one(&manager.pool)
+        .await
+        .unwrap();
+        assert_eq!(count, 0);
+
+        let existing = manager.reserve(rsvp.clone()).await.unwrap();
+        let err = manager.validate_reserve(rsvp).await.unwrap_err();
+
+        let Error::ConflictReservation(info) = err else {
+            panic!("expect conflict reservation error");
+        };
+        assert_eq!(info.conflicts[0].id, existing.id);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_all_should_report_each_reservation() {
+        let manager = ReservationManager::new(migrated_pool);
+        let rsvp = |start: &str, end: &str| {
+            Reservation::new_pending(
+                "john",
+                "ocean_view_room_1",
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+        let rsvps = vec![
+            rsvp("2024-01-01T00:00:00-0700", "2024-01-03T00:00:00-0700"),
+            rsvp("2024-01-02T00:00:00-0700", "2024-01-04T00:00:00-0700"),
+            rsvp("2024-01-05T00:00:00-0700", "2024-01-06T00:00:00-0700"),
+        ];
+
+        // the second one conflicts with the first one even though it isn't written
+        let results = manager.reserve_all(rsvps.clone(), true).await.unwrap();
+        assert!(results[0].as_ref().is_ok_and(|r| r.id == 0));
+        assert!(matches!(results[1], Err(Error::ConflictReservation(_))));
+        assert!(results[2].is_ok());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM rsvp.reservations")
+            .fetch_one(&manager.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let results = manager.reserve_all(rsvps, false).await.unwrap();
+        let Err(Error::ConflictReservation(info)) = &results[1] else {
+            panic!("expect conflict reservation error");
+        };
+        assert_eq!(info.conflicts[0].id, results[0].as_ref().unwrap().id);
+        assert!(results[2].as_ref().is_ok_and(|r| r.id != 0));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reserve_all_or_none_should_roll_back_on_failure() {
+        let manager = ReservationManager::new(migrated_pool);
+        let rsvp = |resource_id: &str| {
+            Reservation::new_pending(
+                "john",
+                resource_id,
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+
+        let results = manager
+            .reserve_all_or_none(vec![rsvp("room-1"), rsvp("room-2"), rsvp("room-1")])
+            .await
+            .unwrap();
+        assert!(results[0].as_ref().is_ok_and(|r| r.id == 0));
+        assert!(matches!(results[2], Err(Error::ConflictReservation(_))));
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM rsvp.reservations")
+            .fetch_one(&manager.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let results = manager
+            .reserve_all_or_none(vec![rsvp("room-1"), rsvp("room-2")])
+            .await
+            .unwrap();
+        assert!(results.iter().all(|r| r.as_ref().is_ok_and(|r| r.id > 0)));
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn export_should_stream_every_status() {
+        let manager = ReservationManager::new(migrated_pool);
+        let mut ids = Vec::new();
+        for day in 1..=3 {
+            let rsvp = Reservation::new_pending(
+                "john",
+                "ocean_view_room_1",
+                format!("2024-01-0{}T00:00:00-0700", day).parse().unwrap(),
+                format!("2024-01-0{}T12:00:00-0700", day).parse().unwrap(),
+                "",
+            );
+            ids.push(manager.reserve(rsvp).await.unwrap().id);
+        }
+        manager.change_status(ids[1]).await.unwrap();
+
+        let query = abi::ReservationQuery {
+            resource_id: "ocean_view_room_1".to_string(),
+            ..Default::default()
+        };
+        let mut rx = manager.export(query.clone()).await;
+        let mut exported = Vec::new();
+        while let Some(rsvp) = rx.recv().await {
+            exported.push(rsvp.unwrap().id);
+        }
+        exported.sort();
+        assert_eq!(exported, ids);
+
+        let query = abi::ReservationQuery {
+            status: ReservationStatus::Confirmed as i32,
+            ..query
+        };
+        let mut rx = manager.export(query).await;
+        assert_eq!(rx.recv().await.unwrap().unwrap().id, ids[1]);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn tenants_should_be_isolated() {
+        let acme = ReservationManager::new(migrated_pool).with_tenant("acme");
+        let globex = acme.with_tenant("globex");
+        let rsvp = Reservation::new_pending(
+            "john",
+            "ocean_view_room_1",
+            "2024-01-01T00:00:00-0700".parse().unwrap(),
+            "2024-01-03T00:00:00-0700".parse().unwrap(),
+            "",
+        );
+
+        // the same resource of different tenants never conflicts
+        let rsvp1 = acme.reserve(rsvp.clone()).await.unwrap();
+        let rsvp2 = globex.reserve(rsvp.clone()).await.unwrap();
+        assert_eq!(rsvp1.tenant_id, "acme");
+        assert_eq!(rsvp2.tenant_id, "globex");
+
+        // and a conflict only reports reservations of the tenant
+        let Error::ConflictReservation(info) = acme.reserve(rsvp).await.unwrap_err() else {
+            panic!("expect conflict reservation error");
+        };
+        assert_eq!(info.conflicts.len(), 1);
+        assert_eq!(info.conflicts[0].id, rsvp1.id);
+
+        // nothing crosses tenants
+        assert_eq!(acme.get(rsvp1.id).await.unwrap(), rsvp1);
+        assert_eq!(acme.get(rsvp2.id).await.unwrap_err(), Error::NotFound);
+        assert_eq!(
+            acme.change_status(rsvp2.id).await.unwrap_err(),
+            Error::NotFound
+        );
+        acme.delete(rsvp2.id).await.unwrap();
+        assert_eq!(globex.get(rsvp2.id).await.unwrap(), rsvp2);
+
+        let filter = abi::ReservationFilterBuilder::default().build().unwrap();
+        let (pager, rsvps) = acme.filter(filter).await.unwrap();
+        assert_eq!(pager.total, 1);
+        assert_eq!(rsvps, vec![rsvp1]);
+
+        let query = abi::ReservationQueryBuilder::default().build().unwrap();
+        let mut rx = globex.query(query).await;
+        assert_eq!(rx.recv().await.unwrap().unwrap(), rsvp2);
+        assert!(rx.recv().await.is_none());
+
+        // the change feed is per tenant as well
+        let tenants: Vec<String> =
+            sqlx::query_scalar("SELECT tenant_id FROM rsvp.reservation_changes ORDER BY id")
+                .fetch_all(&acme.pool)
+                .await
+                .unwrap();
+        assert_eq!(tenants, vec!["acme", "globex"]);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn resources_requiring_approval_should_be_approved() {
+        let manager = ReservationManager::new(migrated_pool);
+        manager
+            .set_resource(abi::Resource::with_approvers("boardroom", &["alice"]))
+            .await
+            .unwrap();
+        let rsvp = |rid: &str, start: &str, end: &str| {
+            Reservation::new_pending(
+                "john",
+                rid,
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            )
+        };
+
+        let rsvp1 = manager
+            .reserve(rsvp(
+                "boardroom",
+                "2024-01-01T00:00:00-0700",
+                "2024-01-02T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+        let rsvp2 = manager
+            .reserve(rsvp(
+                "boardroom",
+                "2024-01-02T00:00:00-0700",
+                "2024-01-03T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+
+        // confirm is refused, the approvers decide
+        assert_eq!(
+            manager.change_status(rsvp1.id).await.unwrap_err(),
+            Error::ApprovalRequired(rsvp1.id)
+        );
+        let (queue, next) = manager.approval_queue("alice", 0, 1).await.unwrap();
+        assert_eq!(queue, vec![rsvp1.clone()]);
+        let (queue, _) = manager.approval_queue("alice", next, 1).await.unwrap();
+        assert_eq!(queue, vec![rsvp2.clone()]);
+        assert!(manager
+            .approval_queue("bob", 0, 10)
+            .await
+            .unwrap()
+            .0
+            .is_empty());
+
+        let (approved, approval) = manager
+            .approve(rsvp1.id, "alice", "enjoy".to_string())
+            .await
+            .unwrap();
+        assert_eq!(approved.status, ReservationStatus::Confirmed as i32);
+        assert_eq!(approval.approver, "alice");
+        assert_eq!(approval.decision, ApprovalDecision::Approved as i32);
+        assert_eq!(approval.comment, "enjoy");
+
+        // a decision is only made once
+        assert_eq!(
+            manager
+                .approve(rsvp1.id, "alice", String::new())
+                .await
+                .unwrap_err(),
+            Error::NotAwaitingApproval(rsvp1.id)
+        );
+
+        // a rejected reservation releases the resource
+        let (rejected, approval) = manager
+            .reject(rsvp2.id, "alice", "board meeting".to_string())
+            .await
+            .unwrap();
+        assert_eq!(rejected.id, rsvp2.id);
+        assert_eq!(approval.decision, ApprovalDecision::Rejected as i32);
+        assert_eq!(manager.get(rsvp2.id).await.unwrap_err(), Error::NotFound);
+        assert!(manager
+            .approval_queue("alice", 0, 10)
+            .await
+            .unwrap()
+            .0
+            .is_empty());
+        manager
+            .reserve(rsvp(
+                "boardroom",
+                "2024-01-02T00:00:00-0700",
+                "2024-01-03T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+
+        // other resources don't take approvals, and may confirm right away
+        let rsvp3 = manager
+            .reserve(rsvp(
+                "room_01",
+                "2024-01-01T00:00:00-0700",
+                "2024-01-02T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .approve(rsvp3.id, "alice", String::new())
+                .await
+                .unwrap_err(),
+            Error::NotAwaitingApproval(rsvp3.id)
+        );
+
+        manager
+            .set_resource(abi::Resource {
+                id: "desk".to_string(),
+                auto_confirm: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let rsvp4 = manager
+            .reserve(rsvp(
+                "desk",
+                "2024-01-01T00:00:00-0700",
+                "2024-01-02T00:00:00-0700",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(rsvp4.status, ReservationStatus::Confirmed as i32);
+        assert_eq!(manager.get(rsvp4.id).await.unwrap(), rsvp4);
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn changes_should_be_audited() {
+        let manager = ReservationManager::new(migrated_pool).with_audit(crate::AuditContext {
+            actor: Some("bob".to_string()),
+            request_id: Some("req-1".to_string()),
+            rpc: Some("confirm".to_string()),
+            reason: Some("checked in".to_string()),
+        });
+        let rsvp = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean_view_room_1",
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+        manager.change_status(rsvp.id).await.unwrap();
+        // changes without a context are recorded too
+        let anonymous = manager.with_audit(Default::default());
+        anonymous.delete(rsvp.id).await.unwrap();
+
+        let query = abi::AuditQuery {
+            reservation_id: rsvp.id,
+            page_size: 2,
+            ..Default::default()
+        };
+        let (entries, next) = manager.audit_log(query.clone()).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].op, abi::ReservationType::Create as i32);
+        assert_eq!(entries[0].before, None);
+        assert_eq!(entries[1].op, abi::ReservationType::Update as i32);
+        assert_eq!(entries[1].actor, "bob");
+        assert_eq!(entries[1].request_id, "req-1");
+        assert_eq!(entries[1].rpc, "confirm");
+        assert_eq!(entries[1].reason, "checked in");
+        assert_eq!(entries[1].user_id, "john");
+        assert_eq!(entries[1].tenant_id, crate::DEFAULT_TENANT);
+        let status =
+            |entry: Option<&prost_types::Struct>| entry.unwrap().fields["status"].kind.clone();
+        assert_eq!(
+            status(entries[1].before.as_ref()),
+            Some(prost_types::value::Kind::StringValue("pending".to_string()))
+        );
+        assert_eq!(
+            status(entries[1].after.as_ref()),
+            Some(prost_types::value::Kind::StringValue(
+                "confirmed".to_string()
+            ))
+        );
+
+        let query = abi::AuditQuery {
+            cursor: next,
+            ..query
+        };
+        let (entries, next) = manager.audit_log(query).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(next, -1);
+        assert_eq!(entries[0].op, abi::ReservationType::Delete as i32);
+        assert_eq!(entries[0].actor, "");
+        assert_eq!(entries[0].after, None);
+
+        // the actor finds the changes made by them
+        let query = abi::AuditQuery {
+            user_id: "bob".to_string(),
+            ..Default::default()
+        };
+        let (entries, _) = manager.audit_log(query).await.unwrap();
+        assert_eq!(entries.len(), 2);
+
+        // other tenants see nothing
+        let query = abi::AuditQuery {
+            resource_id: "ocean_view_room_1".to_string(),
+            ..Default::default()
+        };
+        let (entries, _) = manager.with_tenant("acme").audit_log(query).await.unwrap();
+        assert!(entries.is_empty());
+    }
 }
@@ -0,0 +1,284 @@
+use std::time::Duration;
+
+use abi::Error;
+use sqlx::{
+    postgres::PgRow,
+    types::{
+        chrono::{DateTime, Utc},
+        Json, JsonValue,
+    },
+    FromRow, Row,
+};
+
+use crate::{manager::changes_after, ReservationManager};
+
+/// a payload to POST to a webhook
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub url: String,
+    pub secret: String,
+    /// sequence number of the change
+    pub sequence: i64,
+    pub payload: JsonValue,
+    /// attempts made so far, including the current one
+    pub attempts: i32,
+}
+
+impl ReservationManager {
+    /// the tenants having webhooks, the delivery worker serves them one by one
+    pub async fn webhook_tenants(&self) -> Result<Vec<String>, Error> {
+        let mut tx = self.begin().await?;
+        let tenants = sqlx::query_scalar("SELECT rsvp.webhook_tenants()")
+            .fetch_all(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(tenants)
+    }
+
+    /// turn the changes committed since the last call into deliveries of the webhooks of the
+    /// tenant, the webhook cursors move in the same transaction. Returns how many were queued
+    pub async fn enqueue_deliveries(&self) -> Result<usize, Error> {
+        let mut tx = self.begin().await?;
+
+        // webhooks another worker is enqueuing for are left to it
+        let rows = sqlx::query("SELECT * FROM rsvp.webhooks ORDER BY id FOR UPDATE SKIP LOCKED")
+            .fetch_all(&mut tx)
+            .await?;
+
+        let mut queued = 0;
+        for row in rows {
+            let webhook = abi::Webhook::from_row(&row)?;
+            let cursor: i64 = row.get("cursor");
+
+            let changes = changes_after(&mut tx, cursor).await?;
+            let Some(last) = changes.last().map(|change| change.sequence) else {
+                continue;
+            };
+
+            for change in changes.iter().filter(|change| webhook.wants(change)) {
+                sqlx::query(
+                    "INSERT INTO rsvp.webhook_deliveries (webhook_id, seq, payload)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT DO NOTHING",
+                )
+                .bind(webhook.id)
+                .bind(change.sequence)
+                .bind(Json(change.to_webhook_payload()))
+                .execute(&mut tx)
+                .await?;
+                queued += 1;
+            }
+
+            sqlx::query("UPDATE rsvp.webhooks SET cursor = $2 WHERE id = $1")
+                .bind(webhook.id)
+                .bind(last)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(queued)
+    }
+
+    /// claim the due deliveries of the tenant, oldest first. They are due again once the lease
+    /// is over unless they are completed or failed before
+    pub async fn claim_deliveries(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<Delivery>, Error> {
+        let mut tx = self.begin().await?;
+
+        let deliveries = sqlx::query_as(
+            "WITH due AS (
+                SELECT id FROM rsvp.webhook_deliveries
+                WHERE next_attempt_at <= NOW()
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE rsvp.webhook_deliveries d
+            SET attempts = d.attempts + 1, next_attempt_at = NOW() + $2 * INTERVAL '1 millisecond'
+            FROM due, rsvp.webhooks w
+            WHERE d.id = due.id AND w.id = d.webhook_id
+            RETURNING d.id, d.webhook_id, w.url, w.secret, d.seq, d.payload, d.attempts",
+        )
+        .bind(limit)
+        .bind(lease.as_millis() as i64)
+        .fetch_all(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(deliveries)
+    }
+
+    /// the delivery succeeded
+    pub async fn complete_delivery(&self, id: i64) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+        sqlx::query("DELETE FROM rsvp.webhook_deliveries WHERE id = $1")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// the delivery failed, retry it at the given time or give up and move it to the dead
+    /// letters if there is none
+    pub async fn fail_delivery(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+
+        match retry_at {
+            Some(retry_at) => {
+                sqlx::query(
+                    "UPDATE rsvp.webhook_deliveries SET last_error = $2, next_attempt_at = $3
+                    WHERE id = $1",
+                )
+                .bind(id)
+                .bind(error)
+                .bind(retry_at)
+                .execute(&mut tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(
+                    "WITH failed AS (
+                        DELETE FROM rsvp.webhook_deliveries WHERE id = $1 RETURNING *
+                    )
+                    INSERT INTO rsvp.webhook_dead_letters (webhook_id, seq, payload, attempts, last_error)
+                    SELECT webhook_id, seq, payload, attempts, $2 FROM failed",
+                )
+                .bind(id)
+                .bind(error)
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Delivery {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let payload: Json<JsonValue> = row.get("payload");
+
+        Ok(Self {
+            id: row.get("id"),
+            webhook_id: row.get("webhook_id"),
+            url: row.get("url"),
+            secret: row.get("secret"),
+            sequence: row.get("seq"),
+            payload: payload.0,
+            attempts: row.get("attempts"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abi::{Reservation, ReservationType, Webhook};
+
+    use super::*;
+    use crate::Rsvp;
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn outbox_should_deliver_changes_to_webhooks() {
+        let manager = ReservationManager::new(migrated_pool);
+        let rsvp = |rid: &str| {
+            Reservation::new_pending(
+                "john",
+                rid,
+                "2024-01-01T00:00:00-0700".parse().unwrap(),
+                "2024-01-03T00:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+
+        // changes made before the webhook is created are not delivered
+        manager.reserve(rsvp("ocean_view_room_1")).await.unwrap();
+        let webhook = manager
+            .create_webhook(Webhook {
+                url: "http://localhost:9/hooks".to_string(),
+                event_types: vec![ReservationType::Create as i32],
+                resource_id: "ocean_view_room_2".to_string(),
+                secret: "s3cret".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.webhook_tenants().await.unwrap(),
+            vec![crate::DEFAULT_TENANT.to_string()]
+        );
+
+        let wanted = manager.reserve(rsvp("ocean_view_room_2")).await.unwrap();
+        manager.reserve(rsvp("ocean_view_room_3")).await.unwrap();
+        manager.delete(wanted.id).await.unwrap();
+
+        assert_eq!(manager.enqueue_deliveries().await.unwrap(), 1);
+        // the cursor moved on
+        assert_eq!(manager.enqueue_deliveries().await.unwrap(), 0);
+
+        let lease = Duration::from_secs(60);
+        let deliveries = manager.claim_deliveries(10, lease).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        let delivery = &deliveries[0];
+        assert_eq!(delivery.webhook_id, webhook.id);
+        assert_eq!(delivery.secret, "s3cret");
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.payload["type"], "reservation.created");
+        assert_eq!(delivery.payload["reservation"]["id"], wanted.id);
+        // it's leased
+        assert!(manager
+            .claim_deliveries(10, lease)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // retried when due
+        manager
+            .fail_delivery(delivery.id, "503 Service Unavailable", Some(Utc::now()))
+            .await
+            .unwrap();
+        let deliveries = manager.claim_deliveries(10, lease).await.unwrap();
+        assert_eq!(deliveries[0].attempts, 2);
+
+        // and given up on at last
+        manager
+            .fail_delivery(delivery.id, "503 Service Unavailable", None)
+            .await
+            .unwrap();
+        let dead_letters = manager.dead_letters(webhook.id).await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 2);
+        assert_eq!(dead_letters[0].last_error, "503 Service Unavailable");
+        assert_eq!(dead_letters[0].sequence, delivery.sequence);
+        assert!(manager
+            .claim_deliveries(10, lease)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // deleting the webhook drops its dead letters
+        manager.delete_webhook(webhook.id).await.unwrap();
+        assert!(manager.dead_letters(0).await.unwrap().is_empty());
+        assert_eq!(
+            manager.get_webhook(webhook.id).await.unwrap_err(),
+            Error::NotFound
+        );
+    }
+}
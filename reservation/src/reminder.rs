@@ -0,0 +1,388 @@
+use std::time::Duration;
+
+use abi::{Error, ReminderConfig, ReservationId, ReservationStatus, ReservationType};
+use sqlx::{
+    postgres::PgRow,
+    types::chrono::{DateTime, Utc},
+    FromRow, Row,
+};
+
+use crate::{manager::changes_after, ReservationManager};
+
+/// changes of these fields move or cancel the reminders of a reservation
+const REMINDED_FIELDS: &[&str] = &["resource_id", "user_id", "status", "start"];
+
+/// a reminder of an upcoming reservation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    pub id: i64,
+    pub tenant_id: String,
+    pub reservation_id: ReservationId,
+    pub user_id: String,
+    pub resource_id: String,
+    /// start of the reservation
+    pub start: DateTime<Utc>,
+    /// minutes before the start it's sent
+    pub offset_min: i32,
+    pub remind_at: DateTime<Utc>,
+    /// attempts made so far, including the current one
+    pub attempts: i32,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl ReservationManager {
+    /// the tenants having changes to schedule or reminders to send, the scheduler serves them
+    /// one by one
+    pub async fn reminder_tenants(&self) -> Result<Vec<String>, Error> {
+        let mut tx = self.begin().await?;
+        let tenants = sqlx::query_scalar("SELECT rsvp.reminder_tenants()")
+            .fetch_all(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(tenants)
+    }
+
+    /// schedule the reminders of the reservations of the tenant changed since the last call:
+    /// new and moved reservations get reminders, the pending reminders of moved and deleted
+    /// ones are cancelled. Returns how many changes were processed
+    pub async fn schedule_reminders(&self, config: &ReminderConfig) -> Result<usize, Error> {
+        let mut tx = self.begin().await?;
+
+        sqlx::query("INSERT INTO rsvp.reminder_cursors DEFAULT VALUES ON CONFLICT DO NOTHING")
+            .execute(&mut tx)
+            .await?;
+        // the tenant is left to the scheduler already working on it
+        let cursor: Option<i64> =
+            sqlx::query_scalar("SELECT cursor FROM rsvp.reminder_cursors FOR UPDATE SKIP LOCKED")
+                .fetch_optional(&mut tx)
+                .await?;
+        let Some(cursor) = cursor else {
+            return Ok(0);
+        };
+
+        let changes = changes_after(&mut tx, cursor).await?;
+        let Some(last) = changes.last().map(|change| change.sequence) else {
+            return Ok(0);
+        };
+
+        for change in &changes {
+            let op = ReservationType::try_from(change.op).unwrap_or_default();
+            let moved = op != ReservationType::Update
+                || change
+                    .changed_fields
+                    .iter()
+                    .any(|f| REMINDED_FIELDS.contains(&f.as_str()));
+            let Some(rsvp) = change.reservation.as_ref().filter(|_| moved) else {
+                continue;
+            };
+
+            sqlx::query(
+                "DELETE FROM rsvp.reminders
+                WHERE reservation_id = $1 AND sent_at IS NULL AND failed_at IS NULL",
+            )
+            .bind(rsvp.id)
+            .execute(&mut tx)
+            .await?;
+
+            let status = ReservationStatus::try_from(rsvp.status).unwrap_or_default();
+            let reminded = matches!(
+                status,
+                ReservationStatus::Pending | ReservationStatus::Confirmed
+            );
+            if op == ReservationType::Delete || !reminded {
+                continue;
+            }
+
+            let resource_type: Option<String> =
+                sqlx::query_scalar("SELECT resource_type FROM rsvp.resources WHERE id = $1")
+                    .bind(&rsvp.resource_id)
+                    .fetch_optional(&mut tx)
+                    .await?;
+            let offsets = config.offsets(resource_type.as_deref().unwrap_or_default());
+
+            // reminders already due are skipped, those already sent are not sent again
+            sqlx::query(
+                "INSERT INTO rsvp.reminders
+                    (reservation_id, user_id, resource_id, start_at, offset_min, remind_at, next_attempt_at)
+                SELECT $1, $2, $3, $4, o, $4 - o * INTERVAL '1 minute', $4 - o * INTERVAL '1 minute'
+                FROM unnest($5::integer[]) o
+                WHERE $4 - o * INTERVAL '1 minute' > NOW()
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(rsvp.id)
+            .bind(&rsvp.user_id)
+            .bind(&rsvp.resource_id)
+            .bind(abi::convert_to_utc_time(rsvp.start.as_ref().unwrap()))
+            .bind(offsets.iter().map(|&o| o as i32).collect::<Vec<_>>())
+            .execute(&mut tx)
+            .await?;
+        }
+
+        sqlx::query("UPDATE rsvp.reminder_cursors SET cursor = $1")
+            .bind(last)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(changes.len())
+    }
+
+    /// claim the due reminders of the tenant whose reservations have not started yet, earliest
+    /// first. They are due again once the lease is over unless they are sent or failed before
+    pub async fn claim_reminders(
+        &self,
+        limit: i64,
+        lease: Duration,
+    ) -> Result<Vec<Reminder>, Error> {
+        let mut tx = self.begin().await?;
+
+        let reminders = sqlx::query_as(
+            "WITH due AS (
+                SELECT id FROM rsvp.reminders
+                WHERE sent_at IS NULL AND failed_at IS NULL
+                    AND next_attempt_at <= NOW() AND start_at > NOW()
+                ORDER BY remind_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE rsvp.reminders r
+            SET attempts = r.attempts + 1, next_attempt_at = NOW() + $2 * INTERVAL '1 millisecond'
+            FROM due
+            WHERE r.id = due.id
+            RETURNING r.*",
+        )
+        .bind(limit)
+        .bind(lease.as_millis() as i64)
+        .fetch_all(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(reminders)
+    }
+
+    /// the reminder was sent
+    pub async fn complete_reminder(&self, id: i64) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+        sqlx::query("UPDATE rsvp.reminders SET sent_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// the reminder could not be sent, retry it at the given time or give up if there is none
+    pub async fn fail_reminder(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+
+        sqlx::query(
+            "UPDATE rsvp.reminders
+            SET last_error = $2,
+                next_attempt_at = COALESCE($3, next_attempt_at),
+                failed_at = CASE WHEN $3 IS NULL THEN NOW() END
+            WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// the reminders of the reservation sent or to send, earliest first
+    pub async fn reminders(&self, reservation_id: ReservationId) -> Result<Vec<Reminder>, Error> {
+        let mut tx = self.begin().await?;
+        let reminders = sqlx::query_as(
+            "SELECT * FROM rsvp.reminders WHERE reservation_id = $1 AND failed_at IS NULL
+            ORDER BY remind_at",
+        )
+        .bind(reservation_id)
+        .fetch_all(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(reminders)
+    }
+}
+
+impl FromRow<'_, PgRow> for Reminder {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            reservation_id: row.get("reservation_id"),
+            user_id: row.get("user_id"),
+            resource_id: row.get("resource_id"),
+            start: row.get("start_at"),
+            offset_min: row.get("offset_min"),
+            remind_at: row.get("remind_at"),
+            attempts: row.get("attempts"),
+            sent_at: row.get("sent_at"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abi::{Reservation, Resource};
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::Rsvp;
+
+    fn in_hours(hours: u64) -> prost_types::Timestamp {
+        (SystemTime::now() + Duration::from_secs(hours * 3600)).into()
+    }
+
+    fn pending(rid: &str, start_in_hours: u64, end_in_hours: u64) -> Reservation {
+        Reservation {
+            user_id: "john".to_string(),
+            resource_id: rid.to_string(),
+            start: Some(in_hours(start_in_hours)),
+            end: Some(in_hours(end_in_hours)),
+            status: ReservationStatus::Pending as i32,
+            ..Default::default()
+        }
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reminder_tenants_should_have_changes_to_schedule() {
+        let manager = ReservationManager::new(migrated_pool);
+        let globex = manager.with_tenant("globex");
+        // no offsets, scheduling leaves no reminder to send
+        let config = ReminderConfig {
+            offsets_min: vec![],
+            ..Default::default()
+        };
+        assert!(manager.reminder_tenants().await.unwrap().is_empty());
+
+        manager.reserve(pending("room-1", 48, 50)).await.unwrap();
+        globex.reserve(pending("room-1", 48, 50)).await.unwrap();
+        let mut tenants = manager.reminder_tenants().await.unwrap();
+        tenants.sort();
+        assert_eq!(tenants, vec!["default".to_string(), "globex".to_string()]);
+
+        assert_eq!(manager.schedule_reminders(&config).await.unwrap(), 1);
+        assert_eq!(manager.reminder_tenants().await.unwrap(), vec!["globex"]);
+
+        assert_eq!(globex.schedule_reminders(&config).await.unwrap(), 1);
+        assert!(manager.reminder_tenants().await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn reminders_should_follow_the_reservations() {
+        let manager = ReservationManager::new(migrated_pool);
+        let config = ReminderConfig {
+            offsets_min: vec![24 * 60, 15],
+            resource_types: [("desk".to_string(), vec![30])].into(),
+            ..Default::default()
+        };
+        manager
+            .set_resource(Resource {
+                id: "desk-1".to_string(),
+                resource_type: "desk".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let room = manager.reserve(pending("room-1", 48, 50)).await.unwrap();
+        // the day before is already past
+        let desk = manager.reserve(pending("desk-1", 2, 3)).await.unwrap();
+        let soon = manager.reserve(pending("room-2", 2, 3)).await.unwrap();
+
+        assert_eq!(manager.schedule_reminders(&config).await.unwrap(), 3);
+        assert_eq!(manager.schedule_reminders(&config).await.unwrap(), 0);
+
+        let offsets =
+            |reminders: Vec<Reminder>| reminders.iter().map(|r| r.offset_min).collect::<Vec<_>>();
+        assert_eq!(
+            offsets(manager.reminders(room.id).await.unwrap()),
+            vec![24 * 60, 15]
+        );
+        assert_eq!(offsets(manager.reminders(desk.id).await.unwrap()), vec![30]);
+        assert_eq!(offsets(manager.reminders(soon.id).await.unwrap()), vec![15]);
+
+        // nothing is due yet
+        let lease = Duration::from_secs(60);
+        assert!(manager.claim_reminders(10, lease).await.unwrap().is_empty());
+
+        // moving the reservation moves its reminders, the note doesn't
+        manager
+            .update_note(room.id, "bring slides".to_string())
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE rsvp.reservations
+            SET timespan = tstzrange(NOW() + INTERVAL '72 hours', NOW() + INTERVAL '74 hours')
+            WHERE id = $1",
+        )
+        .bind(room.id)
+        .execute(&manager.pool)
+        .await
+        .unwrap();
+        manager.delete(desk.id).await.unwrap();
+
+        assert_eq!(manager.schedule_reminders(&config).await.unwrap(), 3);
+        let reminders = manager.reminders(room.id).await.unwrap();
+        assert_eq!(offsets(reminders.clone()), vec![24 * 60, 15]);
+        assert_eq!(
+            (reminders[0].start - reminders[0].remind_at).num_hours(),
+            24
+        );
+        assert!(reminders[0].start.timestamp() > in_hours(71).seconds);
+        assert!(manager.reminders(desk.id).await.unwrap().is_empty());
+    }
+
+    #[sqlx_database_tester::test(pool(variable = "migrated_pool", migrations = "../migrations"))]
+    async fn due_reminders_should_be_sent_once() {
+        let manager = ReservationManager::new(migrated_pool);
+        let config = ReminderConfig {
+            offsets_min: vec![120, 90],
+            ..Default::default()
+        };
+        let rsvp = manager.reserve(pending("room-1", 3, 4)).await.unwrap();
+        manager.schedule_reminders(&config).await.unwrap();
+
+        // pretend they are due
+        sqlx::query("UPDATE rsvp.reminders SET next_attempt_at = NOW()")
+            .execute(&manager.pool)
+            .await
+            .unwrap();
+
+        let lease = Duration::from_secs(60);
+        let reminders = manager.claim_reminders(10, lease).await.unwrap();
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].reservation_id, rsvp.id);
+        assert_eq!(reminders[0].attempts, 1);
+        assert!(manager.claim_reminders(10, lease).await.unwrap().is_empty());
+
+        manager.complete_reminder(reminders[0].id).await.unwrap();
+        manager
+            .fail_reminder(reminders[1].id, "connection refused", None)
+            .await
+            .unwrap();
+
+        // confirming the reservation doesn't send any of them again
+        manager.change_status(rsvp.id).await.unwrap();
+        manager.schedule_reminders(&config).await.unwrap();
+        let reminders = manager.reminders(rsvp.id).await.unwrap();
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].offset_min, 120);
+        assert!(reminders[0].sent_at.is_some());
+        assert!(manager.claim_reminders(10, lease).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use reservation::AuditContext;
+use tonic::Request;
+
+use crate::Principal;
+
+/// header correlating the changes of a request, one is generated if the caller doesn't send it
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// header explaining why the caller makes the change
+pub const AUDIT_REASON_HEADER: &str = "x-audit-reason";
+
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// who makes the changes of the request, through which rpc and why
+pub fn audit_from_request<T>(request: &Request<T>, rpc: &str) -> AuditContext {
+    let header = |name: &str| {
+        request
+            .metadata()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+
+    let request_id = header(REQUEST_ID_HEADER)
+        .filter(|id| id.len() <= MAX_REQUEST_ID_LEN)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    AuditContext {
+        actor: Principal::from_request(request).map(|p| p.user_id),
+        request_id: Some(request_id),
+        rpc: Some(rpc.to_string()),
+        reason: header(AUDIT_REASON_HEADER),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_context_should_come_from_request() {
+        let mut request = Request::new(());
+        request.extensions_mut().insert(Principal::new("john"));
+        request
+            .metadata_mut()
+            .insert(REQUEST_ID_HEADER, "req-1".parse().unwrap());
+        request
+            .metadata_mut()
+            .insert(AUDIT_REASON_HEADER, "customer called".parse().unwrap());
+
+        assert_eq!(
+            audit_from_request(&request, "cancel"),
+            AuditContext {
+                actor: Some("john".to_string()),
+                request_id: Some("req-1".to_string()),
+                rpc: Some("cancel".to_string()),
+                reason: Some("customer called".to_string()),
+            }
+        );
+
+        let audit = audit_from_request(&Request::new(()), "reserve");
+        assert_eq!(audit.actor, None);
+        assert_eq!(audit.reason, None);
+        assert!(audit.request_id.is_some());
+    }
+}
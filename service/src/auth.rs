@@ -0,0 +1,327 @@
+use std::{fs, sync::Arc};
+
+use abi::{AuthConfig, JwtAlgorithm, JwtConfig};
+use anyhow::{anyhow, Result};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use tonic::{service::Interceptor, Request, Status};
+
+/// the authenticated caller, attached to the request extensions by `Authenticator`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub user_id: String,
+    /// tenant the caller belongs to, if the identity names one
+    pub tenant: Option<String>,
+}
+
+impl Principal {
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            tenant: None,
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// the caller of the request, None if authentication is disabled
+    pub fn from_request<T>(request: &Request<T>) -> Option<Self> {
+        request.extensions().get::<Principal>().cloned()
+    }
+}
+
+/// claims we read from a bearer token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// validates bearer JWTs or client certificates and attaches the caller `Principal`
+#[derive(Clone, Default)]
+pub struct Authenticator {
+    // None if authentication is disabled
+    inner: Option<Arc<AuthenticatorInner>>,
+}
+
+struct AuthenticatorInner {
+    jwt: Option<(DecodingKey, Validation)>,
+    mtls: bool,
+}
+
+impl Authenticator {
+    pub fn from_config(config: Option<&AuthConfig>) -> Result<Self> {
+        let Some(config) = config else {
+            return Ok(Self::default());
+        };
+
+        let jwt = config.jwt.as_ref().map(jwt_decoder).transpose()?;
+
+        Ok(Self {
+            inner: Some(Arc::new(AuthenticatorInner {
+                jwt,
+                mtls: config.mtls,
+            })),
+        })
+    }
+
+    /// find out who the caller is, Ok(None) if authentication is disabled
+    pub fn authenticate<T>(&self, request: &Request<T>) -> Result<Option<Principal>, abi::Error> {
+        let Some(inner) = &self.inner else {
+            return Ok(None);
+        };
+
+        if let Some(token) = bearer_token(request)? {
+            let Some((key, validation)) = &inner.jwt else {
+                return Err(abi::Error::Unauthenticated(
+                    "bearer token is not accepted".to_string(),
+                ));
+            };
+
+            let data = jsonwebtoken::decode::<Claims>(token, key, validation)
+                .map_err(|e| abi::Error::Unauthenticated(format!("invalid token: {}", e)))?;
+
+            let principal = Principal {
+                user_id: data.claims.sub,
+                tenant: data.claims.tenant,
+            };
+            return Ok(Some(principal));
+        }
+
+        if inner.mtls {
+            if let Some(cert) = request
+                .peer_certs()
+                .and_then(|certs| certs.first().cloned())
+            {
+                return principal_from_cert(cert.as_ref()).map(Some);
+            }
+        }
+
+        Err(abi::Error::Unauthenticated(
+            "missing credentials".to_string(),
+        ))
+    }
+}
+
+impl Interceptor for Authenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(principal) = self.authenticate(&request)? {
+            request.extensions_mut().insert(principal);
+        }
+
+        Ok(request)
+    }
+}
+
+fn jwt_decoder(config: &JwtConfig) -> Result<(DecodingKey, Validation)> {
+    let (algorithm, key) = match config.algorithm {
+        JwtAlgorithm::HS256 => {
+            let secret = config
+                .secret
+                .as_ref()
+                .ok_or_else(|| anyhow!("jwt secret is required for HS256"))?;
+            (
+                Algorithm::HS256,
+                DecodingKey::from_secret(secret.as_bytes()),
+            )
+        }
+        JwtAlgorithm::RS256 => {
+            let path = config
+                .public_key
+                .as_ref()
+                .ok_or_else(|| anyhow!("jwt public key is required for RS256"))?;
+            let pem = fs::read(path)?;
+            (Algorithm::RS256, DecodingKey::from_rsa_pem(&pem)?)
+        }
+    };
+
+    let mut validation = Validation::new(algorithm);
+    validation.validate_aud = config.audience.is_some();
+    if let Some(audience) = &config.audience {
+        validation.set_audience(&[audience]);
+    }
+    if let Some(issuer) = &config.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+
+    Ok((key, validation))
+}
+
+fn bearer_token<T>(request: &Request<T>) -> Result<Option<&str>, abi::Error> {
+    let Some(value) = request.metadata().get("authorization") else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|token| Some(token.trim()))
+        .ok_or_else(|| abi::Error::Unauthenticated("malformed authorization header".to_string()))
+}
+
+/// the common name of the certificate subject is the user id, the organization the tenant
+fn principal_from_cert(der: &[u8]) -> Result<Principal, abi::Error> {
+    let invalid = || abi::Error::Unauthenticated("invalid client certificate".to_string());
+
+    let (_, cert) = x509_parser::parse_x509_certificate(der).map_err(|_| invalid())?;
+    let cn = cert
+        .subject()
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .ok_or_else(invalid)?;
+    let tenant = cert
+        .subject()
+        .iter_organization()
+        .next()
+        .and_then(|o| o.as_str().ok());
+
+    Ok(Principal {
+        user_id: cn.to_string(),
+        tenant: tenant.map(|t| t.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{EncodingKey, Header};
+
+    use super::*;
+
+    fn hs256_config() -> AuthConfig {
+        AuthConfig {
+            jwt: Some(JwtConfig {
+                algorithm: JwtAlgorithm::HS256,
+                secret: Some("reservation-secret".to_string()),
+                public_key: None,
+                issuer: None,
+                audience: None,
+            }),
+            mtls: false,
+            policy: Default::default(),
+        }
+    }
+
+    fn claims(sub: &str) -> Claims {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        Claims {
+            sub: sub.to_string(),
+            exp: now.as_secs() + 3600,
+            tenant: None,
+        }
+    }
+
+    fn request_with_token(token: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        request
+    }
+
+    #[test]
+    fn disabled_authenticator_should_allow_anonymous() {
+        let auth = Authenticator::from_config(None).unwrap();
+
+        assert_eq!(auth.authenticate(&Request::new(())), Ok(None));
+    }
+
+    #[test]
+    fn hs256_token_should_authenticate() {
+        let auth = Authenticator::from_config(Some(&hs256_config())).unwrap();
+        let mut claims = claims("john");
+        claims.tenant = Some("acme".to_string());
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"reservation-secret"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            auth.authenticate(&request_with_token(&token)),
+            Ok(Some(Principal::new("john").with_tenant("acme")))
+        );
+    }
+
+    #[test]
+    fn invalid_token_should_reject() {
+        let auth = Authenticator::from_config(Some(&hs256_config())).unwrap();
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims("john"),
+            &EncodingKey::from_secret(b"another-secret"),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            auth.authenticate(&request_with_token(&token)),
+            Err(abi::Error::Unauthenticated(_))
+        ));
+        assert_eq!(
+            auth.authenticate(&Request::new(())),
+            Err(abi::Error::Unauthenticated(
+                "missing credentials".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rs256_token_should_authenticate() {
+        let config = AuthConfig {
+            jwt: Some(JwtConfig {
+                algorithm: JwtAlgorithm::RS256,
+                secret: None,
+                public_key: Some("fixtures/jwt_rsa.pub.pem".to_string()),
+                issuer: Some("reservation".to_string()),
+                audience: None,
+            }),
+            mtls: false,
+            policy: Default::default(),
+        };
+        let auth = Authenticator::from_config(Some(&config)).unwrap();
+
+        #[derive(Serialize)]
+        struct IssuedClaims {
+            #[serde(flatten)]
+            claims: Claims,
+            iss: String,
+        }
+
+        let key = EncodingKey::from_rsa_pem(&fs::read("fixtures/jwt_rsa.pem").unwrap()).unwrap();
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &IssuedClaims {
+                claims: claims("lei"),
+                iss: "reservation".to_string(),
+            },
+            &key,
+        )
+        .unwrap();
+
+        assert_eq!(
+            auth.authenticate(&request_with_token(&token)),
+            Ok(Some(Principal::new("lei")))
+        );
+    }
+
+    #[test]
+    fn client_cert_common_name_should_be_user_id() {
+        let pem = fs::read("fixtures/tls/client.pem").unwrap();
+        let (_, pem) = x509_parser::pem::parse_x509_pem(&pem).unwrap();
+
+        assert_eq!(
+            principal_from_cert(&pem.contents),
+            Ok(Principal::new("john"))
+        );
+    }
+}
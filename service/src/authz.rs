@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+
+use abi::{Error, PolicyConfig, Reservation, ReservationConflictInfo};
+
+use crate::Principal;
+
+/// what a caller wants to do with a reservation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Reserve,
+    Confirm,
+    Update,
+    Cancel,
+    Read,
+    /// approve or reject a reservation of a resource requiring approval
+    Approve,
+    /// change how the reservations of the resource are confirmed
+    ManageResource,
+    /// read who changed the reservations, when and why
+    Audit,
+    /// get the changes of the reservations POSTed to a webhook
+    ManageWebhook,
+}
+
+/// decides what an authenticated caller may do, consulted by `RsvpService` for every rpc
+pub trait Authorizer: Send + Sync + 'static {
+    /// Err(Error::PermissionDenied) if the principal may not perform the action on the reservation
+    fn authorize(
+        &self,
+        principal: &Principal,
+        action: Action,
+        rsvp: &Reservation,
+    ) -> Result<(), Error>;
+
+    /// the part of the reservation the principal may see, None to hide it completely.
+    /// By default, a reservation the principal may not read only shows the resource and time
+    fn redact(&self, principal: &Principal, mut rsvp: Reservation) -> Option<Reservation> {
+        if self.authorize(principal, Action::Read, &rsvp).is_err() {
+            rsvp.user_id = String::new();
+            rsvp.note = String::new();
+            rsvp.labels.clear();
+            rsvp.attributes = None;
+        }
+
+        Some(rsvp)
+    }
+
+    /// hide the holders of the conflicting reservations the principal may not read
+    fn redact_conflicts(&self, principal: &Principal, info: &mut ReservationConflictInfo) {
+        for conflict in info.conflicts.iter_mut() {
+            let rsvp = Reservation {
+                id: conflict.id,
+                user_id: conflict.user_id.clone().unwrap_or_default(),
+                resource_id: conflict.window.rid.clone(),
+                ..Default::default()
+            };
+
+            if self.authorize(principal, Action::Read, &rsvp).is_err() {
+                conflict.user_id = None;
+            }
+        }
+    }
+}
+
+/// role based policy: owners manage their own reservations, resource managers see, confirm
+/// and reject the reservations of their resources, admins may do anything
+#[derive(Debug, Clone, Default)]
+pub struct RoleAuthorizer {
+    admins: HashSet<String>,
+    // resource id -> managers
+    managers: HashMap<String, HashSet<String>>,
+}
+
+impl RoleAuthorizer {
+    pub fn new(policy: &PolicyConfig) -> Self {
+        Self {
+            admins: policy.admins.iter().cloned().collect(),
+            managers: policy
+                .resource_managers
+                .iter()
+                .map(|(rid, users)| (rid.clone(), users.iter().cloned().collect()))
+                .collect(),
+        }
+    }
+
+    fn is_admin(&self, principal: &Principal) -> bool {
+        self.admins.contains(&principal.user_id)
+    }
+
+    fn is_manager(&self, principal: &Principal, resource_id: &str) -> bool {
+        self.managers
+            .get(resource_id)
+            .is_some_and(|users| users.contains(&principal.user_id))
+    }
+}
+
+impl Authorizer for RoleAuthorizer {
+    fn authorize(
+        &self,
+        principal: &Principal,
+        action: Action,
+        rsvp: &Reservation,
+    ) -> Result<(), Error> {
+        if self.is_admin(principal) {
+            return Ok(());
+        }
+
+        let owner = rsvp.user_id == principal.user_id;
+        let manager = self.is_manager(principal, &rsvp.resource_id);
+
+        let allowed = match action {
+            Action::Reserve | Action::Update => owner,
+            Action::Confirm | Action::Approve | Action::ManageResource => manager,
+            Action::Cancel | Action::Read | Action::Audit | Action::ManageWebhook => {
+                owner || manager
+            }
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "{} can not {:?} reservation {}",
+                principal.user_id, action, rsvp.id
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorizer() -> RoleAuthorizer {
+        RoleAuthorizer::new(&PolicyConfig {
+            admins: vec!["alice".to_string()],
+            resource_managers: HashMap::from([("room_01".to_string(), vec!["bob".to_string()])]),
+        })
+    }
+
+    fn rsvp() -> Reservation {
+        Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        )
+    }
+
+    #[test]
+    fn role_authorizer_should_check_roles() {
+        let authz = authorizer();
+        let rsvp = rsvp();
+        let (john, bob, alice, lei) = (
+            Principal::new("john"),
+            Principal::new("bob"),
+            Principal::new("alice"),
+            Principal::new("lei"),
+        );
+
+        assert!(authz.authorize(&john, Action::Update, &rsvp).is_ok());
+        assert!(authz.authorize(&john, Action::Cancel, &rsvp).is_ok());
+        assert!(authz.authorize(&john, Action::Confirm, &rsvp).is_err());
+
+        assert!(authz.authorize(&bob, Action::Confirm, &rsvp).is_ok());
+        assert!(authz.authorize(&bob, Action::Cancel, &rsvp).is_ok());
+        assert!(authz.authorize(&bob, Action::Update, &rsvp).is_err());
+        assert!(authz.authorize(&bob, Action::ManageResource, &rsvp).is_ok());
+        assert!(authz
+            .authorize(&john, Action::ManageResource, &rsvp)
+            .is_err());
+
+        assert!(authz.authorize(&alice, Action::Update, &rsvp).is_ok());
+        assert!(authz.authorize(&alice, Action::Confirm, &rsvp).is_ok());
+
+        assert!(authz.authorize(&lei, Action::Read, &rsvp).is_err());
+        assert!(authz.authorize(&lei, Action::Cancel, &rsvp).is_err());
+        assert!(authz.authorize(&bob, Action::Audit, &rsvp).is_ok());
+        assert!(authz.authorize(&lei, Action::Audit, &rsvp).is_err());
+    }
+
+    #[test]
+    fn redact_should_hide_owner_and_note() {
+        let authz = authorizer();
+
+        let visible = authz.redact(&Principal::new("bob"), rsvp()).unwrap();
+        assert_eq!(visible, rsvp());
+
+        let redacted = authz.redact(&Principal::new("lei"), rsvp()).unwrap();
+        assert_eq!(redacted.user_id, "");
+        assert_eq!(redacted.note, "");
+        assert_eq!(redacted.resource_id, "room_01");
+        assert_eq!(redacted.start, rsvp().start);
+    }
+}
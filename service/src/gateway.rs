@@ -0,0 +1,369 @@
+use std::{collections::HashMap, sync::Arc};
+
+use abi::{
+    reservation_service_server::ReservationService, CancelRequest, ConfirmRequest, Error,
+    FilterRequest, GetRequest, IcalFeedRequest, QueryRequest, Reservation, ReservationFilter,
+    ReservationQuery, ReservationStatus, ReserveRequest, StatusDetails, UpdateRequest,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tonic::{metadata::MetadataMap, Code, Extensions, Request, Status};
+
+use crate::{Authenticator, RsvpService};
+
+/// the fields of a reservation a PATCH may change
+const PATCH_FIELDS: [&str; 2] = ["note", "status"];
+
+/// REST/JSON gateway. It maps the routes onto the rpcs of the service, so HTTP requests go
+/// through the same authentication, tenancy, policy and audit as gRPC ones
+#[derive(Clone)]
+pub struct Gateway {
+    svc: Arc<RsvpService>,
+    authenticator: Authenticator,
+}
+
+/// a failed request, its body is derived from the error details the rpc failed with
+#[derive(Debug)]
+pub struct ApiError(Box<Status>);
+
+impl Gateway {
+    pub fn new(svc: Arc<RsvpService>, authenticator: Authenticator) -> Self {
+        Self { svc, authenticator }
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/reservations", get(list).post(reserve))
+            .route("/reservations.ics", get(ical_feed))
+            .route(
+                "/reservations/:id",
+                get(get_reservation).patch(update).delete(cancel),
+            )
+            .with_state(self)
+    }
+
+    /// the rpc request of an HTTP one: the headers become its metadata (authorization,
+    /// tenant, request id, audit reason) and the caller is authenticated like gRPC callers
+    fn request<T>(&self, headers: &HeaderMap, message: T) -> Result<Request<T>, ApiError> {
+        let metadata = MetadataMap::from_headers(headers.clone());
+        let mut request = Request::from_parts(metadata, Extensions::default(), message);
+
+        if let Some(principal) = self.authenticator.authenticate(&request)? {
+            request.extensions_mut().insert(principal);
+        }
+
+        Ok(request)
+    }
+}
+
+/// POST /reservations, `?validateOnly=true` checks the reservation without making it
+async fn reserve(
+    State(gw): State<Gateway>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    let mut rsvp = Reservation::from_proto_json(parse_body(&body)?)?;
+    if rsvp.status == ReservationStatus::Unknown as i32 {
+        rsvp.status = ReservationStatus::Pending as i32;
+    }
+    let validate_only = params
+        .get("validateOnly")
+        .or_else(|| params.get("validate_only"))
+        .map(|v| v.parse::<bool>())
+        .transpose()
+        .map_err(|_| Error::InvalidJson("validateOnly is not a boolean".to_string()))?
+        .unwrap_or_default();
+
+    let request = ReserveRequest {
+        reservation: Some(rsvp),
+        validate_only,
+    };
+    let response = gw.svc.reserve(gw.request(&headers, request)?).await?;
+    let response = response.into_inner();
+
+    if validate_only {
+        let body = json!({
+            "reservation": response.reservation.as_ref().map(Reservation::to_proto_json),
+            "dryRun": response.dry_run.unwrap_or_default().to_proto_json(),
+        });
+        return Ok(Json(body).into_response());
+    }
+
+    let rsvp = response.reservation.unwrap_or_default();
+    Ok((StatusCode::CREATED, Json(rsvp.to_proto_json())).into_response())
+}
+
+async fn get_reservation(
+    State(gw): State<Gateway>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    let request = gw.request(&headers, GetRequest::new(parse_id(&id)?))?;
+    let rsvp = gw.svc.get(request).await?.into_inner().reservation;
+
+    Ok(Json(rsvp.unwrap_or_default().to_proto_json()))
+}
+
+/// PATCH /reservations/{id}, changes the note and/or confirms the reservation
+async fn update(
+    State(gw): State<Gateway>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<Value>, ApiError> {
+    let id = parse_id(&id)?;
+    let body = parse_body(&body)?;
+    let fields: Vec<String> = match &body {
+        Value::Object(fields) => fields.keys().cloned().collect(),
+        _ => vec![],
+    };
+    if let Some(field) = fields.iter().find(|f| !PATCH_FIELDS.contains(&f.as_str())) {
+        return Err(Error::InvalidJson(format!("field {} can't be changed", field)).into());
+    }
+    let patch = Reservation::from_proto_json(body)?;
+
+    let mut rsvp = None;
+    if fields.iter().any(|f| f == "note") {
+        let request = UpdateRequest {
+            id,
+            note: patch.note,
+        };
+        let response = gw.svc.update(gw.request(&headers, request)?).await?;
+        rsvp = response.into_inner().reservation;
+    }
+    if fields.iter().any(|f| f == "status") {
+        if patch.status != ReservationStatus::Confirmed as i32 {
+            return Err(Error::InvalidStatus(patch.status).into());
+        }
+        let request = gw.request(&headers, ConfirmRequest::new(id))?;
+        rsvp = gw.svc.confirm(request).await?.into_inner().reservation;
+    }
+
+    let rsvp = match rsvp {
+        Some(rsvp) => rsvp,
+        None => {
+            let request = gw.request(&headers, GetRequest::new(id))?;
+            gw.svc
+                .get(request)
+                .await?
+                .into_inner()
+                .reservation
+                .unwrap_or_default()
+        }
+    };
+
+    Ok(Json(rsvp.to_proto_json()))
+}
+
+async fn cancel(
+    State(gw): State<Gateway>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    let request = gw.request(&headers, CancelRequest::new(parse_id(&id)?))?;
+    let rsvp = gw.svc.cancel(request).await?.into_inner().reservation;
+
+    Ok(Json(rsvp.unwrap_or_default().to_proto_json()))
+}
+
+/// GET /reservations, the parameters are the fields of a filter paged by cursor, or of a
+/// query paged by number if they have a time window (start or end). Like the builders,
+/// they list the pending reservations unless a status is given
+async fn list(
+    State(gw): State<Gateway>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    let is_query = ["start", "end"].iter().any(|p| params.contains_key(*p));
+    let params = json!(params);
+
+    if is_query {
+        let query = with_query_defaults(ReservationQuery::from_proto_json(params)?);
+        let request = gw.request(&headers, QueryRequest::new(query))?;
+        let mut stream = gw.svc.query(request).await?.into_inner();
+
+        let mut rsvps = Vec::new();
+        while let Some(rsvp) = stream.next().await {
+            rsvps.push(rsvp?.to_proto_json());
+        }
+        return Ok(Json(json!({ "reservations": rsvps })));
+    }
+
+    let filter = with_filter_defaults(ReservationFilter::from_proto_json(params)?);
+    let request = gw.request(&headers, FilterRequest::new(filter))?;
+    let response = gw.svc.filter(request).await?.into_inner();
+    let rsvps: Vec<_> = response
+        .reservations
+        .iter()
+        .map(Reservation::to_proto_json)
+        .collect();
+
+    Ok(Json(json!({
+        "reservations": rsvps,
+        "pager": response.pager.unwrap_or_default().to_proto_json(),
+    })))
+}
+
+/// GET /reservations.ics, the parameters are the fields of the query of the feed
+async fn ical_feed(
+    State(gw): State<Gateway>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let query = ReservationQuery::from_proto_json(json!(params))?;
+    let request = gw.request(&headers, IcalFeedRequest::new(query))?;
+    let ical = gw.svc.ical_feed(request).await?.into_inner().ical;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ical,
+    )
+        .into_response())
+}
+
+/// the defaults of `ReservationQueryBuilder` for the fields the parameters left out
+fn with_query_defaults(query: ReservationQuery) -> ReservationQuery {
+    ReservationQuery {
+        status: default_status(query.status),
+        page: query.page.max(1),
+        page_size: default_page_size(query.page_size),
+        ..query
+    }
+}
+
+/// the defaults of `ReservationFilterBuilder` for the fields the parameters left out
+fn with_filter_defaults(filter: ReservationFilter) -> ReservationFilter {
+    ReservationFilter {
+        status: default_status(filter.status),
+        page_size: default_page_size(filter.page_size),
+        ..filter
+    }
+}
+
+fn default_status(status: i32) -> i32 {
+    match status == ReservationStatus::Unknown as i32 {
+        true => ReservationStatus::Pending as i32,
+        false => status,
+    }
+}
+
+fn default_page_size(page_size: i32) -> i32 {
+    match page_size {
+        0 => 10,
+        page_size => page_size,
+    }
+}
+
+fn parse_body(body: &str) -> Result<Value, Error> {
+    serde_json::from_str(body).map_err(|e| Error::InvalidJson(e.to_string()))
+}
+
+/// ids that aren't numbers don't name any reservation
+fn parse_id(id: &str) -> Result<i64, Error> {
+    id.parse().map_err(|_| Error::NotFound)
+}
+
+/// the HTTP status of a gRPC code and the name of the code, as in the google.rpc mapping
+fn http_status(code: Code) -> (StatusCode, &'static str) {
+    match code {
+        Code::Ok => (StatusCode::OK, "OK"),
+        Code::Cancelled => (StatusCode::REQUEST_TIMEOUT, "CANCELLED"),
+        Code::Unknown => (StatusCode::INTERNAL_SERVER_ERROR, "UNKNOWN"),
+        Code::InvalidArgument => (StatusCode::BAD_REQUEST, "INVALID_ARGUMENT"),
+        Code::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, "DEADLINE_EXCEEDED"),
+        Code::NotFound => (StatusCode::NOT_FOUND, "NOT_FOUND"),
+        Code::AlreadyExists => (StatusCode::CONFLICT, "ALREADY_EXISTS"),
+        Code::PermissionDenied => (StatusCode::FORBIDDEN, "PERMISSION_DENIED"),
+        Code::ResourceExhausted => (StatusCode::TOO_MANY_REQUESTS, "RESOURCE_EXHAUSTED"),
+        Code::FailedPrecondition => (StatusCode::BAD_REQUEST, "FAILED_PRECONDITION"),
+        Code::Aborted => (StatusCode::CONFLICT, "ABORTED"),
+        Code::OutOfRange => (StatusCode::BAD_REQUEST, "OUT_OF_RANGE"),
+        Code::Unimplemented => (StatusCode::NOT_IMPLEMENTED, "UNIMPLEMENTED"),
+        Code::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL"),
+        Code::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE"),
+        Code::DataLoss => (StatusCode::INTERNAL_SERVER_ERROR, "DATA_LOSS"),
+        Code::Unauthenticated => (StatusCode::UNAUTHORIZED, "UNAUTHENTICATED"),
+    }
+}
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> Self {
+        Self(Box::new(status))
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        Self(Box::new(e.into()))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (code, status) = http_status(self.0.code());
+        let details = StatusDetails::from_status(&self.0);
+
+        let body = json!({
+            "error": {
+                "code": code.as_u16(),
+                "status": status,
+                "reason": details.reason().unwrap_or(status),
+                "message": self.0.message(),
+                "fields": details
+                    .field_violations
+                    .iter()
+                    .map(|violation| violation.field.as_str())
+                    .collect::<Vec<_>>(),
+                "conflicts": details
+                    .conflicts
+                    .iter()
+                    .map(|conflict| conflict.to_proto_json())
+                    .collect::<Vec<_>>(),
+            }
+        });
+
+        (code, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(response: Response) -> Value {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn errors_should_have_consistent_bodies() {
+        let response = ApiError::from(Error::InvalidUserId("".to_string())).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body(response).await,
+            json!({
+                "error": {
+                    "code": 400,
+                    "status": "INVALID_ARGUMENT",
+                    "reason": "INVALID_USER_ID",
+                    "message": "Invalid user id",
+                    "fields": ["user_id"],
+                    "conflicts": [],
+                }
+            })
+        );
+
+        // statuses without details fall back to their code
+        let response = ApiError::from(Status::unavailable("down")).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body(response).await["error"]["reason"], "UNAVAILABLE");
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use abi::reservation_service_server::ReservationServiceServer;
+use reservation::ReservationManager;
+use tokio::time;
+use tonic::server::NamedService;
+use tonic_health::{server::HealthReporter, ServingStatus};
+
+use crate::RsvpService;
+
+/// how long a check waits for the database before reporting it's not serving
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+/// how often the database is checked for a change of the status
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// reports the status of the server ("") and the reservation service to the grpc.health.v1
+/// service: they are serving as long as the pool gets a connection and the database has every
+/// migration of this build
+pub struct HealthChecker {
+    manager: ReservationManager,
+    reporter: HealthReporter,
+}
+
+impl HealthChecker {
+    pub fn new(manager: ReservationManager, reporter: HealthReporter) -> Self {
+        Self { manager, reporter }
+    }
+
+    /// check until the task is dropped
+    pub async fn run(mut self) {
+        loop {
+            self.check_once().await;
+            time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// check the database once and report the status, true if serving
+    pub async fn check_once(&mut self) -> bool {
+        let serving = matches!(
+            time::timeout(CHECK_TIMEOUT, self.manager.pending_migrations()).await,
+            Ok(Ok(pending)) if pending.is_empty()
+        );
+
+        let status = match serving {
+            true => ServingStatus::Serving,
+            false => ServingStatus::NotServing,
+        };
+        for service in ["", ReservationServiceServer::<RsvpService>::NAME] {
+            self.reporter.set_service_status(service, status).await;
+        }
+
+        serving
+    }
+}
@@ -1,27 +1,80 @@
-use abi::{Config, ListenResponse, Reservation};
+use abi::{Config, ExportChunk, ListenResponse, Reservation};
 use anyhow::Error;
+use axum_server::tls_rustls::RustlsConfig;
 use futures::stream::Stream;
 use reservation::ReservationManager;
-use std::{net::SocketAddr, pin::Pin, task::Poll};
+use std::{fs, net::SocketAddr, pin::Pin, sync::Arc, task::Poll};
 use tokio::sync::mpsc;
-use tonic::Status;
+use tonic::{
+    service::interceptor::InterceptedService,
+    transport::{Certificate, Identity, ServerTlsConfig},
+    Status,
+};
+use tonic_web::GrpcWebLayer;
+use tower::util::option_layer;
 
+mod audit;
+mod auth;
+mod authz;
+mod gateway;
+mod health;
+mod reminder;
 mod service;
+mod tenant;
+mod web;
+mod webhook;
 
+pub use audit::{audit_from_request, AUDIT_REASON_HEADER, REQUEST_ID_HEADER};
+pub use auth::{Authenticator, Claims, Principal};
+pub use authz::{Action, Authorizer, RoleAuthorizer};
+pub use gateway::{ApiError, Gateway};
+pub use health::HealthChecker;
+pub use reminder::{
+    notifier_from_config, reminder_payload, FileNotifier, LogNotifier, Notifier, ReminderScheduler,
+    SmtpNotifier, WebhookNotifier,
+};
+pub use service::LISTEN_SEQUENCE_HEADER;
+pub use tenant::{tenant_from_request, TENANT_HEADER};
+pub use web::cors_layer;
+pub use webhook::{sign, AddressPolicy, WebhookWorker, SEQUENCE_HEADER, SIGNATURE_HEADER};
+
+// lets the test helpers name the crate the same way in unit and integration tests
+#[cfg(test)]
+extern crate self as reservation_service;
 #[cfg(test)]
 pub mod test_util;
 
 pub type ReservationStream = Pin<Box<dyn Stream<Item = Result<Reservation, Status>> + Send>>;
 pub type ListenStream = Pin<Box<dyn Stream<Item = Result<ListenResponse, Status>> + Send>>;
+pub type ExportStream = Pin<Box<dyn Stream<Item = Result<ExportChunk, Status>> + Send>>;
 
 pub struct RsvpService {
     pub manager: ReservationManager,
+    pub authorizer: Arc<dyn Authorizer>,
+    /// where the webhooks may be delivered
+    pub webhook_policy: AddressPolicy,
 }
 
 impl RsvpService {
     pub async fn from_config(config: &Config) -> Result<Self, Error> {
         let manager = ReservationManager::from_config(&config.db).await?;
-        Ok(Self { manager })
+        let policy = config
+            .auth
+            .as_ref()
+            .map(|auth| auth.policy.clone())
+            .unwrap_or_default();
+
+        Ok(Self {
+            manager,
+            authorizer: Arc::new(RoleAuthorizer::new(&policy)),
+            webhook_policy: AddressPolicy::new(&config.webhook),
+        })
+    }
+
+    /// replace the role based policy from the config
+    pub fn with_authorizer(mut self, authorizer: impl Authorizer) -> Self {
+        self.authorizer = Arc::new(authorizer);
+        self
     }
 }
 
@@ -54,14 +107,88 @@ impl<T> Stream for TonicReceiverStream<T> {
 pub async fn start_server(config: &Config) -> Result<(), Error> {
     let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port).parse()?;
 
-    let svc = RsvpService::from_config(config).await?;
-    let svc = abi::reservation_service_server::ReservationServiceServer::new(svc);
+    let authenticator = Authenticator::from_config(config.auth.as_ref())?;
+    let svc = Arc::new(RsvpService::from_config(config).await?);
+    let worker = WebhookWorker::new(svc.manager.clone(), config.webhook.clone())?;
+    tokio::spawn(worker.run());
+    let scheduler = ReminderScheduler::from_config(svc.manager.clone(), config.reminder.clone())?;
+    tokio::spawn(scheduler.run());
+    let gateway = Gateway::new(svc.clone(), authenticator.clone());
+    let (reporter, health) = tonic_health::server::health_reporter();
+    tokio::spawn(HealthChecker::new(svc.manager.clone(), reporter).run());
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(abi::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()?;
+    let svc = InterceptedService::new(
+        abi::reservation_service_server::ReservationServiceServer::from_arc(svc),
+        authenticator,
+    );
 
-    println!("Listening on {}", addr);
+    let cors = cors_layer(&config.server.cors_origins)?;
+    let grpc_web = config.server.grpc_web.then(GrpcWebLayer::new);
+
+    // gRPC-Web clients speak HTTP/1.1
+    let mut builder = tonic::transport::Server::builder().accept_http1(config.server.grpc_web);
+    if let Some(tls) = &config.server.tls {
+        builder = builder.tls_config(server_tls_config(config, tls)?)?;
+    }
 
-    tonic::transport::Server::builder()
+    println!("Listening on {}", addr);
+    let router = builder
+        .layer(option_layer(cors.clone()))
+        .layer(option_layer(grpc_web))
         .add_service(svc)
-        .serve(addr)
-        .await?;
+        // unauthenticated, for load balancers and tools like grpcurl
+        .add_service(health)
+        .add_service(reflection);
+    let grpc = async { router.serve(addr).await.map_err(Error::from) };
+
+    match config.server.http_port {
+        Some(port) => {
+            let http_addr: SocketAddr = format!("{}:{}", config.server.host, port).parse()?;
+            println!("Serving REST on {}", http_addr);
+            let router = match cors {
+                Some(cors) => gateway.router().layer(cors),
+                None => gateway.router(),
+            };
+            // the REST gateway is served with the identity of the gRPC server, the callers
+            // authenticate with a token
+            let http = async {
+                match &config.server.tls {
+                    Some(tls) => {
+                        let tls_config = RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?;
+                        axum_server::bind_rustls(http_addr, tls_config)
+                            .serve(router.into_make_service())
+                            .await?
+                    }
+                    None => {
+                        axum::Server::bind(&http_addr)
+                            .serve(router.into_make_service())
+                            .await?
+                    }
+                }
+                Ok::<_, Error>(())
+            };
+            tokio::try_join!(grpc, http)?;
+        }
+        None => grpc.await?,
+    }
+
     Ok(())
 }
+
+fn server_tls_config(config: &Config, tls: &abi::TlsConfig) -> Result<ServerTlsConfig, Error> {
+    let identity = Identity::from_pem(fs::read(&tls.cert)?, fs::read(&tls.key)?);
+    let mut tls_config = ServerTlsConfig::new().identity(identity);
+
+    if let Some(ca) = &tls.client_ca {
+        // callers with a bearer token don't need a client certificate
+        let jwt_enabled = config.auth.as_ref().is_some_and(|auth| auth.jwt.is_some());
+        tls_config = tls_config
+            .client_ca_root(Certificate::from_pem(fs::read(ca)?))
+            .client_auth_optional(jwt_enabled);
+    }
+
+    Ok(tls_config)
+}
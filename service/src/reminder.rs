@@ -0,0 +1,458 @@
+use std::{sync::Arc, time::Duration};
+
+use abi::{NotifierConfig, ReminderConfig};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures::future;
+use lettre::{message::Mailbox, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use reservation::{Reminder, ReservationManager};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+
+use crate::{sign, SIGNATURE_HEADER};
+
+/// reminders sent at once per tenant
+const REMINDER_BATCH: i64 = 100;
+/// timeout of the requests of the notifiers
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// sends the reminders to the users, implement it to send them anywhere else
+#[tonic::async_trait]
+pub trait Notifier: Send + Sync + 'static {
+    async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error>;
+}
+
+/// the notifier of the config
+pub fn notifier_from_config(config: &NotifierConfig) -> Result<Arc<dyn Notifier>, anyhow::Error> {
+    Ok(match config {
+        NotifierConfig::Log => Arc::new(LogNotifier),
+        NotifierConfig::Webhook { url, secret } => Arc::new(WebhookNotifier::new(url, secret)?),
+        NotifierConfig::File { path } => Arc::new(FileNotifier::new(path)),
+        NotifierConfig::Smtp {
+            host,
+            port,
+            from,
+            domain,
+            insecure,
+        } => Arc::new(SmtpNotifier::new(host, *port, from, domain, *insecure)?),
+    })
+}
+
+/// the reminder as sent by the webhook and file notifiers
+pub fn reminder_payload(reminder: &Reminder) -> serde_json::Value {
+    json!({
+        "type": "reservation.reminder",
+        "tenant_id": reminder.tenant_id,
+        "reservation_id": reminder.reservation_id,
+        "user_id": reminder.user_id,
+        "resource_id": reminder.resource_id,
+        "start": reminder.start.to_rfc3339(),
+        "remind_at": reminder.remind_at.to_rfc3339(),
+        "offset_min": reminder.offset_min,
+    })
+}
+
+fn reminder_text(reminder: &Reminder) -> String {
+    format!(
+        "Your reservation {} of {} starts at {}",
+        reminder.reservation_id,
+        reminder.resource_id,
+        reminder.start.to_rfc3339()
+    )
+}
+
+/// prints the reminders
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogNotifier;
+
+#[tonic::async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error> {
+        println!(
+            "reminder for {}: {}",
+            reminder.user_id,
+            reminder_text(reminder)
+        );
+        Ok(())
+    }
+}
+
+/// POSTs the reminders as JSON, signed with the secret like the webhook deliveries
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(NOTIFY_TIMEOUT).build()?,
+            url: url.into(),
+            secret: secret.into(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error> {
+        let body = reminder_payload(reminder).to_string();
+        let signature = sign(&self.secret, Utc::now().timestamp(), &body);
+
+        self.client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// appends the reminders to a file, one JSON object per line
+#[derive(Debug, Clone)]
+pub struct FileNotifier {
+    path: String,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[tonic::async_trait]
+impl Notifier for FileNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error> {
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        // a single write, so lines of concurrent reminders don't interleave
+        file.write_all(format!("{}\n", reminder_payload(reminder)).as_bytes())
+            .await?;
+        // tokio writes in the background, the write is lost if the file is dropped before
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// mails the reminders to the users, user ids without a domain are mailed at the given one.
+/// The connection must be upgraded with STARTTLS unless it's insecure
+#[derive(Clone)]
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    domain: String,
+}
+
+impl SmtpNotifier {
+    pub fn new(
+        host: &str,
+        port: u16,
+        from: &str,
+        domain: &str,
+        insecure: bool,
+    ) -> Result<Self, anyhow::Error> {
+        let builder = match insecure {
+            true => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            false => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        };
+        let transport = builder.port(port).timeout(Some(NOTIFY_TIMEOUT)).build();
+
+        Ok(Self {
+            transport,
+            from: from.parse()?,
+            domain: domain.to_string(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Notifier for SmtpNotifier {
+    async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error> {
+        let to = match reminder.user_id.contains('@') {
+            true => reminder.user_id.clone(),
+            false => format!("{}@{}", reminder.user_id, self.domain),
+        };
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to
+                .parse()
+                .with_context(|| format!("invalid address {}", to))?)
+            .subject(format!("Reminder: {}", reminder.resource_id))
+            .body(reminder_text(reminder))?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+/// schedules the reminders of the reservations as they change and sends the due ones
+#[derive(Clone)]
+pub struct ReminderScheduler {
+    manager: ReservationManager,
+    notifier: Arc<dyn Notifier>,
+    config: ReminderConfig,
+}
+
+impl ReminderScheduler {
+    pub fn new(
+        manager: ReservationManager,
+        config: ReminderConfig,
+        notifier: Arc<dyn Notifier>,
+    ) -> Self {
+        Self {
+            manager,
+            notifier,
+            config,
+        }
+    }
+
+    /// a scheduler sending the reminders through the notifier of the config
+    pub fn from_config(
+        manager: ReservationManager,
+        config: ReminderConfig,
+    ) -> Result<Self, anyhow::Error> {
+        let notifier = notifier_from_config(&config.notifier)?;
+        Ok(Self::new(manager, config, notifier))
+    }
+
+    /// schedule and send until the task is dropped
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                eprintln!("reminder scheduling failed: {}", e);
+            }
+
+            tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+    }
+
+    /// schedule the reminders of the changed reservations and attempt the due ones of every
+    /// tenant once. Returns the number of attempts made
+    pub async fn run_once(&self) -> Result<usize, abi::Error> {
+        // a claimed reminder is attempted again if the scheduler dies before it is recorded
+        let lease = NOTIFY_TIMEOUT * 2;
+        let mut attempted = 0;
+
+        for tenant in self.manager.reminder_tenants().await? {
+            let manager = self.manager.with_tenant(tenant);
+            manager.schedule_reminders(&self.config).await?;
+
+            let reminders = manager.claim_reminders(REMINDER_BATCH, lease).await?;
+            let results = future::join_all(reminders.iter().map(|r| self.notifier.notify(r))).await;
+            attempted += reminders.len();
+
+            for (reminder, result) in reminders.iter().zip(results) {
+                match result {
+                    Ok(()) => manager.complete_reminder(reminder.id).await?,
+                    Err(e) => {
+                        let retry_at = self.retry_at(reminder.attempts);
+                        manager
+                            .fail_reminder(reminder.id, &e.to_string(), retry_at)
+                            .await?
+                    }
+                }
+            }
+        }
+
+        Ok(attempted)
+    }
+
+    /// when to try again after the given number of attempts, None to give up
+    fn retry_at(&self, attempts: i32) -> Option<DateTime<Utc>> {
+        (attempts < self.config.max_attempts).then(|| {
+            Utc::now() + chrono::Duration::milliseconds(self.config.retry_interval_ms as i64)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use abi::Reservation;
+    use reservation::Rsvp;
+    use tokio::{
+        io::{AsyncBufReadExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{test_util::TestConfig, RsvpService};
+
+    fn reminder(user_id: &str) -> Reminder {
+        Reminder {
+            id: 1,
+            tenant_id: "default".to_string(),
+            reservation_id: 42,
+            user_id: user_id.to_string(),
+            resource_id: "ocean-view-room-713".to_string(),
+            start: "2022-12-26T15:00:00Z".parse().unwrap(),
+            offset_min: 15,
+            remind_at: "2022-12-26T14:45:00Z".parse().unwrap(),
+            attempts: 1,
+            sent_at: None,
+        }
+    }
+
+    /// accepts a single mail and returns its data
+    async fn smtp_stand_in(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let reply = match line.to_uppercase().as_str() {
+                "." if in_data => {
+                    in_data = false;
+                    "250 queued"
+                }
+                _ if in_data => {
+                    data.push_str(&line);
+                    data.push('\n');
+                    continue;
+                }
+                "DATA" => {
+                    in_data = true;
+                    "354 end data with <CR><LF>.<CR><LF>"
+                }
+                "QUIT" => break,
+                _ => "250 OK",
+            };
+            writer
+                .write_all(format!("{}\r\n", reply).as_bytes())
+                .await
+                .unwrap();
+        }
+        writer.write_all(b"221 bye\r\n").await.unwrap();
+
+        data
+    }
+
+    /// fails the first reminder it is given
+    #[derive(Default)]
+    struct FlakyNotifier(Mutex<Vec<Reminder>>);
+
+    #[tonic::async_trait]
+    impl Notifier for FlakyNotifier {
+        async fn notify(&self, reminder: &Reminder) -> Result<(), anyhow::Error> {
+            let mut received = self.0.lock().unwrap();
+            received.push(reminder.clone());
+            match received.len() {
+                1 => Err(anyhow::anyhow!("connection refused")),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn smtp_notifier_should_mail_the_user() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(smtp_stand_in(listener));
+
+        let notifier =
+            SmtpNotifier::new("127.0.0.1", port, "rsvp@example.com", "example.com", true).unwrap();
+        notifier.notify(&reminder("john")).await.unwrap();
+
+        let data = server.await.unwrap();
+        assert!(data.contains("To: john@example.com"));
+        assert!(data.contains("From: rsvp@example.com"));
+        assert!(data.contains("Subject: Reminder: ocean-view-room-713"));
+        assert!(data.contains("Your reservation 42 of ocean-view-room-713 starts at"));
+    }
+
+    #[tokio::test]
+    async fn smtp_notifier_should_require_starttls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(smtp_stand_in(listener));
+
+        // the stand-in doesn't offer STARTTLS, nothing is sent in the clear
+        let notifier =
+            SmtpNotifier::new("127.0.0.1", port, "rsvp@example.com", "example.com", false).unwrap();
+        assert!(notifier.notify(&reminder("john")).await.is_err());
+
+        assert_eq!(server.await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn file_notifier_should_append_json_lines() {
+        let path = std::env::temp_dir().join(format!("reminders-{}.ndjson", uuid::Uuid::new_v4()));
+        let notifier = FileNotifier::new(path.to_str().unwrap());
+
+        notifier.notify(&reminder("john")).await.unwrap();
+        notifier.notify(&reminder("lei")).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "reservation.reminder");
+        assert_eq!(lines[0]["user_id"], "john");
+        assert_eq!(lines[1]["user_id"], "lei");
+        assert_eq!(lines[1]["start"], "2022-12-26T15:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn scheduler_should_retry_due_reminders_until_sent() {
+        let config = TestConfig::new();
+        let manager = RsvpService::from_config(&config).await.unwrap().manager;
+        let notifier = Arc::new(FlakyNotifier::default());
+        let scheduler = ReminderScheduler::new(
+            manager.clone(),
+            ReminderConfig {
+                offsets_min: vec![1],
+                retry_interval_ms: 0,
+                ..Default::default()
+            },
+            notifier.clone(),
+        );
+
+        // reminded a minute before it starts, two seconds from now
+        let start = Utc::now() + chrono::Duration::seconds(62);
+        let rsvp = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean-view-room-713",
+                start.into(),
+                (start + chrono::Duration::hours(1)).into(),
+                "",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(scheduler.run_once().await.unwrap(), 0);
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+
+        // failed, then retried right away
+        assert_eq!(scheduler.run_once().await.unwrap(), 1);
+        assert_eq!(scheduler.run_once().await.unwrap(), 1);
+        assert_eq!(scheduler.run_once().await.unwrap(), 0);
+
+        let received = notifier.0.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].reservation_id, rsvp.id);
+        assert_eq!(received[1].attempts, 2);
+
+        let reminders = manager.reminders(rsvp.id).await.unwrap();
+        assert_eq!(reminders.len(), 1);
+        assert!(reminders[0].sent_at.is_some());
+    }
+}
@@ -1,13 +1,210 @@
-use reservation::Rsvp;
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::{future, stream, StreamExt};
+use reservation::{ReservationManager, Rsvp};
 
 use abi::{
-    reservation_service_server::ReservationService, CancelRequest, CancelResponse, ConfirmRequest,
-    ConfirmResponse, FilterRequest, FilterResponse, GetRequest, GetResponse, ListenRequest,
-    QueryRequest, ReserveRequest, ReserveResponse, UpdateRequest, UpdateResponse,
+    reservation_service_server::ReservationService, ApprovalQueueRequest, ApprovalQueueResponse,
+    ApproveRequest, ApproveResponse, AuditLogRequest, AuditLogResponse, BulkFormat, CancelRequest,
+    CancelResponse, ConfirmRequest, ConfirmResponse, ConflictPolicy, CreateWebhookRequest,
+    CreateWebhookResponse, DeleteWebhookRequest, DeleteWebhookResponse, DryRunResult, Error,
+    ExportChunk, ExportRequest, FilterRequest, FilterResponse, GetHistoryRequest,
+    GetHistoryResponse, GetRequest, GetResourceRequest, GetResourceResponse, GetResponse,
+    IcalFeedRequest, IcalFeedResponse, IcalImportOutcome, IcalImportRequest, IcalImportResponse,
+    ImportError, ImportRequest, ImportResponse, ListDeadLettersRequest, ListDeadLettersResponse,
+    ListWebhooksRequest, ListWebhooksResponse, ListenRequest, QueryRequest, RejectRequest,
+    RejectResponse, Reservation, ReservationId, ReservationStatus, ReserveRequest, ReserveResponse,
+    SearchRequest, SearchResponse, SetResourceRequest, SetResourceResponse, UpdateRequest,
+    UpdateResponse, Validator, Webhook,
 };
 use tonic::{Request, Response, Status};
 
-use crate::{ListenStream, ReservationStream, RsvpService, TonicReceiverStream};
+use crate::{
+    audit_from_request, tenant_from_request, Action, Authorizer, ExportStream, ListenStream,
+    Principal, ReservationStream, RsvpService, TonicReceiverStream,
+};
+
+/// the response header of listen telling the sequence number the stream starts after, a client
+/// reconnecting resumes from there until it receives a change
+pub const LISTEN_SEQUENCE_HEADER: &str = "x-listen-sequence";
+/// the most reservations a feed holds
+const ICAL_FEED_LIMIT: usize = 10_000;
+/// reservations sent in a chunk of an export
+const EXPORT_CHUNK: usize = 100;
+
+impl RsvpService {
+    /// the manager confined to the tenant of the request, auditing its changes as made by
+    /// the caller through the rpc
+    fn tenant_manager<T>(
+        &self,
+        request: &Request<T>,
+        rpc: &str,
+    ) -> Result<ReservationManager, Error> {
+        let manager = match tenant_from_request(request)? {
+            Some(tenant) => self.manager.with_tenant(tenant),
+            None => self.manager.clone(),
+        };
+
+        Ok(manager.with_audit(audit_from_request(request, rpc)))
+    }
+
+    /// make sure the caller may act on the reservation, anonymous callers are not checked
+    async fn authorize(
+        &self,
+        manager: &ReservationManager,
+        principal: Option<&Principal>,
+        action: Action,
+        id: ReservationId,
+    ) -> Result<(), Error> {
+        if let Some(principal) = principal {
+            let rsvp = manager.get(id).await?;
+            self.authorizer.authorize(principal, action, &rsvp)?;
+        }
+
+        Ok(())
+    }
+
+    /// reserving with another status than pending confirms (or blocks) the reservation at once,
+    /// so the caller must be allowed to confirm it too
+    fn authorize_reserve(&self, principal: &Principal, rsvp: &Reservation) -> Result<(), Error> {
+        self.authorizer
+            .authorize(principal, Action::Reserve, rsvp)?;
+
+        match ReservationStatus::try_from(rsvp.status) {
+            Ok(ReservationStatus::Pending | ReservationStatus::Unknown) => Ok(()),
+            _ => self.authorizer.authorize(principal, Action::Confirm, rsvp),
+        }
+    }
+
+    /// the part of the reservation the caller may see
+    fn redact(&self, principal: Option<&Principal>, rsvp: Reservation) -> Option<Reservation> {
+        match principal {
+            Some(principal) => self.authorizer.redact(principal, rsvp),
+            None => Some(rsvp),
+        }
+    }
+
+    /// the holder, labels and note of the reservations the caller may not read are redacted, so
+    /// they can't be matched either: another holder is refused and labels or words of the note
+    /// only match the caller's own reservations, unless they may read those of the resource
+    fn scope(
+        &self,
+        principal: Option<&Principal>,
+        resource_id: &str,
+        user_id: &mut String,
+        private: bool,
+    ) -> Result<(), Error> {
+        let Some(principal) = principal else {
+            return Ok(());
+        };
+
+        let rsvp = Reservation {
+            user_id: user_id.clone(),
+            resource_id: resource_id.to_string(),
+            ..Default::default()
+        };
+        if !user_id.is_empty() {
+            return self.authorizer.authorize(principal, Action::Read, &rsvp);
+        }
+
+        if private
+            && self
+                .authorizer
+                .authorize(principal, Action::Read, &rsvp)
+                .is_err()
+        {
+            *user_id = principal.user_id.clone();
+        }
+
+        Ok(())
+    }
+
+    /// only the approvers of the resource, or whoever the policy lets approve, decide
+    async fn authorize_decision(
+        &self,
+        manager: &ReservationManager,
+        principal: Option<&Principal>,
+        approver: &str,
+        id: ReservationId,
+    ) -> Result<(), Error> {
+        let rsvp = manager.get(id).await?;
+        let resource = manager.get_resource(&rsvp.resource_id).await?;
+
+        if resource.approvers.iter().any(|a| a == approver) {
+            return Ok(());
+        }
+
+        match principal {
+            Some(principal) => self.authorizer.authorize(principal, Action::Approve, &rsvp),
+            None => Err(Error::PermissionDenied(format!(
+                "{} is not an approver of {}",
+                approver, rsvp.resource_id
+            ))),
+        }
+    }
+
+    /// webhooks get the changes of the reservations of a user or a resource, only those allowed
+    /// to read them may manage the webhook. A webhook of every reservation is for admins
+    fn authorize_webhook(
+        &self,
+        principal: Option<&Principal>,
+        webhook: &Webhook,
+    ) -> Result<(), Error> {
+        let Some(principal) = principal else {
+            return Ok(());
+        };
+
+        let rsvp = Reservation {
+            user_id: webhook.user_id.clone(),
+            resource_id: webhook.resource_id.clone(),
+            ..Default::default()
+        };
+        self.authorizer
+            .authorize(principal, Action::ManageWebhook, &rsvp)
+    }
+
+    /// don't leak who holds the conflicting reservations
+    fn redact_error(&self, principal: Option<&Principal>, mut e: Error) -> Error {
+        if let (Some(principal), Error::ConflictReservation(info)) = (principal, &mut e) {
+            self.authorizer.redact_conflicts(principal, info);
+        }
+
+        e
+    }
+}
+
+/// webhook secrets are write only
+fn hide_secret(webhook: Webhook) -> Webhook {
+    Webhook {
+        secret: String::new(),
+        ..webhook
+    }
+}
+
+/// the comment of a decision is the reason of the change, unless the caller gives another one
+fn with_comment(mut manager: ReservationManager, comment: &str) -> ReservationManager {
+    if manager.audit.reason.is_none() && !comment.is_empty() {
+        manager.audit.reason = Some(comment.to_string());
+    }
+
+    manager
+}
+
+/// authenticated callers act as themselves, the approver must be given otherwise
+fn approver(principal: Option<&Principal>, approver: String) -> Result<String, Error> {
+    match principal {
+        Some(principal) if approver.is_empty() || approver == principal.user_id => {
+            Ok(principal.user_id.clone())
+        }
+        Some(principal) => Err(Error::PermissionDenied(format!(
+            "{} can not act for {}",
+            principal.user_id, approver
+        ))),
+        None if approver.is_empty() => Err(Error::MissingField("approver".to_string())),
+        None => Ok(approver),
+    }
+}
 
 #[tonic::async_trait]
 impl ReservationService for RsvpService {
@@ -15,28 +212,61 @@ impl ReservationService for RsvpService {
         &self,
         request: Request<ReserveRequest>,
     ) -> std::result::Result<Response<ReserveResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "reserve")?;
+        let principal = principal.as_ref();
         let request = request.into_inner();
 
-        if request.reservation.is_none() {
-            return Err(Status::invalid_argument("reservation is required"));
+        let Some(mut reservation) = request.reservation else {
+            return Err(Error::MissingField("reservation".to_string()).into());
+        };
+
+        // authenticated callers reserve for themselves unless told otherwise
+        if let Some(principal) = principal {
+            if reservation.user_id.is_empty() {
+                reservation.user_id = principal.user_id.clone();
+            }
+            self.authorize_reserve(principal, &reservation)?;
+        }
+
+        if request.validate_only {
+            let (reservation, dry_run) = match manager.validate_reserve(reservation).await {
+                Ok(reservation) => (Some(reservation), DryRunResult::ok()),
+                Err(e @ (Error::DbError(_) | Error::Unknown)) => return Err(e.into()),
+                // the reservation would be rejected, report why instead of failing the rpc
+                Err(e) => (None, DryRunResult::from(&self.redact_error(principal, e))),
+            };
+
+            return Ok(Response::new(ReserveResponse {
+                reservation,
+                dry_run: Some(dry_run),
+            }));
         }
 
-        let reservation = self.manager.reserve(request.reservation.unwrap()).await?;
+        let reservation = manager
+            .reserve(reservation)
+            .await
+            .map_err(|e| self.redact_error(principal, e))?;
 
         Ok(Response::new(ReserveResponse {
             reservation: Some(reservation),
+            dry_run: None,
         }))
     }
     async fn confirm(
         &self,
         request: Request<ConfirmRequest>,
     ) -> std::result::Result<Response<ConfirmResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "confirm")?;
         let request = request.into_inner();
         if request.id == 0 {
-            return Err(Status::invalid_argument("id is required"));
+            return Err(Error::MissingField("id".to_string()).into());
         }
 
-        let reservation = self.manager.change_status(request.id).await?;
+        self.authorize(&manager, principal.as_ref(), Action::Confirm, request.id)
+            .await?;
+        let reservation = manager.change_status(request.id).await?;
 
         Ok(Response::new(ConfirmResponse {
             reservation: Some(reservation),
@@ -46,13 +276,17 @@ impl ReservationService for RsvpService {
         &self,
         request: Request<UpdateRequest>,
     ) -> std::result::Result<Response<UpdateResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "update")?;
         let request = request.into_inner();
 
         if request.id == 0 {
-            return Err(Status::invalid_argument("id is required"));
+            return Err(Error::MissingField("id".to_string()).into());
         }
 
-        let reservation = self.manager.update_note(request.id, request.note).await?;
+        self.authorize(&manager, principal.as_ref(), Action::Update, request.id)
+            .await?;
+        let reservation = manager.update_note(request.id, request.note).await?;
 
         Ok(Response::new(UpdateResponse {
             reservation: Some(reservation),
@@ -62,13 +296,17 @@ impl ReservationService for RsvpService {
         &self,
         request: Request<CancelRequest>,
     ) -> std::result::Result<Response<CancelResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "cancel")?;
         let request = request.into_inner();
 
         if request.id == 0 {
-            return Err(Status::invalid_argument("id is required"));
+            return Err(Error::MissingField("id".to_string()).into());
         }
 
-        let _ = self.manager.delete(request.id).await?;
+        self.authorize(&manager, principal.as_ref(), Action::Cancel, request.id)
+            .await?;
+        let _ = manager.delete(request.id).await?;
 
         Ok(Response::new(CancelResponse { reservation: None }))
     }
@@ -76,59 +314,645 @@ impl ReservationService for RsvpService {
         &self,
         request: Request<GetRequest>,
     ) -> std::result::Result<Response<GetResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "get")?;
         let request = request.into_inner();
 
         if request.id == 0 {
-            return Err(Status::invalid_argument("id is required"));
+            return Err(Error::MissingField("id".to_string()).into());
         }
 
-        let reservation = self.manager.get(request.id).await?;
+        let reservation = match request.as_of {
+            Some(as_of) => manager.get_as_of(request.id, as_of).await?,
+            None => manager.get(request.id).await?,
+        };
+        // a hidden reservation is indistinguishable from a missing one
+        let reservation = self
+            .redact(principal.as_ref(), reservation)
+            .ok_or(Error::NotFound)?;
 
         Ok(Response::new(GetResponse {
             reservation: Some(reservation),
         }))
     }
+    async fn get_history(
+        &self,
+        request: Request<GetHistoryRequest>,
+    ) -> std::result::Result<Response<GetHistoryResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "get_history")?;
+        let request = request.into_inner();
+
+        if request.id == 0 {
+            return Err(Error::MissingField("id".to_string()).into());
+        }
+
+        let versions = manager
+            .get_history(request.id)
+            .await?
+            .into_iter()
+            .map(|mut version| {
+                let rsvp = version.reservation.take()?;
+                version.reservation = Some(self.redact(principal.as_ref(), rsvp)?);
+                Some(version)
+            })
+            .collect::<Option<Vec<_>>>()
+            // a hidden reservation is indistinguishable from a missing one
+            .ok_or(Error::NotFound)?;
+
+        Ok(Response::new(GetHistoryResponse { versions }))
+    }
     /// Server streaming response type for the query method.
     type queryStream = ReservationStream;
     async fn query(
         &self,
         request: Request<QueryRequest>,
     ) -> std::result::Result<Response<Self::queryStream>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "query")?;
         let request = request.into_inner();
-        if request.query.is_none() {
-            return Err(Status::invalid_argument("query is required"));
-        }
+        let Some(mut query) = request.query else {
+            return Err(Error::MissingField("query".to_string()).into());
+        };
+        let private = !query.label_selector.is_empty();
+        self.scope(
+            principal.as_ref(),
+            &query.resource_id,
+            &mut query.user_id,
+            private,
+        )?;
 
-        let rsvps = self.manager.query(request.query.unwrap()).await;
+        let rsvps = manager.query(query).await;
 
         let stream = TonicReceiverStream::new(rsvps);
+        let Some(principal) = principal else {
+            return Ok(Response::new(Box::pin(stream)));
+        };
+
+        let authorizer: Arc<dyn Authorizer> = self.authorizer.clone();
+        let stream = stream.filter_map(move |item| {
+            future::ready(match item {
+                Ok(rsvp) => authorizer.redact(&principal, rsvp).map(Ok),
+                Err(e) => Some(Err(e)),
+            })
+        });
         Ok(Response::new(Box::pin(stream)))
     }
     async fn filter(
         &self,
         request: Request<FilterRequest>,
     ) -> std::result::Result<Response<FilterResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "filter")?;
         let request = request.into_inner();
 
-        if request.filter.is_none() {
-            return Err(Status::invalid_argument("filter is required"));
-        }
+        let Some(mut filter) = request.filter else {
+            return Err(Error::MissingField("filter".to_string()).into());
+        };
+        let private = !filter.label_selector.is_empty();
+        self.scope(
+            principal.as_ref(),
+            &filter.resource_id,
+            &mut filter.user_id,
+            private,
+        )?;
 
-        let (pager, reservations) = self.manager.filter(request.filter.unwrap()).await?;
+        let (pager, reservations) = manager.filter(filter).await?;
+        let reservations = reservations
+            .into_iter()
+            .filter_map(|rsvp| self.redact(principal.as_ref(), rsvp))
+            .collect();
 
         Ok(Response::new(FilterResponse {
             pager: Some(pager),
             reservations,
         }))
     }
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> std::result::Result<Response<SearchResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "search")?;
+        let request = request.into_inner();
+
+        let Some(mut search) = request.search else {
+            return Err(Error::MissingField("search".to_string()).into());
+        };
+        let private = !search.query.is_empty() || !search.label_selector.is_empty();
+        self.scope(
+            principal.as_ref(),
+            &search.resource_id,
+            &mut search.user_id,
+            private,
+        )?;
+
+        let (pager, hits) = manager.search(search).await?;
+        let hits = match principal {
+            Some(principal) => hits
+                .into_iter()
+                .filter_map(|mut hit| {
+                    let rsvp = hit.reservation.take()?;
+                    // the snippet quotes the note
+                    if self
+                        .authorizer
+                        .authorize(&principal, Action::Read, &rsvp)
+                        .is_err()
+                    {
+                        hit.snippet.clear();
+                    }
+                    hit.reservation = Some(self.authorizer.redact(&principal, rsvp)?);
+                    Some(hit)
+                })
+                .collect(),
+            None => hits,
+        };
+
+        Ok(Response::new(SearchResponse {
+            hits,
+            pager: Some(pager),
+        }))
+    }
+    async fn set_resource(
+        &self,
+        request: Request<SetResourceRequest>,
+    ) -> std::result::Result<Response<SetResourceResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "set_resource")?;
+        let request = request.into_inner();
+
+        let Some(resource) = request.resource else {
+            return Err(Error::MissingField("resource".to_string()).into());
+        };
+
+        if let Some(principal) = principal {
+            let rsvp = Reservation {
+                resource_id: resource.id.clone(),
+                ..Default::default()
+            };
+            self.authorizer
+                .authorize(&principal, Action::ManageResource, &rsvp)?;
+        }
+
+        let resource = manager.set_resource(resource).await?;
+
+        Ok(Response::new(SetResourceResponse {
+            resource: Some(resource),
+        }))
+    }
+    async fn get_resource(
+        &self,
+        request: Request<GetResourceRequest>,
+    ) -> std::result::Result<Response<GetResourceResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "get_resource")?;
+        let request = request.into_inner();
+
+        if request.id.is_empty() {
+            return Err(Error::MissingField("id".to_string()).into());
+        }
+
+        // the approvers are for those managing the resource, like setting them
+        if let Some(principal) = principal {
+            let rsvp = Reservation {
+                resource_id: request.id.clone(),
+                ..Default::default()
+            };
+            self.authorizer
+                .authorize(&principal, Action::ManageResource, &rsvp)?;
+        }
+
+        let resource = manager.get_resource(&request.id).await?;
+
+        Ok(Response::new(GetResourceResponse {
+            resource: Some(resource),
+        }))
+    }
+    async fn approve(
+        &self,
+        request: Request<ApproveRequest>,
+    ) -> std::result::Result<Response<ApproveResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "approve")?;
+        let request = request.into_inner();
+        let manager = with_comment(manager, &request.comment);
+
+        if request.id == 0 {
+            return Err(Error::MissingField("id".to_string()).into());
+        }
+
+        let approver = approver(principal.as_ref(), request.approver)?;
+        self.authorize_decision(&manager, principal.as_ref(), &approver, request.id)
+            .await?;
+        let (reservation, approval) = manager
+            .approve(request.id, &approver, request.comment)
+            .await?;
+
+        Ok(Response::new(ApproveResponse {
+            reservation: Some(reservation),
+            approval: Some(approval),
+        }))
+    }
+    async fn reject(
+        &self,
+        request: Request<RejectRequest>,
+    ) -> std::result::Result<Response<RejectResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "reject")?;
+        let request = request.into_inner();
+        let manager = with_comment(manager, &request.comment);
+
+        if request.id == 0 {
+            return Err(Error::MissingField("id".to_string()).into());
+        }
+
+        let approver = approver(principal.as_ref(), request.approver)?;
+        self.authorize_decision(&manager, principal.as_ref(), &approver, request.id)
+            .await?;
+        let (reservation, approval) = manager
+            .reject(request.id, &approver, request.comment)
+            .await?;
+
+        Ok(Response::new(RejectResponse {
+            reservation: Some(reservation),
+            approval: Some(approval),
+        }))
+    }
+    async fn approval_queue(
+        &self,
+        request: Request<ApprovalQueueRequest>,
+    ) -> std::result::Result<Response<ApprovalQueueResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "approval_queue")?;
+        let request = request.into_inner();
+
+        let approver = approver(principal.as_ref(), request.approver)?;
+        let (reservations, next_cursor) = manager
+            .approval_queue(&approver, request.cursor, request.page_size)
+            .await?;
+
+        Ok(Response::new(ApprovalQueueResponse {
+            reservations,
+            next_cursor,
+        }))
+    }
+    async fn audit_log(
+        &self,
+        request: Request<AuditLogRequest>,
+    ) -> std::result::Result<Response<AuditLogResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "audit_log")?;
+        let request = request.into_inner();
+
+        let Some(query) = request.query else {
+            return Err(Error::MissingField("query".to_string()).into());
+        };
+
+        if let Some(principal) = principal {
+            let mut rsvp = Reservation {
+                id: query.reservation_id,
+                user_id: query.user_id.clone(),
+                resource_id: query.resource_id.clone(),
+                ..Default::default()
+            };
+            // the log of a single reservation is seen like the reservation, as long as it exists
+            if query.reservation_id > 0 && rsvp.user_id.is_empty() && rsvp.resource_id.is_empty() {
+                match manager.get(query.reservation_id).await {
+                    Ok(found) => rsvp = found,
+                    Err(Error::NotFound) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.authorizer
+                .authorize(&principal, Action::Audit, &rsvp)?;
+        }
+
+        let (entries, next_cursor) = manager.audit_log(query).await?;
+
+        Ok(Response::new(AuditLogResponse {
+            entries,
+            next_cursor,
+        }))
+    }
+    async fn create_webhook(
+        &self,
+        request: Request<CreateWebhookRequest>,
+    ) -> std::result::Result<Response<CreateWebhookResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "create_webhook")?;
+        let request = request.into_inner();
+
+        let Some(webhook) = request.webhook else {
+            return Err(Error::MissingField("webhook".to_string()).into());
+        };
+
+        self.authorize_webhook(principal.as_ref(), &webhook)?;
+        self.webhook_policy.check_url(&webhook.url)?;
+        let webhook = manager.create_webhook(webhook).await?;
+
+        Ok(Response::new(CreateWebhookResponse {
+            webhook: Some(hide_secret(webhook)),
+        }))
+    }
+    async fn list_webhooks(
+        &self,
+        request: Request<ListWebhooksRequest>,
+    ) -> std::result::Result<Response<ListWebhooksResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "list_webhooks")?;
+
+        let webhooks = manager
+            .list_webhooks()
+            .await?
+            .into_iter()
+            .filter(|webhook| self.authorize_webhook(principal.as_ref(), webhook).is_ok())
+            .map(hide_secret)
+            .collect();
+
+        Ok(Response::new(ListWebhooksResponse { webhooks }))
+    }
+    async fn delete_webhook(
+        &self,
+        request: Request<DeleteWebhookRequest>,
+    ) -> std::result::Result<Response<DeleteWebhookResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "delete_webhook")?;
+        let request = request.into_inner();
+
+        let webhook = manager.get_webhook(request.id).await?;
+        self.authorize_webhook(principal.as_ref(), &webhook)?;
+        let webhook = manager.delete_webhook(request.id).await?;
+
+        Ok(Response::new(DeleteWebhookResponse {
+            webhook: Some(hide_secret(webhook)),
+        }))
+    }
+    async fn list_dead_letters(
+        &self,
+        request: Request<ListDeadLettersRequest>,
+    ) -> std::result::Result<Response<ListDeadLettersResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "list_dead_letters")?;
+        let request = request.into_inner();
+
+        // the dead letters of a webhook are seen like the webhook, those of all of them by admins
+        let webhook = match request.webhook_id {
+            0 => Webhook::default(),
+            id => manager.get_webhook(id).await?,
+        };
+        self.authorize_webhook(principal.as_ref(), &webhook)?;
+        let dead_letters = manager.dead_letters(request.webhook_id).await?;
+
+        Ok(Response::new(ListDeadLettersResponse { dead_letters }))
+    }
+    async fn ical_feed(
+        &self,
+        request: Request<IcalFeedRequest>,
+    ) -> std::result::Result<Response<IcalFeedResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "ical_feed")?;
+        let request = request.into_inner();
+
+        let Some(mut query) = request.query else {
+            return Err(Error::MissingField("query".to_string()).into());
+        };
+        if query.user_id.is_empty() && query.resource_id.is_empty() {
+            return Err(Error::MissingField("user_id".to_string()).into());
+        }
+        query.validate()?;
+        let private = !query.label_selector.is_empty();
+        self.scope(
+            principal.as_ref(),
+            &query.resource_id,
+            &mut query.user_id,
+            private,
+        )?;
+
+        // every status the calendar shows unless one is asked for, like an export
+        let mut rsvps = manager.export(query).await;
+        let mut reservations = Vec::new();
+        while let Some(rsvp) = rsvps.recv().await {
+            reservations.extend(self.redact(principal.as_ref(), rsvp?));
+            if reservations.len() == ICAL_FEED_LIMIT {
+                break;
+            }
+        }
+
+        Ok(Response::new(IcalFeedResponse {
+            ical: abi::to_ical(&reservations, Utc::now()),
+        }))
+    }
+    /// reserve the events of an iCalendar, every occurrence of the recurring ones. The import isn't
+    /// atomic: each event reserved is reported even if others fail, a batch the database failed to
+    /// commit fails the call but keeps the batches before, importing again reports them as
+    /// conflicts
+    async fn import_ical(
+        &self,
+        request: Request<IcalImportRequest>,
+    ) -> std::result::Result<Response<IcalImportResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "import_ical")?;
+        let principal = principal.as_ref();
+        let mut request = request.into_inner();
+
+        // authenticated callers import for themselves unless told otherwise
+        if let Some(principal) = principal {
+            if request.user_id.is_empty() {
+                request.user_id = principal.user_id.clone();
+            }
+        }
+
+        let mut results = request.plan(Utc::now())?;
+
+        let mut planned = Vec::new();
+        for (i, result) in results.iter_mut().enumerate() {
+            let Some(rsvp) = &result.reservation else {
+                continue;
+            };
+            let authorized = match principal {
+                Some(principal) => self.authorize_reserve(principal, rsvp),
+                None => Ok(()),
+            };
+            match authorized {
+                Ok(()) => planned.push((i, rsvp.clone())),
+                Err(e) => {
+                    result.outcome = IcalImportOutcome::Skipped as i32;
+                    result.reason = e.to_string();
+                }
+            }
+        }
+
+        let (indexes, rsvps): (Vec<_>, Vec<_>) = planned.into_iter().unzip();
+        let reserved = manager.reserve_all(rsvps, request.dry_run).await?;
+        for (i, ret) in indexes.into_iter().zip(reserved) {
+            let result = &mut results[i];
+            match ret {
+                Ok(rsvp) => {
+                    result.outcome = IcalImportOutcome::Created as i32;
+                    result.reservation = Some(rsvp);
+                }
+                Err(e) => {
+                    result.outcome = match e {
+                        Error::ConflictReservation(_) => IcalImportOutcome::Conflict,
+                        _ => IcalImportOutcome::Skipped,
+                    } as i32;
+                    result.reason = self.redact_error(principal, e).to_string();
+                }
+            }
+        }
+
+        Ok(Response::new(IcalImportResponse::new(results)))
+    }
+    /// Server streaming response type for the export_reservations method.
+    type export_reservationsStream = ExportStream;
+    /// stream the matching reservations as CSV or JSON Lines
+    async fn export_reservations(
+        &self,
+        request: Request<ExportRequest>,
+    ) -> std::result::Result<Response<Self::export_reservationsStream>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "export_reservations")?;
+        let request = request.into_inner();
+        request.validate()?;
+        let format = BulkFormat::try_from(request.format).unwrap_or(BulkFormat::Csv);
+
+        let mut query = request.query.unwrap_or_default();
+        let private = !query.label_selector.is_empty();
+        self.scope(
+            principal.as_ref(),
+            &query.resource_id,
+            &mut query.user_id,
+            private,
+        )?;
+
+        let rsvps = manager.export(query).await;
+
+        let authorizer: Arc<dyn Authorizer> = self.authorizer.clone();
+        let rsvps = TonicReceiverStream::new(rsvps).filter_map(move |item| {
+            future::ready(match (item, &principal) {
+                (Ok(rsvp), Some(principal)) => authorizer.redact(principal, rsvp).map(Ok),
+                (item, _) => Some(item),
+            })
+        });
+
+        let header = format.header();
+        let header = (!header.is_empty()).then_some(Ok(ExportChunk { data: header }));
+        let chunks = rsvps.chunks(EXPORT_CHUNK).then(move |items| {
+            let rsvps = items.into_iter().collect::<Result<Vec<_>, Status>>();
+            future::ready(rsvps.map(|rsvps| ExportChunk {
+                data: format.encode(&rsvps),
+            }))
+        });
+
+        Ok(Response::new(Box::pin(stream::iter(header).chain(chunks))))
+    }
+    /// import reservations in batches, reporting the rows that weren't
+    async fn import_reservations(
+        &self,
+        request: Request<ImportRequest>,
+    ) -> std::result::Result<Response<ImportResponse>, Status> {
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "import_reservations")?;
+        let principal = principal.as_ref();
+        let request = request.into_inner();
+        request.validate()?;
+        let format = BulkFormat::try_from(request.format).unwrap_or(BulkFormat::Csv);
+        let policy =
+            ConflictPolicy::try_from(request.on_conflict).unwrap_or(ConflictPolicy::Report);
+
+        let mut errors = Vec::new();
+        let mut rows = Vec::new();
+        for (line, rsvp) in format.decode(&request.data)? {
+            let rsvp = rsvp.and_then(|rsvp| match principal {
+                Some(principal) => self.authorize_reserve(principal, &rsvp).map(|_| rsvp),
+                None => Ok(rsvp),
+            });
+            match rsvp {
+                Ok(rsvp) => rows.push((line, rsvp)),
+                Err(e) => errors.push(ImportError::new(line, &e)),
+            }
+        }
+        let total = rows.len() + errors.len();
+
+        let aborts = policy == ConflictPolicy::Fail;
+        let (lines, rsvps): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        let results = match policy {
+            // nothing is imported, but the conflicts are still worth reporting
+            ConflictPolicy::Fail if !errors.is_empty() => manager.reserve_all(rsvps, true).await?,
+            ConflictPolicy::Fail => manager.reserve_all_or_none(rsvps).await?,
+            _ => manager.reserve_all(rsvps, false).await?,
+        };
+
+        let mut imported = 0;
+        let mut failed = !errors.is_empty();
+        for (line, ret) in lines.into_iter().zip(results) {
+            match ret {
+                Ok(_) => imported += 1,
+                Err(e @ (Error::DbError(_) | Error::Unknown)) => return Err(e.into()),
+                Err(Error::ConflictReservation(_)) if policy == ConflictPolicy::Skip => {}
+                Err(e) => {
+                    failed = true;
+                    errors.push(ImportError::new(line, &self.redact_error(principal, e)));
+                }
+            }
+        }
+        let aborted = aborts && failed;
+        if aborted {
+            imported = 0;
+        }
+        errors.sort_by_key(|e| e.line);
+
+        Ok(Response::new(ImportResponse {
+            imported,
+            skipped: (total - imported as usize) as i32,
+            errors,
+            aborted,
+        }))
+    }
     /// Server streaming response type for the listen method.
     type listenStream = ListenStream;
     /// another system can monitor the reservations and newly reserved/confirmed/canceled reservations
     async fn listen(
         &self,
-        _request: Request<ListenRequest>,
+        request: Request<ListenRequest>,
     ) -> std::result::Result<Response<Self::listenStream>, Status> {
-        todo!()
+        let principal = Principal::from_request(&request);
+        let manager = self.tenant_manager(&request, "listen")?;
+        let mut request = request.into_inner();
+
+        // the changes made from now on start after the last one, the stream reads again after it
+        let start = match request.after_sequence {
+            Some(seq) => seq,
+            None => manager.last_change().await?,
+        };
+        request.after_sequence = Some(start);
+
+        let changes = manager.listen(request).await;
+
+        let stream = TonicReceiverStream::new(changes);
+        let with_start = |stream: ListenStream| {
+            let mut response = Response::new(stream);
+            response
+                .metadata_mut()
+                .insert(LISTEN_SEQUENCE_HEADER, start.into());
+            response
+        };
+        let Some(principal) = principal else {
+            return Ok(with_start(Box::pin(stream)));
+        };
+
+        let authorizer: Arc<dyn Authorizer> = self.authorizer.clone();
+        let stream = stream.filter_map(move |item| {
+            future::ready(match item {
+                Ok(mut change) => {
+                    let rsvp = change.reservation.take();
+                    change.reservation = rsvp.and_then(|rsvp| authorizer.redact(&principal, rsvp));
+                    change.reservation.is_some().then_some(Ok(change))
+                }
+                Err(e) => Some(Err(e)),
+            })
+        });
+        Ok(with_start(Box::pin(stream)))
     }
 }
 
@@ -137,7 +961,7 @@ mod tests {
     use abi::{Reservation, ReservationStatus};
 
     use super::*;
-    use crate::test_util::TestConfig;
+    use crate::test_util::{with_principal, TestConfig};
 
     #[tokio::test]
     async fn rpc_reserve_should_work() {
@@ -151,9 +975,7 @@ mod tests {
             "2022-12-30T12:00:00-0700".parse().unwrap(),
             "I need this room for a meeting",
         );
-        let request = tonic::Request::new(ReserveRequest {
-            reservation: Some(reservation.clone()),
-        });
+        let request = tonic::Request::new(ReserveRequest::new(reservation.clone()));
         let response = service.reserve(request).await.unwrap();
         let reservation1 = response.into_inner().reservation;
 
@@ -170,47 +992,801 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn rpc_confirm_should_work() {
+    async fn rpc_listen_should_report_the_sequence_it_starts_after() {
         let config = TestConfig::new();
-
         let service = RsvpService::from_config(&config).await.unwrap();
-        let reservation = service
-            .manager
-            .reserve(Reservation::new_pending(
-                "john",
-                "room_01",
-                "2022-12-26T15:00:00-0700".parse().unwrap(),
-                "2022-12-30T12:00:00-0700".parse().unwrap(),
-                "I need this room for a meeting",
-            ))
-            .await
-            .unwrap();
 
-        let request = tonic::Request::new(ConfirmRequest { id: reservation.id });
-        let response = service.confirm(request).await.unwrap();
-        let reservation1 = response.into_inner().reservation;
+        let start = |response: &Response<ListenStream>| {
+            response
+                .metadata()
+                .get(LISTEN_SEQUENCE_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        let request = tonic::Request::new(ListenRequest::default());
+        assert_eq!(start(&service.listen(request).await.unwrap()), "0");
 
-        assert!(reservation1.is_some());
+        let reservation = Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "",
+        );
+        let request = tonic::Request::new(ReserveRequest::new(reservation));
+        service.reserve(request).await.unwrap();
 
-        let reservation1 = reservation1.unwrap();
+        // the change made before isn't sent, a reconnect resumes after it
+        let request = tonic::Request::new(ListenRequest::default());
+        assert_eq!(start(&service.listen(request).await.unwrap()), "1");
+        let request = tonic::Request::new(ListenRequest {
+            after_sequence: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(start(&service.listen(request).await.unwrap()), "5");
 
-        assert_eq!(reservation1.status, ReservationStatus::Confirmed as i32);
+        // 0 is the start of a tenant without changes, resuming from it sends them all
+        let request = tonic::Request::new(ListenRequest {
+            after_sequence: Some(0),
+            ..Default::default()
+        });
+        let response = service.listen(request).await.unwrap();
+        assert_eq!(start(&response), "0");
+        let change = response.into_inner().next().await.unwrap().unwrap();
+        assert_eq!(change.sequence, 1);
     }
 
     #[tokio::test]
-    async fn rpc_update_should_work() {
+    async fn rpc_reserve_should_use_principal() {
         let config = TestConfig::new();
 
         let service = RsvpService::from_config(&config).await.unwrap();
-        let reservation = service
-            .manager
-            .reserve(Reservation::new_pending(
-                "john",
+        let reservation = Reservation::new_pending(
+            "",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+
+        let mut request = tonic::Request::new(ReserveRequest::new(reservation.clone()));
+        request.extensions_mut().insert(Principal::new("john"));
+        let response = service.reserve(request).await.unwrap().into_inner();
+
+        assert_eq!(response.reservation.unwrap().user_id, "john");
+
+        // reserving for someone else is denied
+        let mut other = reservation;
+        other.user_id = "lei".to_string();
+        let mut request = tonic::Request::new(ReserveRequest::new(other));
+        request.extensions_mut().insert(Principal::new("john"));
+        let status = service.reserve(request).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn rpc_reserve_confirmed_should_need_confirm() {
+        let config = TestConfig::new();
+
+        let policy = abi::PolicyConfig {
+            resource_managers: [("room_01".to_string(), vec!["bob".to_string()])].into(),
+            ..Default::default()
+        };
+        let service = RsvpService::from_config(&config)
+            .await
+            .unwrap()
+            .with_authorizer(crate::RoleAuthorizer::new(&policy));
+
+        // a reservation of the given week, so they don't conflict
+        let reserve = |user_id: &str, status: ReservationStatus, week: i64| {
+            let mut rsvp = Reservation::new_pending(
+                user_id,
                 "room_01",
                 "2022-12-26T15:00:00-0700".parse().unwrap(),
                 "2022-12-30T12:00:00-0700".parse().unwrap(),
-                "I need this room for a meeting",
-            ))
+                "",
+            );
+            rsvp.start.as_mut().unwrap().seconds += week * 7 * 24 * 3600;
+            rsvp.end.as_mut().unwrap().seconds += week * 7 * 24 * 3600;
+            rsvp.status = status as i32;
+            let mut request = tonic::Request::new(ReserveRequest::new(rsvp));
+            request.extensions_mut().insert(Principal::new(user_id));
+            request
+        };
+
+        // the owner can't confirm on reserve, nor block the resource
+        for status in [ReservationStatus::Confirmed, ReservationStatus::Blocked] {
+            let status = service
+                .reserve(reserve("john", status, 0))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        }
+
+        let rsvp = service
+            .reserve(reserve("john", ReservationStatus::Pending, 0))
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Pending as i32);
+
+        // the resource manager may
+        let rsvp = service
+            .reserve(reserve("bob", ReservationStatus::Confirmed, 1))
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        assert_eq!(rsvp.status, ReservationStatus::Confirmed as i32);
+    }
+
+    #[tokio::test]
+    async fn rpc_should_enforce_policy() {
+        let config = TestConfig::new();
+
+        let policy = abi::PolicyConfig {
+            admins: vec!["alice".to_string()],
+            resource_managers: [("room_01".to_string(), vec!["bob".to_string()])].into(),
+        };
+        let service = RsvpService::from_config(&config)
+            .await
+            .unwrap()
+            .with_authorizer(crate::RoleAuthorizer::new(&policy));
+
+        let reservation = Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+        let request = with_principal("john", ReserveRequest::new(reservation.clone()));
+        let rsvp = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+
+        // others can't touch it, and only see when the resource is taken
+        let request = with_principal("lei", CancelRequest { id: rsvp.id });
+        let status = service.cancel(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal("lei", GetRequest::new(rsvp.id));
+        let redacted = service
+            .get(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        assert_eq!(redacted.user_id, "");
+        assert_eq!(redacted.note, "");
+        assert_eq!(redacted.start, rsvp.start);
+
+        // nor who holds a conflicting reservation
+        let mut conflict = reservation.clone();
+        conflict.user_id = "lei".to_string();
+        let request = with_principal("lei", ReserveRequest::new_validate_only(conflict));
+        let dry_run = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .dry_run
+            .unwrap();
+        assert_eq!(dry_run.conflicts[0].id, rsvp.id);
+        assert_eq!(dry_run.conflicts[0].user_id, "");
+
+        // nor find it by its holder or its note
+        let query = abi::ReservationQueryBuilder::default()
+            .user_id("john")
+            .build()
+            .unwrap();
+        let request = with_principal("lei", QueryRequest::new(query));
+        let status = service.query(request).await.err().unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let search = abi::ReservationSearchBuilder::default()
+            .query("meeting")
+            .build()
+            .unwrap();
+        let request = with_principal("lei", SearchRequest::new(search.clone()));
+        let res = service.search(request).await.unwrap().into_inner();
+        assert!(res.hits.is_empty());
+
+        let request = with_principal("bob", SearchRequest::new(search.clone()));
+        let res = service.search(request).await.unwrap().into_inner();
+        assert!(res.hits.is_empty());
+
+        let mut search = search;
+        search.resource_id = "room_01".to_string();
+        let request = with_principal("bob", SearchRequest::new(search));
+        let res = service.search(request).await.unwrap().into_inner();
+        assert_eq!(res.hits[0].reservation, Some(rsvp.clone()));
+
+        // the owner can't confirm, the resource manager can
+        let request = with_principal("john", ConfirmRequest::new(rsvp.id));
+        let status = service.confirm(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal("bob", ConfirmRequest::new(rsvp.id));
+        let confirmed = service
+            .confirm(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        assert_eq!(confirmed.status, ReservationStatus::Confirmed as i32);
+
+        // admins can do anything
+        let request = with_principal("alice", CancelRequest { id: rsvp.id });
+        service.cancel(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rpc_should_isolate_tenants() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+
+        fn with_tenant<T>(tenant: &str, msg: T) -> tonic::Request<T> {
+            let mut request = tonic::Request::new(msg);
+            request
+                .metadata_mut()
+                .insert(crate::TENANT_HEADER, tenant.parse().unwrap());
+            request
+        }
+
+        // the same room of two tenants can be reserved at the same time
+        let mut ids = vec![];
+        for tenant in ["acme", "globex"] {
+            let request = with_tenant(tenant, ReserveRequest::new(reservation.clone()));
+            let rsvp = service
+                .reserve(request)
+                .await
+                .unwrap()
+                .into_inner()
+                .reservation
+                .unwrap();
+            assert_eq!(rsvp.tenant_id, tenant);
+            ids.push(rsvp.id);
+        }
+
+        let request = with_tenant("acme", GetRequest::new(ids[1]));
+        let status = service.get(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        // neither is in the default tenant
+        let request = tonic::Request::new(GetRequest::new(ids[0]));
+        let status = service.get(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn rpc_approval_workflow_should_work() {
+        let config = TestConfig::new();
+
+        let policy = abi::PolicyConfig {
+            admins: vec!["alice".to_string()],
+            ..Default::default()
+        };
+        let service = RsvpService::from_config(&config)
+            .await
+            .unwrap()
+            .with_authorizer(crate::RoleAuthorizer::new(&policy));
+
+        // only admins and resource managers configure resources
+        let resource = abi::Resource::with_approvers("boardroom", &["carol"]);
+        let request = with_principal("john", SetResourceRequest::new(resource.clone()));
+        let status = service.set_resource(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal("alice", SetResourceRequest::new(resource));
+        service.set_resource(request).await.unwrap();
+
+        let request = with_principal(
+            "john",
+            GetResourceRequest {
+                id: "boardroom".to_string(),
+            },
+        );
+        let status = service.get_resource(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal(
+            "alice",
+            GetResourceRequest {
+                id: "boardroom".to_string(),
+            },
+        );
+        let resource = service.get_resource(request).await.unwrap().into_inner();
+        assert_eq!(resource.resource.unwrap().approvers, vec!["carol"]);
+
+        let reservation = Reservation::new_pending(
+            "john",
+            "boardroom",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+        let request = with_principal("john", ReserveRequest::new(reservation));
+        let rsvp = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+
+        let request = tonic::Request::new(ConfirmRequest::new(rsvp.id));
+        let status = service.confirm(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let approve = ApproveRequest {
+            id: rsvp.id,
+            approver: String::new(),
+            comment: "approved".to_string(),
+        };
+        let request = with_principal("john", approve.clone());
+        let status = service.approve(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal("carol", ApprovalQueueRequest::default());
+        let queue = service.approval_queue(request).await.unwrap().into_inner();
+        assert_eq!(queue.reservations, vec![rsvp.clone()]);
+
+        let request = with_principal("carol", approve);
+        let response = service.approve(request).await.unwrap().into_inner();
+        assert_eq!(
+            response.reservation.unwrap().status,
+            ReservationStatus::Confirmed as i32
+        );
+        assert_eq!(response.approval.unwrap().approver, "carol");
+    }
+
+    #[tokio::test]
+    async fn rpc_audit_log_should_record_the_caller() {
+        let config = TestConfig::new();
+        let service = RsvpService::from_config(&config).await.unwrap();
+
+        let reservation = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+        let mut request = with_principal("john", ReserveRequest::new(reservation));
+        request
+            .metadata_mut()
+            .insert(crate::REQUEST_ID_HEADER, "req-42".parse().unwrap());
+        let rsvp = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+
+        let mut request = with_principal("john", CancelRequest { id: rsvp.id });
+        request
+            .metadata_mut()
+            .insert(crate::AUDIT_REASON_HEADER, "plans changed".parse().unwrap());
+        service.cancel(request).await.unwrap();
+
+        let query = abi::AuditQuery {
+            reservation_id: rsvp.id,
+            user_id: "john".to_string(),
+            ..Default::default()
+        };
+        let request = with_principal("lei", AuditLogRequest::new(query.clone()));
+        let status = service.audit_log(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let request = with_principal("john", AuditLogRequest::new(query));
+        let response = service.audit_log(request).await.unwrap().into_inner();
+        assert_eq!(response.next_cursor, -1);
+        let [created, deleted] = &response.entries[..] else {
+            panic!("expect two audit entries");
+        };
+        assert_eq!(created.actor, "john");
+        assert_eq!(created.rpc, "reserve");
+        assert_eq!(created.request_id, "req-42");
+        assert_eq!(deleted.rpc, "cancel");
+        assert_eq!(deleted.reason, "plans changed");
+        assert_eq!(deleted.op, abi::ReservationType::Delete as i32);
+    }
+
+    #[tokio::test]
+    async fn rpc_webhooks_should_be_managed_by_those_reading_the_changes() {
+        let config = TestConfig::new();
+        let service = RsvpService::from_config(&config).await.unwrap();
+
+        let webhook = Webhook {
+            url: "https://example.com/hooks".to_string(),
+            user_id: "john".to_string(),
+            secret: "s3cret".to_string(),
+            ..Default::default()
+        };
+        let request = with_principal(
+            "lei",
+            CreateWebhookRequest {
+                webhook: Some(webhook.clone()),
+            },
+        );
+        let status = service.create_webhook(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        // nor be pointed at the internal network
+        let internal = Webhook {
+            url: "http://169.254.169.254/latest/meta-data".to_string(),
+            ..webhook.clone()
+        };
+        let request = with_principal(
+            "john",
+            CreateWebhookRequest {
+                webhook: Some(internal),
+            },
+        );
+        let status = service.create_webhook(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let request = with_principal(
+            "john",
+            CreateWebhookRequest {
+                webhook: Some(webhook),
+            },
+        );
+        let created = service
+            .create_webhook(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .webhook
+            .unwrap();
+        assert!(created.id > 0);
+        assert_eq!(created.secret, "");
+
+        let request = with_principal("lei", ListWebhooksRequest {});
+        let response = service.list_webhooks(request).await.unwrap().into_inner();
+        assert!(response.webhooks.is_empty());
+        let request = with_principal("john", ListWebhooksRequest {});
+        let response = service.list_webhooks(request).await.unwrap().into_inner();
+        assert_eq!(response.webhooks, vec![created.clone()]);
+
+        let request = with_principal("lei", ListDeadLettersRequest { webhook_id: 0 });
+        let status = service.list_dead_letters(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let request = with_principal(
+            "john",
+            ListDeadLettersRequest {
+                webhook_id: created.id,
+            },
+        );
+        let response = service
+            .list_dead_letters(request)
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.dead_letters.is_empty());
+
+        let request = with_principal("lei", DeleteWebhookRequest { id: created.id });
+        let status = service.delete_webhook(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let request = with_principal("john", DeleteWebhookRequest { id: created.id });
+        service.delete_webhook(request).await.unwrap();
+        let request = with_principal("john", DeleteWebhookRequest { id: created.id });
+        let status = service.delete_webhook(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn rpc_ical_feed_should_work() {
+        let config = TestConfig::new();
+        let service = RsvpService::from_config(&config).await.unwrap();
+
+        let reservation = Reservation::new_pending(
+            "john",
+            "ocean-view-room-713",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+        let request = tonic::Request::new(ReserveRequest::new(reservation));
+        let rsvp = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+
+        let query = abi::ReservationQueryBuilder::default()
+            .resource_id("ocean-view-room-713")
+            .start(abi::convert_to_timestamp(
+                "2022-12-01T00:00:00Z".parse().unwrap(),
+            ))
+            .end(abi::convert_to_timestamp(
+                "2023-01-01T00:00:00Z".parse().unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        let request = with_principal("john", IcalFeedRequest::new(query.clone()));
+        let ical = service.ical_feed(request).await.unwrap().into_inner().ical;
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.contains(&format!("UID:{}\r\n", rsvp.ical_uid())));
+        assert!(ical.contains("DTSTART:20221226T220000Z\r\n"));
+
+        assert!(ical.contains("CONTACT:john\r\n"));
+
+        // the confirmed and blocked reservations are shown too unless a status is asked for
+        let confirmed = Reservation {
+            status: ReservationStatus::Confirmed as i32,
+            ..Reservation::new_pending(
+                "john",
+                "ocean-view-room-713",
+                "2022-12-30T15:00:00-0700".parse().unwrap(),
+                "2022-12-31T12:00:00-0700".parse().unwrap(),
+                "",
+            )
+        };
+        let request = tonic::Request::new(ReserveRequest::new(confirmed));
+        let confirmed = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .reservation
+            .unwrap();
+        let all = abi::ReservationQuery {
+            status: ReservationStatus::Unknown as i32,
+            ..query.clone()
+        };
+        let request = with_principal("john", IcalFeedRequest::new(all));
+        let ical = service.ical_feed(request).await.unwrap().into_inner().ical;
+        assert!(ical.contains(&format!("UID:{}\r\n", rsvp.ical_uid())));
+        assert!(ical.contains(&format!("UID:{}\r\n", confirmed.ical_uid())));
+        let request = with_principal("john", IcalFeedRequest::new(query.clone()));
+        let ical = service.ical_feed(request).await.unwrap().into_inner().ical;
+        assert!(!ical.contains(&format!("UID:{}\r\n", confirmed.ical_uid())));
+
+        // others only see the room is busy
+        let request = with_principal("lei", IcalFeedRequest::new(query.clone()));
+        let ical = service.ical_feed(request).await.unwrap().into_inner().ical;
+        assert!(ical.contains(&format!("UID:{}\r\n", rsvp.ical_uid())));
+        assert!(!ical.contains("CONTACT:john"));
+        assert!(!ical.contains("DESCRIPTION:"));
+
+        let query = abi::ReservationQuery {
+            resource_id: String::new(),
+            ..query
+        };
+        let request = with_principal("john", IcalFeedRequest::new(query));
+        let status = service.ical_feed(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn rpc_export_and_import_should_work() {
+        let config = TestConfig::new();
+        let service = RsvpService::from_config(&config).await.unwrap();
+
+        for (user_id, start, end) in [
+            ("john", "2024-01-01T09:00:00Z", "2024-01-01T10:00:00Z"),
+            ("lei", "2024-01-02T09:00:00Z", "2024-01-02T10:00:00Z"),
+        ] {
+            let rsvp = Reservation::new_pending(
+                user_id,
+                "ocean-view-room-713",
+                start.parse().unwrap(),
+                end.parse().unwrap(),
+                "",
+            );
+            let request = tonic::Request::new(ReserveRequest::new(rsvp));
+            service.reserve(request).await.unwrap();
+        }
+
+        let export = ExportRequest {
+            query: Some(abi::ReservationQuery {
+                resource_id: "ocean-view-room-713".to_string(),
+                ..Default::default()
+            }),
+            format: BulkFormat::Csv as i32,
+        };
+        let chunks = service
+            .export_reservations(tonic::Request::new(export))
+            .await
+            .unwrap()
+            .into_inner();
+        let data: Vec<_> = chunks.map(|chunk| chunk.unwrap().data).collect().await;
+        let data = data.concat();
+        assert_eq!(data.lines().count(), 3);
+        assert!(data.starts_with("id,tenant_id,resource_id,user_id,"));
+
+        let import = |on_conflict: ConflictPolicy, data: String| {
+            tonic::Request::new(ImportRequest {
+                format: BulkFormat::Csv as i32,
+                data,
+                on_conflict: on_conflict as i32,
+            })
+        };
+
+        // the exported reservations conflict with themselves
+        let response = service
+            .import_reservations(import(ConflictPolicy::Report, data.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.imported, response.skipped), (0, 2));
+        let lines: Vec<_> = response
+            .errors
+            .iter()
+            .map(|e| (e.line, e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(2, "RESERVATION_CONFLICT"), (3, "RESERVATION_CONFLICT")]
+        );
+
+        let response = service
+            .import_reservations(import(ConflictPolicy::Skip, data.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.skipped, response.errors.len()), (2, 0));
+
+        // a new row is imported with the others or not at all
+        let data = format!(
+            "{}0,,ocean-view-room-713,john,confirmed,2024-01-03T09:00:00Z,2024-01-03T10:00:00Z,,,\n",
+            data
+        );
+        let response = service
+            .import_reservations(import(ConflictPolicy::Fail, data.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.aborted);
+        assert_eq!((response.imported, response.skipped), (0, 3));
+
+        let response = service
+            .import_reservations(import(ConflictPolicy::Skip, data))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.aborted);
+        assert_eq!((response.imported, response.skipped), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn rpc_import_ical_should_work() {
+        let config = TestConfig::new();
+        let service = RsvpService::from_config(&config).await.unwrap();
+
+        let existing = Reservation::new_pending(
+            "lei",
+            "ocean-view-room-713",
+            "2024-01-15T09:00:00Z".parse().unwrap(),
+            "2024-01-15T10:00:00Z".parse().unwrap(),
+            "a secret meeting",
+        );
+        service
+            .reserve(tonic::Request::new(ReserveRequest::new(existing)))
+            .await
+            .unwrap();
+
+        // a weekly meeting, the third one conflicts with lei's reservation
+        let ical = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//test//EN\r\n\
+            BEGIN:VEVENT\r\nUID:weekly@example.com\r\nSUMMARY:Weekly\r\n\
+            DTSTART:20240101T090000Z\r\nDTEND:20240101T100000Z\r\n\
+            RRULE:FREQ=WEEKLY;COUNT=4\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let import = IcalImportRequest {
+            ical: ical.to_string(),
+            resource_id: "ocean-view-room-713".to_string(),
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let request = with_principal("john", import.clone());
+        let response = service.import_ical(request).await.unwrap().into_inner();
+        assert_eq!((response.created, response.conflicts), (3, 1));
+        let conflict = &response.results[2];
+        assert_eq!(conflict.outcome, IcalImportOutcome::Conflict as i32);
+        assert!(!conflict.reason.contains("a secret meeting"));
+        assert!(response
+            .results
+            .iter()
+            .filter_map(|r| r.reservation.as_ref())
+            .all(|r| r.user_id == "john"));
+
+        // nothing was reserved by the dry run
+        let request = with_principal("john", import.clone());
+        let response = service.import_ical(request).await.unwrap().into_inner();
+        assert_eq!(response.created, 3);
+
+        let import = IcalImportRequest {
+            dry_run: false,
+            ..import
+        };
+        let request = with_principal("john", import.clone());
+        let response = service.import_ical(request).await.unwrap().into_inner();
+        assert_eq!((response.created, response.conflicts), (3, 1));
+        assert!(response.results[0].reservation.as_ref().unwrap().id > 0);
+
+        // importing again conflicts with what was imported
+        let request = with_principal("john", import.clone());
+        let response = service.import_ical(request).await.unwrap().into_inner();
+        assert_eq!(response.conflicts, 4);
+
+        // users only import for themselves
+        let import = IcalImportRequest {
+            user_id: "lei".to_string(),
+            ..import
+        };
+        let request = with_principal("john", import);
+        let response = service.import_ical(request).await.unwrap().into_inner();
+        assert_eq!(response.skipped, 4);
+    }
+
+    #[tokio::test]
+    async fn rpc_confirm_should_work() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = service
+            .manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting",
+            ))
+            .await
+            .unwrap();
+
+        let request = tonic::Request::new(ConfirmRequest { id: reservation.id });
+        let response = service.confirm(request).await.unwrap();
+        let reservation1 = response.into_inner().reservation;
+
+        assert!(reservation1.is_some());
+
+        let reservation1 = reservation1.unwrap();
+
+        assert_eq!(reservation1.status, ReservationStatus::Confirmed as i32);
+    }
+
+    #[tokio::test]
+    async fn rpc_update_should_work() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = service
+            .manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting",
+            ))
             .await
             .unwrap();
 
@@ -272,7 +1848,7 @@ mod tests {
             .await
             .unwrap();
 
-        let request = tonic::Request::new(GetRequest { id: reservation.id });
+        let request = tonic::Request::new(GetRequest::new(reservation.id));
         let response = service.get(request).await.unwrap();
         let reservation1 = response.into_inner().reservation;
 
@@ -282,4 +1858,174 @@ mod tests {
 
         assert_eq!(reservation1.id, reservation.id);
     }
+
+    #[tokio::test]
+    async fn rpc_get_history_should_work() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = service
+            .manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting",
+            ))
+            .await
+            .unwrap();
+        service
+            .manager
+            .update_note(reservation.id, "moved to the afternoon".to_string())
+            .await
+            .unwrap();
+
+        let request = tonic::Request::new(GetHistoryRequest::new(reservation.id));
+        let versions = service
+            .get_history(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .versions;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].reservation, Some(reservation.clone()));
+
+        let request = tonic::Request::new(GetRequest {
+            id: reservation.id,
+            as_of: versions[0].changed_at.clone(),
+        });
+        let response = service.get(request).await.unwrap().into_inner();
+        assert_eq!(response.reservation.unwrap().note, reservation.note);
+    }
+
+    #[tokio::test]
+    async fn rpc_listen_should_stream_changes() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let mut request = tonic::Request::new(ListenRequest {
+            changed_fields: vec!["status".to_string()],
+            ..Default::default()
+        });
+        request.extensions_mut().insert(Principal::new("lei"));
+        let mut stream = service.listen(request).await.unwrap().into_inner();
+
+        let rsvp = service
+            .manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting",
+            ))
+            .await
+            .unwrap();
+        service
+            .manager
+            .update_note(rsvp.id, "moved to the afternoon".to_string())
+            .await
+            .unwrap();
+        service.manager.change_status(rsvp.id).await.unwrap();
+
+        let mut changes = Vec::new();
+        for _ in 0..2 {
+            let next = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next());
+            changes.push(next.await.unwrap().unwrap().unwrap());
+        }
+
+        let [created, confirmed] = changes.try_into().unwrap();
+        assert_eq!(created.op, abi::ReservationType::Create as i32);
+        // lei may not see who holds the reservation
+        assert_eq!(created.reservation.unwrap().user_id, "");
+
+        assert_eq!(confirmed.op, abi::ReservationType::Update as i32);
+        assert_eq!(confirmed.changed_fields, vec!["status".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn rpc_search_should_work() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = service
+            .manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "room_01",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "I need this room for a meeting with the CEO",
+            ))
+            .await
+            .unwrap();
+
+        let search = abi::ReservationSearchBuilder::default()
+            .query("ceo")
+            .build()
+            .unwrap();
+        let request = tonic::Request::new(SearchRequest::new(search));
+        let res = service.search(request).await.unwrap().into_inner();
+
+        assert_eq!(res.hits.len(), 1);
+        assert_eq!(res.hits[0].reservation, Some(reservation));
+        assert_eq!(res.pager.unwrap().total, 1);
+
+        let request = tonic::Request::new(SearchRequest { search: None });
+        let status = service.search(request).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn rpc_reserve_validate_only_should_not_create_reservation() {
+        let config = TestConfig::new();
+
+        let service = RsvpService::from_config(&config).await.unwrap();
+        let reservation = Reservation::new_pending(
+            "john",
+            "room_01",
+            "2022-12-26T15:00:00-0700".parse().unwrap(),
+            "2022-12-30T12:00:00-0700".parse().unwrap(),
+            "I need this room for a meeting",
+        );
+
+        let request = tonic::Request::new(ReserveRequest::new_validate_only(reservation.clone()));
+        let response = service.reserve(request).await.unwrap().into_inner();
+
+        assert_eq!(response.dry_run, Some(DryRunResult::ok()));
+        assert_eq!(response.reservation.unwrap().id, 0);
+
+        // nothing is written
+        let filter = abi::ReservationFilterBuilder::default().build().unwrap();
+        let (pager, _) = service.manager.filter(filter).await.unwrap();
+        assert_eq!(pager.total, 0);
+
+        // a conflict is described in the result
+        let existing = service.manager.reserve(reservation.clone()).await.unwrap();
+        let request = tonic::Request::new(ReserveRequest::new_validate_only(reservation.clone()));
+        let response = service.reserve(request).await.unwrap().into_inner();
+        let dry_run = response.dry_run.unwrap();
+
+        assert!(response.reservation.is_none());
+        assert!(!dry_run.ok);
+        assert_eq!(dry_run.reason, "RESERVATION_CONFLICT");
+        assert_eq!(dry_run.conflicts.len(), 1);
+        assert_eq!(dry_run.conflicts[0].id, existing.id);
+
+        // and so is a validation failure
+        let mut invalid = reservation;
+        invalid.user_id = String::new();
+        let request = tonic::Request::new(ReserveRequest::new_validate_only(invalid));
+        let dry_run = service
+            .reserve(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .dry_run
+            .unwrap();
+
+        assert_eq!(dry_run.reason, "INVALID_USER_ID");
+    }
 }
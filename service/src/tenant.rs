@@ -0,0 +1,101 @@
+use abi::Error;
+use reservation::DEFAULT_TENANT;
+use tonic::Request;
+
+use crate::Principal;
+
+/// header naming the tenant of a request, honoured only when authentication is disabled
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+const MAX_TENANT_LEN: usize = 64;
+
+/// the tenant a request works on: the tenant of the caller identity, or the one named by the
+/// `x-tenant-id` header when authentication is disabled. None if neither is given, the default
+/// tenant is used then. An authenticated caller may only name its own tenant, a caller without
+/// one works on the default tenant
+pub fn tenant_from_request<T>(request: &Request<T>) -> Result<Option<String>, Error> {
+    let header = match request.metadata().get(TENANT_HEADER) {
+        Some(value) => {
+            let tenant = value
+                .to_str()
+                .map_err(|_| Error::InvalidTenant(format!("{:?}", value)))?;
+            if tenant.is_empty() || tenant.len() > MAX_TENANT_LEN {
+                return Err(Error::InvalidTenant(tenant.to_string()));
+            }
+            Some(tenant.to_string())
+        }
+        None => None,
+    };
+
+    let Some(principal) = request.extensions().get::<Principal>() else {
+        return Ok(header);
+    };
+
+    match (&principal.tenant, header) {
+        (identity, Some(header)) if identity.as_ref() != Some(&header) => {
+            Err(Error::PermissionDenied(format!(
+                "{} of tenant {} can not access tenant {}",
+                principal.user_id,
+                identity.as_deref().unwrap_or(DEFAULT_TENANT),
+                header
+            )))
+        }
+        (identity, _) => Ok(identity.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(principal: Option<Principal>, header: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(principal) = principal {
+            request.extensions_mut().insert(principal);
+        }
+        if let Some(header) = header {
+            request
+                .metadata_mut()
+                .insert(TENANT_HEADER, header.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn tenant_should_come_from_identity_or_header() {
+        let acme = Principal::new("john").with_tenant("acme");
+
+        assert_eq!(tenant_from_request(&request(None, None)), Ok(None));
+        assert_eq!(
+            tenant_from_request(&request(None, Some("globex"))),
+            Ok(Some("globex".to_string()))
+        );
+        assert_eq!(
+            tenant_from_request(&request(Some(acme.clone()), None)),
+            Ok(Some("acme".to_string()))
+        );
+        assert_eq!(
+            tenant_from_request(&request(Some(acme.clone()), Some("acme"))),
+            Ok(Some("acme".to_string()))
+        );
+        assert!(matches!(
+            tenant_from_request(&request(Some(acme), Some("globex"))),
+            Err(Error::PermissionDenied(_))
+        ));
+
+        // an authenticated caller without a tenant can't pick one
+        let john = Principal::new("john");
+        assert_eq!(
+            tenant_from_request(&request(Some(john.clone()), None)),
+            Ok(None)
+        );
+        assert!(matches!(
+            tenant_from_request(&request(Some(john), Some("globex"))),
+            Err(Error::PermissionDenied(_))
+        ));
+        assert_eq!(
+            tenant_from_request(&request(None, Some(""))),
+            Err(Error::InvalidTenant("".to_string()))
+        );
+    }
+}
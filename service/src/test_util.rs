@@ -1,4 +1,5 @@
 use abi::Config;
+use reservation_service::Principal;
 use sqlx::{Connection, Executor, PgConnection};
 use std::{ops::Deref, thread};
 use tokio::runtime::Runtime;
@@ -59,6 +60,14 @@ impl TestConfig {
     }
 }
 
+/// a request made by the given user, as the authenticator would leave it
+#[allow(dead_code)]
+pub fn with_principal<T>(user_id: &str, msg: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(msg);
+    request.extensions_mut().insert(Principal::new(user_id));
+    request
+}
+
 impl Default for TestConfig {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::{AUDIT_REASON_HEADER, REQUEST_ID_HEADER, TENANT_HEADER};
+
+/// how long browsers may cache a preflight response
+const CORS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// headers of gRPC-Web clients besides the ones of our own requests
+const GRPC_WEB_HEADERS: [&str; 3] = ["x-grpc-web", "x-user-agent", "grpc-timeout"];
+/// trailers gRPC-Web clients read the status of a call from
+const GRPC_WEB_EXPOSED_HEADERS: [&str; 3] =
+    ["grpc-status", "grpc-message", "grpc-status-details-bin"];
+
+/// CORS for the browser facing endpoints (gRPC-Web and the REST gateway), None if no
+/// origin is allowed. "*" allows any origin, invalid origins are an error
+pub fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>, anyhow::Error> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = match origins.iter().any(|origin| origin == "*") {
+        true => AllowOrigin::any(),
+        false => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|origin| origin.parse::<HeaderValue>())
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+    };
+
+    let allow_headers = [
+        header::AUTHORIZATION.as_str(),
+        header::CONTENT_TYPE.as_str(),
+        TENANT_HEADER,
+        REQUEST_ID_HEADER,
+        AUDIT_REASON_HEADER,
+    ]
+    .into_iter()
+    .chain(GRPC_WEB_HEADERS)
+    .map(HeaderName::from_static)
+    .collect::<Vec<_>>();
+
+    let cors = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers(allow_headers)
+        .expose_headers(GRPC_WEB_EXPOSED_HEADERS.map(HeaderName::from_static))
+        .max_age(CORS_MAX_AGE);
+
+    Ok(Some(cors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cors_layer_should_need_valid_origins() {
+        assert!(cors_layer(&[]).unwrap().is_none());
+        assert!(cors_layer(&["*".to_string()]).unwrap().is_some());
+        assert!(cors_layer(&["https://app.example.com".to_string()])
+            .unwrap()
+            .is_some());
+        assert!(cors_layer(&["https://app.example.com\n".to_string()]).is_err());
+    }
+}
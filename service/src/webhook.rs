@@ -0,0 +1,457 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use abi::WebhookConfig;
+use chrono::{DateTime, Utc};
+use futures::future;
+use hmac::{Hmac, Mac};
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Resolve, Resolving},
+    redirect,
+};
+use reservation::{Delivery, ReservationManager};
+use sha2::Sha256;
+use url::{Host, Url};
+
+/// header signing the payload: `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`
+pub const SIGNATURE_HEADER: &str = "x-rsvp-signature";
+/// header carrying the sequence number of the change, receivers drop the duplicates with it
+pub const SEQUENCE_HEADER: &str = "x-rsvp-sequence";
+
+/// deliveries attempted at once per tenant
+const DELIVERY_BATCH: i64 = 100;
+
+/// the signature header of the body, receivers compute it with the shared secret to verify it
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// where webhooks may be delivered. Loopback, link-local and private addresses are refused
+/// unless the config allows them, so a webhook can't be pointed at the internal network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AddressPolicy {
+    pub allow_private: bool,
+}
+
+impl AddressPolicy {
+    pub fn new(config: &WebhookConfig) -> Self {
+        Self {
+            allow_private: config.allow_private_urls,
+        }
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.allow_private || is_public(ip)
+    }
+
+    /// check the host of a webhook url if it's an address or localhost, the addresses of the
+    /// other hosts are checked when they are resolved
+    pub fn check_url(&self, url: &str) -> Result<(), abi::Error> {
+        let invalid = |reason: &str| abi::Error::InvalidWebhook(format!("{}: {}", reason, url));
+
+        let url = Url::parse(url).map_err(|_| invalid("invalid url"))?;
+        let allowed = match url.host() {
+            Some(Host::Ipv4(ip)) => self.allows(ip.into()),
+            Some(Host::Ipv6(ip)) => self.allows(ip.into()),
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                self.allow_private || !(domain == "localhost" || domain.ends_with(".localhost"))
+            }
+            None => return Err(invalid("url has no host")),
+        };
+
+        match allowed {
+            true => Ok(()),
+            false => Err(invalid("url must not be a private address")),
+        }
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    // 100.64.0.0/10 is shared by the carrier grade NATs
+    let shared = a == 100 && (b & 0xc0) == 64;
+    // 198.18.0.0/15 is for benchmarks, 192.0.0.0/24 for protocol assignments
+    let benchmarking = a == 198 && (b & 0xfe) == 18;
+    let protocol = a == 192 && b == 0 && c == 0;
+    // 240.0.0.0/4 is reserved, broadcast included
+    let reserved = a >= 240;
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || shared
+        || benchmarking
+        || protocol
+        || reserved
+        || a == 0)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let first = segments[0];
+    // fc00::/7 unique local, fe80::/10 link-local
+    let unique_local = (first & 0xfe00) == 0xfc00;
+    let link_local = (first & 0xffc0) == 0xfe80;
+
+    // 64:ff9b::/96 (NAT64) and 2002::/16 (6to4) reach the IPv4 address they embed
+    let octets = ip.octets();
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return is_public_v4(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ));
+    }
+    if first == 0x2002 {
+        return is_public_v4(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]));
+    }
+
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local)
+}
+
+/// resolves the hosts of the webhooks to the addresses the policy allows
+struct PolicyResolver(AddressPolicy);
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.0;
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<_> = tokio::net::lookup_host((host, 0))
+                .await?
+                .filter(|addr| policy.allows(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                let message = format!("{} has no address webhooks may be delivered to", host);
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, message).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as _)
+        })
+    }
+}
+
+/// POSTs the changes queued in the outbox to the webhooks, failed deliveries are retried with
+/// exponential backoff until they are given up on
+#[derive(Debug, Clone)]
+pub struct WebhookWorker {
+    manager: ReservationManager,
+    client: reqwest::Client,
+    config: WebhookConfig,
+    policy: AddressPolicy,
+}
+
+impl WebhookWorker {
+    pub fn new(manager: ReservationManager, config: WebhookConfig) -> Result<Self, anyhow::Error> {
+        let policy = AddressPolicy::new(&config);
+        // a redirect could lead anywhere, the receiver must answer itself
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .redirect(redirect::Policy::none())
+            .dns_resolver(Arc::new(PolicyResolver(policy)))
+            .build()?;
+
+        Ok(Self {
+            manager,
+            client,
+            config,
+            policy,
+        })
+    }
+
+    /// deliver until the task is dropped
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                eprintln!("webhook delivery failed: {}", e);
+            }
+
+            tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+    }
+
+    /// queue the new changes and attempt the due deliveries of every tenant once.
+    /// Returns the number of attempts made
+    pub async fn run_once(&self) -> Result<usize, abi::Error> {
+        // a claimed delivery is attempted again if the worker dies before it is recorded
+        let lease = Duration::from_millis(self.config.timeout_ms * 2);
+        let mut attempted = 0;
+
+        for tenant in self.manager.webhook_tenants().await? {
+            let manager = self.manager.with_tenant(tenant);
+            manager.enqueue_deliveries().await?;
+
+            let deliveries = manager.claim_deliveries(DELIVERY_BATCH, lease).await?;
+            let results = future::join_all(deliveries.iter().map(|d| self.deliver(d))).await;
+            attempted += deliveries.len();
+
+            for (delivery, result) in deliveries.iter().zip(results) {
+                match result {
+                    Ok(()) => manager.complete_delivery(delivery.id).await?,
+                    Err(e) => {
+                        let retry_at = self.retry_at(delivery.attempts);
+                        manager.fail_delivery(delivery.id, &e, retry_at).await?
+                    }
+                }
+            }
+        }
+
+        Ok(attempted)
+    }
+
+    async fn deliver(&self, delivery: &Delivery) -> Result<(), String> {
+        // addresses in the url are not resolved, check them before connecting
+        self.policy
+            .check_url(&delivery.url)
+            .map_err(|e| e.to_string())?;
+
+        let body = delivery.payload.to_string();
+        let signature = sign(&delivery.secret, Utc::now().timestamp(), &body);
+
+        let response = self
+            .client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(SEQUENCE_HEADER, delivery.sequence)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(response.status().to_string())
+        }
+    }
+
+    /// when to try again after the given number of attempts, None to give up
+    fn retry_at(&self, attempts: i32) -> Option<DateTime<Utc>> {
+        if attempts >= self.config.max_attempts {
+            return None;
+        }
+
+        let exponent = attempts.clamp(1, 32) as u32 - 1;
+        let backoff = self
+            .config
+            .initial_backoff_ms
+            .saturating_mul(1 << exponent)
+            .min(self.config.max_backoff_ms);
+
+        Some(Utc::now() + chrono::Duration::milliseconds(backoff as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use abi::{Reservation, ReservationType, Webhook};
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use reservation::Rsvp;
+
+    use super::*;
+    use crate::{test_util::TestConfig, RsvpService};
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    /// the receiver is down the first time it is called
+    async fn receive(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let mut received = received.lock().unwrap();
+        received.push((headers, body));
+
+        match received.len() {
+            1 => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::OK,
+        }
+    }
+
+    #[test]
+    fn sign_should_work() {
+        assert_eq!(
+            sign("s3cret", 1700000000, r#"{"sequence":1}"#),
+            sign("s3cret", 1700000000, r#"{"sequence":1}"#)
+        );
+        assert_ne!(
+            sign("s3cret", 1700000000, r#"{"sequence":1}"#),
+            sign("s3cret", 1700000001, r#"{"sequence":1}"#)
+        );
+        assert_ne!(
+            sign("s3cret", 1700000000, r#"{"sequence":1}"#),
+            sign("other", 1700000000, r#"{"sequence":1}"#)
+        );
+        assert!(sign("s3cret", 1700000000, "").starts_with("t=1700000000,v1="));
+    }
+
+    #[test]
+    fn address_policy_should_refuse_private_addresses() {
+        let policy = AddressPolicy::default();
+
+        for url in [
+            "http://127.0.0.1:8080/hooks",
+            "http://localhost/hooks",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.1/hooks",
+            "http://192.168.1.1/hooks",
+            "http://100.64.0.1/hooks",
+            "http://[::1]/hooks",
+            "http://[fd00::1]/hooks",
+            "http://[::ffff:127.0.0.1]/hooks",
+            "http://224.0.0.1/hooks",
+            "http://198.18.0.1/hooks",
+            "http://192.0.0.1/hooks",
+            "http://240.0.0.1/hooks",
+            "http://[ff02::1]/hooks",
+            "http://[64:ff9b::a00:1]/hooks",
+            "http://[2002:a9fe:a9fe::1]/hooks",
+        ] {
+            assert!(policy.check_url(url).is_err(), "{} is private", url);
+        }
+        for url in [
+            "https://example.com/hooks",
+            "http://93.184.216.34/hooks",
+            "http://[64:ff9b::5db8:d822]/hooks",
+            "http://[2002:5db8:d822::1]/hooks",
+        ] {
+            assert_eq!(policy.check_url(url), Ok(()));
+        }
+
+        let policy = AddressPolicy {
+            allow_private: true,
+        };
+        assert_eq!(policy.check_url("http://127.0.0.1:8080/hooks"), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn resolver_should_refuse_private_addresses() {
+        let name: Name = "localhost".parse().unwrap();
+
+        let resolver = PolicyResolver(AddressPolicy::default());
+        assert!(resolver.resolve(name.clone()).await.is_err());
+
+        let resolver = PolicyResolver(AddressPolicy {
+            allow_private: true,
+        });
+        let addrs: Vec<_> = resolver.resolve(name).await.unwrap().collect();
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+    }
+
+    #[tokio::test]
+    async fn worker_should_retry_until_delivered() {
+        let mut config = TestConfig::new();
+        config.config.webhook.initial_backoff_ms = 0;
+        config.config.webhook.allow_private_urls = true;
+        let manager = RsvpService::from_config(&config).await.unwrap().manager;
+
+        let received = Received::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hooks", post(receive))
+            .with_state(received.clone());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let worker = WebhookWorker::new(manager.clone(), config.webhook.clone()).unwrap();
+        manager
+            .create_webhook(Webhook {
+                url: format!("http://{}/hooks", addr),
+                event_types: vec![ReservationType::Create as i32],
+                secret: "s3cret".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let rsvp = manager
+            .reserve(Reservation::new_pending(
+                "john",
+                "ocean-view-room-713",
+                "2022-12-26T15:00:00-0700".parse().unwrap(),
+                "2022-12-30T12:00:00-0700".parse().unwrap(),
+                "",
+            ))
+            .await
+            .unwrap();
+
+        // failed, then retried right away as there is no backoff
+        assert_eq!(worker.run_once().await.unwrap(), 1);
+        assert_eq!(worker.run_once().await.unwrap(), 1);
+        assert_eq!(worker.run_once().await.unwrap(), 0);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(received[0].1, *body);
+
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let timestamp = signature[2..signature.find(',').unwrap()].parse().unwrap();
+        assert_eq!(signature, sign("s3cret", timestamp, body));
+
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["type"], "reservation.created");
+        assert_eq!(payload["reservation"]["id"], rsvp.id);
+        assert_eq!(
+            headers[SEQUENCE_HEADER].to_str().unwrap(),
+            payload["sequence"].to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_should_back_off_exponentially() {
+        let config = TestConfig::new();
+        let manager = RsvpService::from_config(&config).await.unwrap().manager;
+        let worker = WebhookWorker::new(
+            manager,
+            WebhookConfig {
+                initial_backoff_ms: 1000,
+                max_backoff_ms: 5000,
+                max_attempts: 5,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let delay = |attempts| {
+            worker
+                .retry_at(attempts)
+                .map(|at| (at - Utc::now()).num_milliseconds())
+        };
+        assert!((900..=1000).contains(&delay(1).unwrap()));
+        assert!((3900..=4000).contains(&delay(3).unwrap()));
+        assert!((4900..=5000).contains(&delay(4).unwrap()));
+        assert_eq!(delay(5), None);
+    }
+}
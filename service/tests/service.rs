@@ -1,19 +1,33 @@
 #[path = "../src/test_util.rs"]
 mod test_utils;
 
-use std::{sync::Once, time::Duration};
+use prost::Message;
+use serde_json::{json, Value};
+use sqlx::{Connection, PgConnection};
+use std::{
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use tokio::time;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+use tonic_health::pb::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+use tonic_reflection::pb::{
+    server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse, ServerReflectionRequest,
+};
 
 use abi::{
-    Config, ConfirmRequest, FilterRequest, FilterResponse, QueryRequest, Reservation,
+    AuthConfig, Config, ConfirmRequest, FilterRequest, FilterResponse, JwtAlgorithm, JwtConfig,
+    ListenRequest, ListenResponse, PolicyConfig, QueryRequest, Reservation,
     ReservationFilterBuilder, ReservationQueryBuilder, ReservationStatus, ReserveRequest,
+    StatusDetails, TlsConfig,
 };
 
 use test_utils::TestConfig;
 
-use reservation_service::start_server;
-
-static START: Once = Once::new();
+use reservation_service::{start_server, Claims};
 
 #[tokio::test]
 async fn grpc_server_should_work() {
@@ -45,7 +59,15 @@ async fn grpc_server_should_work() {
         .reservation
         .unwrap();
 
-    assert_eq!(ret, rsvp);
+    // the reservation is created in the default tenant
+    assert_eq!(ret.tenant_id, "default");
+    assert_eq!(
+        ret,
+        Reservation {
+            tenant_id: "default".to_string(),
+            ..rsvp
+        }
+    );
 
     // test conflict reservation
     let rsvp = Reservation::new_pending(
@@ -62,6 +84,19 @@ async fn grpc_server_should_work() {
 
     assert!(ret2.is_err());
 
+    // conflict details are decoded from the status, no need to parse the message
+    let status = ret2.unwrap_err();
+    let details = StatusDetails::from_status(&status);
+
+    assert_eq!(status.code(), tonic::Code::AlreadyExists);
+    assert_eq!(details.reason(), Some("RESERVATION_CONFLICT"));
+    assert_eq!(details.conflicts.len(), 1);
+    assert_eq!(details.conflicts[0].id, ret.id);
+    assert_eq!(details.conflicts[0].user_id, "john");
+    assert_eq!(details.conflicts[0].resource_id, "room_01");
+    assert_eq!(details.conflicts[0].start, ret.start);
+    assert_eq!(details.conflicts[0].end, ret.end);
+
     // test confirm reservation
     let request = tonic::Request::new(ConfirmRequest::new(ret.id));
 
@@ -128,8 +163,8 @@ async fn grpc_query_should_work() {
     .await
     .unwrap();
 
-    // insert 100 reservations
-    for i in 0..100 {
+    // insert more reservations than the query channel can buffer
+    for i in 0..200 {
         let rsvp = Reservation::new_pending(
             "john",
             format!("house_{}", i),
@@ -151,6 +186,7 @@ async fn grpc_query_should_work() {
 
     let query = ReservationQueryBuilder::default()
         .user_id("john")
+        .page_size(200)
         .build()
         .unwrap();
 
@@ -158,16 +194,505 @@ async fn grpc_query_should_work() {
 
     let mut ret = client.query(request).await.unwrap().into_inner();
 
+    let mut count = 0;
     while let Some(reservation) = ret.message().await.unwrap() {
         assert_eq!(reservation.user_id, "john");
+        count += 1;
+    }
+
+    assert_eq!(count, 200);
+}
+
+#[tokio::test]
+async fn grpc_jwt_auth_should_work() {
+    let mut config = TestConfig::with_server_port(50002);
+    config.config.auth = Some(AuthConfig {
+        jwt: Some(JwtConfig {
+            algorithm: JwtAlgorithm::HS256,
+            secret: Some("reservation-secret".to_string()),
+            public_key: None,
+            issuer: None,
+            audience: None,
+        }),
+        mtls: false,
+        policy: Default::default(),
+    });
+    start_service(config.config.clone()).await;
+
+    let mut client = abi::reservation_service_client::ReservationServiceClient::connect(
+        config.server.url(false),
+    )
+    .await
+    .unwrap();
+
+    let rsvp = Reservation::new_pending(
+        "",
+        "room_01",
+        "2022-12-26T15:00:00-0700".parse().unwrap(),
+        "2022-12-30T12:00:00-0700".parse().unwrap(),
+        "I need this room for a meeting",
+    );
+
+    // no token
+    let status = client
+        .reserve(tonic::Request::new(ReserveRequest::new(rsvp.clone())))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    // the user id defaults to the subject of the token
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let claims = Claims {
+        sub: "john".to_string(),
+        exp,
+        tenant: None,
+    };
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(b"reservation-secret"),
+    )
+    .unwrap();
+
+    let mut request = tonic::Request::new(ReserveRequest::new(rsvp.clone()));
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    let ret = client
+        .reserve(request)
+        .await
+        .unwrap()
+        .into_inner()
+        .reservation
+        .unwrap();
+    assert_eq!(ret.user_id, "john");
+
+    // but can't be someone else
+    let mut rsvp = rsvp;
+    rsvp.user_id = "lei".to_string();
+    rsvp.resource_id = "room_02".to_string();
+    let mut request = tonic::Request::new(ReserveRequest::new(rsvp));
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    let status = client.reserve(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(
+        StatusDetails::from_status(&status).reason(),
+        Some("PERMISSION_DENIED")
+    );
+}
+
+#[tokio::test]
+async fn grpc_mtls_auth_should_work() {
+    let mut config = TestConfig::with_server_port(50003);
+    config.config.server.http_port = Some(50008);
+    config.config.server.tls = Some(TlsConfig {
+        cert: "fixtures/tls/server.pem".to_string(),
+        key: "fixtures/tls/server.key".to_string(),
+        client_ca: Some("fixtures/tls/ca.pem".to_string()),
+    });
+    config.config.auth = Some(AuthConfig {
+        jwt: None,
+        mtls: true,
+        policy: Default::default(),
+    });
+    start_service(config.config.clone()).await;
+
+    let tls = ClientTlsConfig::new()
+        .domain_name("localhost")
+        .ca_certificate(Certificate::from_pem(
+            fs::read("fixtures/tls/ca.pem").unwrap(),
+        ))
+        .identity(Identity::from_pem(
+            fs::read("fixtures/tls/client.pem").unwrap(),
+            fs::read("fixtures/tls/client.key").unwrap(),
+        ));
+    let channel = Channel::from_shared(config.server.url(true))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = abi::reservation_service_client::ReservationServiceClient::new(channel);
+
+    let rsvp = Reservation::new_pending(
+        "",
+        "room_01",
+        "2022-12-26T15:00:00-0700".parse().unwrap(),
+        "2022-12-30T12:00:00-0700".parse().unwrap(),
+        "I need this room for a meeting",
+    );
+
+    // the common name of the client certificate is the user id
+    let ret = client
+        .reserve(tonic::Request::new(ReserveRequest::new(rsvp)))
+        .await
+        .unwrap()
+        .into_inner()
+        .reservation
+        .unwrap();
+    assert_eq!(ret.user_id, "john");
+
+    // the REST gateway is served with the same identity, never in the clear
+    let ca = reqwest::Certificate::from_pem(&fs::read("fixtures/tls/ca.pem").unwrap()).unwrap();
+    let https = reqwest::Client::builder()
+        .add_root_certificate(ca)
+        .build()
+        .unwrap();
+    let response = https
+        .get(format!("https://localhost:50008/reservations/{}", ret.id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let plaintext = reqwest::get(format!("http://localhost:50008/reservations/{}", ret.id)).await;
+    assert!(plaintext.is_err());
+}
+
+#[tokio::test]
+async fn rest_gateway_should_work() {
+    let mut config = TestConfig::with_server_port(50004);
+    config.config.server.http_port = Some(50005);
+    config.config.auth = Some(AuthConfig {
+        jwt: Some(JwtConfig {
+            algorithm: JwtAlgorithm::HS256,
+            secret: Some("reservation-secret".to_string()),
+            public_key: None,
+            issuer: None,
+            audience: None,
+        }),
+        mtls: false,
+        policy: PolicyConfig {
+            resource_managers: [("room_01".to_string(), vec!["bob".to_string()])].into(),
+            ..Default::default()
+        },
+    });
+    start_service(config.config.clone()).await;
+
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let token = |sub: &str| {
+        let claims = Claims {
+            sub: sub.to_string(),
+            exp,
+            tenant: None,
+        };
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"reservation-secret"),
+        )
+        .unwrap()
+    };
+    let (john, bob) = (token("john"), token("bob"));
+
+    let url = "http://127.0.0.1:50005/reservations";
+    let client = reqwest::Client::new();
+    let send = |request: reqwest::RequestBuilder| async {
+        let response = request.bearer_auth(&john).send().await.unwrap();
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap();
+        (
+            status,
+            serde_json::from_str(&body).unwrap_or(Value::String(body)),
+        )
+    };
+    let rsvp = json!({
+        "resourceId": "room_01",
+        "start": "2022-12-26T15:00:00-07:00",
+        "end": "2022-12-30T12:00:00-07:00",
+        "note": "I need this room for a meeting",
+    });
+
+    // the caller is authenticated like a gRPC one
+    let response = client
+        .post(url)
+        .body(rsvp.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let (status, created) = send(client.post(url).body(rsvp.to_string())).await;
+    assert_eq!(status, 201);
+    assert_eq!(created["userId"], "john");
+    assert_eq!(created["status"], "RESERVATION_STATUS_PENDING");
+    assert_eq!(created["start"], "2022-12-26T22:00:00Z");
+    let id = created["id"].as_str().unwrap().to_string();
+
+    let (status, body) = send(client.post(url).body(rsvp.to_string())).await;
+    assert_eq!(status, 409);
+    assert_eq!(body["error"]["reason"], "RESERVATION_CONFLICT");
+    assert_eq!(body["error"]["conflicts"][0]["id"], id.as_str());
+
+    let (status, body) = send(client.post(url).body("{")).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["reason"], "INVALID_JSON");
+
+    let (status, body) = send(client.get(format!("{}/{}", url, id))).await;
+    assert_eq!((status, &body), (200, &created));
+
+    let patch = json!({ "note": "late arrival" });
+    let (status, body) = send(
+        client
+            .patch(format!("{}/{}", url, id))
+            .body(patch.to_string()),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["note"], "late arrival");
+
+    // only the manager of the room confirms
+    let patch = json!({ "status": "RESERVATION_STATUS_CONFIRMED" }).to_string();
+    let (status, body) = send(client.patch(format!("{}/{}", url, id)).body(patch.clone())).await;
+    assert_eq!(status, 403);
+    assert_eq!(body["error"]["reason"], "PERMISSION_DENIED");
+    let response = client
+        .patch(format!("{}/{}", url, id))
+        .bearer_auth(&bob)
+        .body(patch)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["status"], "RESERVATION_STATUS_CONFIRMED");
+
+    let (status, body) = send(client.get(format!("{}?userId=john&status=confirmed", url))).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["reservations"][0]["id"], id.as_str());
+    assert_eq!(body["pager"]["total"], "1");
+
+    let window = "status=confirmed&start=2022-12-01T00:00:00Z&end=2023-01-01T00:00:00Z";
+    let (status, body) = send(client.get(format!("{}?user_id=john&{}", url, window))).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["reservations"].as_array().unwrap().len(), 1);
+
+    let response = client
+        .get(format!("{}.ics?userId=john&{}", url, window))
+        .bearer_auth(&john)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/calendar; charset=utf-8"
+    );
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .starts_with("BEGIN:VCALENDAR"));
+
+    let (status, _) = send(client.delete(format!("{}/{}", url, id))).await;
+    assert_eq!(status, 200);
+    let (status, body) = send(client.get(format!("{}/{}", url, id))).await;
+    assert_eq!(status, 404);
+    assert_eq!(body["error"]["reason"], "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn grpc_web_should_work() {
+    let mut config = TestConfig::with_server_port(50006);
+    config.config.server.grpc_web = true;
+    config.config.server.cors_origins = vec!["https://app.example.com".to_string()];
+    let config_clone = config.clone();
+    start_service(config_clone).await;
+
+    let url = format!("http://127.0.0.1:{}/reservation.ReservationService", 50006);
+    let client = reqwest::Client::new();
+    let call = |method: &str, message: Vec<u8>| {
+        client
+            .post(format!("{}/{}", url, method))
+            .header("content-type", "application/grpc-web+proto")
+            .header("x-grpc-web", "1")
+            .header("origin", "https://app.example.com")
+            .body(grpc_web_frame(0, &message))
+            .send()
+    };
+
+    // preflight of an allowed origin is answered, the others are not
+    let preflight = |origin: &'static str| {
+        client
+            .request(reqwest::Method::OPTIONS, format!("{}/query", url))
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type,x-grpc-web")
+            .send()
+    };
+    let res = preflight("https://app.example.com").await.unwrap();
+    assert_eq!(
+        res.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+    let res = preflight("https://evil.example.com").await.unwrap();
+    assert!(res.headers().get("access-control-allow-origin").is_none());
+
+    // listen over HTTP/1.1 before the reservation is made
+    let mut changes = call("listen", ListenRequest::default().encode_to_vec())
+        .await
+        .unwrap();
+    assert_eq!(changes.status(), 200);
+    assert_eq!(
+        changes.headers()["access-control-allow-origin"],
+        "https://app.example.com"
+    );
+
+    let rsvp = Reservation::new_pending(
+        "john",
+        "room_web",
+        "2022-12-26T15:00:00-0700".parse().unwrap(),
+        "2022-12-30T12:00:00-0700".parse().unwrap(),
+        "web",
+    );
+    let res = call("reserve", ReserveRequest::new(rsvp).encode_to_vec())
+        .await
+        .unwrap();
+    let frames = grpc_web_frames(&res.bytes().await.unwrap());
+    assert_eq!(frames.len(), 2);
+    assert!(String::from_utf8_lossy(&frames[1].1).contains("grpc-status:0"));
+
+    let mut buf = Vec::new();
+    let change = loop {
+        let chunk = time::timeout(Duration::from_secs(5), changes.chunk())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        buf.extend_from_slice(&chunk);
+        if let Some((0, data)) = grpc_web_frames(&buf).first() {
+            break ListenResponse::decode(data.as_slice()).unwrap();
+        }
+    };
+    assert_eq!(change.reservation.unwrap().resource_id, "room_web");
+
+    // server streaming query ends with the status in the trailer frame
+    let query = ReservationQueryBuilder::default()
+        .user_id("john")
+        .build()
+        .unwrap();
+    let res = call("query", QueryRequest::new(query).encode_to_vec())
+        .await
+        .unwrap();
+    let frames = grpc_web_frames(&res.bytes().await.unwrap());
+    assert_eq!(frames.len(), 2);
+    let rsvp = Reservation::decode(frames[0].1.as_slice()).unwrap();
+    assert_eq!(rsvp.resource_id, "room_web");
+    assert_eq!(frames[1].0, 0x80);
+    assert!(String::from_utf8_lossy(&frames[1].1).contains("grpc-status:0"));
+}
+
+#[tokio::test]
+async fn health_and_reflection_should_work() {
+    let config = TestConfig::with_server_port(50007);
+    let config_clone = config.clone();
+    start_service(config_clone).await;
+
+    let channel = Channel::from_shared(config.server.url(false))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let mut health = HealthClient::new(channel.clone());
+    let check = |service: &str| HealthCheckRequest {
+        service: service.to_string(),
+    };
+    for service in ["", "reservation.ReservationService"] {
+        let res = health.check(check(service)).await.unwrap().into_inner();
+        assert_eq!(res.status(), ServingStatus::Serving);
+    }
+    let status = health.check(check("nothing.Unknown")).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let mut watch = health.watch(check("")).await.unwrap().into_inner();
+    let res = watch.message().await.unwrap().unwrap();
+    assert_eq!(res.status(), ServingStatus::Serving);
+
+    let mut reflection = ServerReflectionClient::new(channel);
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut res = reflection
+        .server_reflection_info(futures::stream::iter(vec![request]))
+        .await
+        .unwrap()
+        .into_inner();
+    let Some(MessageResponse::ListServicesResponse(list)) =
+        res.message().await.unwrap().unwrap().message_response
+    else {
+        panic!("expected the services");
+    };
+    let names: Vec<_> = list.service.into_iter().map(|s| s.name).collect();
+    assert!(names.contains(&"reservation.ReservationService".to_string()));
+    assert!(names.contains(&"grpc.health.v1.Health".to_string()));
+    assert!(names.contains(&"grpc.reflection.v1alpha.ServerReflection".to_string()));
+
+    // a database missing a migration of this build is not serving
+    let mut conn = PgConnection::connect(&config.db.url()).await.unwrap();
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT max(version) FROM _sqlx_migrations)",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+    // the status is checked again after a while
+    let not_serving = async {
+        while let Some(res) = watch.message().await.unwrap() {
+            if res.status() == ServingStatus::NotServing {
+                break;
+            }
+        }
+    };
+    time::timeout(Duration::from_secs(10), not_serving)
+        .await
+        .unwrap();
+    let res = health
+        .check(check("reservation.ReservationService"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(res.status(), ServingStatus::NotServing);
+}
+
+fn grpc_web_frame(flag: u8, data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![flag];
+    frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// complete (flag, data) frames of a gRPC-Web body
+fn grpc_web_frames(mut body: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut frames = Vec::new();
+    while body.len() >= 5 {
+        let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+        if body.len() < 5 + len {
+            break;
+        }
+        frames.push((body[0], body[5..5 + len].to_vec()));
+        body = &body[5 + len..];
     }
+    frames
 }
 
 async fn start_service(config: Config) {
-    START.call_once(|| {
-        tokio::spawn(async move {
-            start_server(&config).await.unwrap();
-        });
+    // every test owns its database and port, so every test starts its own server
+    tokio::spawn(async move {
+        start_server(&config).await.unwrap();
     });
 
     time::sleep(Duration::from_millis(500)).await;